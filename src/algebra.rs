@@ -2,6 +2,7 @@ use crate::expression::{Address, Context, Expression, ExpressionType, Expression
 use crate::worksheet::{Action, WorkableExpressionSequence, WorksheetContext};
 use crate::arithmetic::{ArithmeticOperator, ArithmeticError};
 use crate::utils::gcd;
+use num_bigint::BigInt;
 use crate::{address, parser::parser};
 use std::cmp::{min,max};
 use lazy_static::lazy_static;
@@ -23,6 +24,9 @@ pub enum AlgebraError {
     FunctionApplicationError,
     NotAFunction,
     NotAFraction,
+    NotLinear,
+    NoUniqueSolution,
+    StepLimitReached,
 }
 
 impl From<ExpressionError> for AlgebraError {
@@ -112,21 +116,9 @@ impl Expression {
             eb::variable("X"));
         let rule_exprs = vec![add_zero, zero_add, mul_one, one_mul, mul_zero, zero_mul, div_one, sub_zero];
         
-        let mut prev_expr = Expression::default();
-        let mut expr = self.clone();
-        while expr != prev_expr {
-            prev_expr = expr.clone();
-            for e in &rule_exprs {
-                let possible_eq_addr = expr.get_possible_equation_application_addresses(e);
-                if possible_eq_addr.is_empty() { continue; }
-                let addr = possible_eq_addr.first()
-                    .expect("Expression::get_possible_equation_application_address is valid");
-                if let Ok(new_expr) = expr.apply_equation_at(e, addr) {
-                    expr = new_expr;
-                }
-            }
-        }
-        return expr
+        // every equation removes nodes, so every pass but the last one makes the expression smaller
+        return self.rewrite_to_fixpoint_with_limit(&rule_exprs, self.size() + 1)
+            .expect("the simplification equations terminate")
             .normalize_two_children_assoc_train_to_binary_op(&ctx.binary_ops)
             .normalize_single_children_assoc_train()
     }
@@ -175,11 +167,11 @@ impl Expression {
         if !denominator_selected_expr.is_numeric() { return Err(ArithmeticError::NotNumeric.into()); }
         let both_integer = numerator_selected_expr.is_integer() && denominator_selected_expr.is_integer();
         let (new_num_sym, new_den_sym) = if both_integer {
-            let num_val = numerator_selected_expr.symbol.parse::<i64>().map_err(|_| ArithmeticError::NotNumeric)?;
-            let den_val = denominator_selected_expr.symbol.parse::<i64>().map_err(|_| ArithmeticError::NotNumeric)?;
-            let gcd = gcd(num_val, den_val);
-            let new_num_val = num_val / gcd;
-            let new_den_val = den_val / gcd;
+            let num_val = numerator_selected_expr.symbol.parse::<BigInt>().map_err(|_| ArithmeticError::NotNumeric)?;
+            let den_val = denominator_selected_expr.symbol.parse::<BigInt>().map_err(|_| ArithmeticError::NotNumeric)?;
+            let gcd = gcd(&num_val, &den_val);
+            let new_num_val = num_val / &gcd;
+            let new_den_val = den_val / &gcd;
             (new_num_val.to_string(), new_den_val.to_string())
         } else {
             let num_val = numerator_selected_expr.symbol.parse::<f64>().map_err(|_| ArithmeticError::NotNumeric)?;
@@ -209,6 +201,21 @@ impl Expression {
         let expr = self.apply_function_to_both_side(fn_expr)?;
         return Ok(expr);
     }
+    
+    /// `A = B` => `log(base, A) = log(base, B)`
+    pub fn take_log_of_both_side(&self, base: &Expression) -> Result<Expression, AlgebraError> {
+        // =(_(X),log({base},X))
+        let fn_expr = eb::equation(
+            eb::nary("_", vec![eb::variable("X")]),
+            eb::nary(ArithmeticOperator::Log.as_str(), vec![base.clone(), eb::variable("X")])
+        );
+        return self.apply_function_to_both_side(fn_expr);
+    }
+    
+    /// `A = B` => `A ^ (1 / n) = B ^ (1 / n)`
+    pub fn raise_both_side_to_reciprocal_of(&self, n: &Expression) -> Result<Expression, AlgebraError> {
+        return self.apply_simple_arithmetic_to_both_side(&ArithmeticOperator::Pow, &reciprocal_of(n));
+    }
 }
 
 impl WorkableExpressionSequence {
@@ -218,6 +225,18 @@ impl WorkableExpressionSequence {
         return self.try_push(Action::ApplyAction(name), expr);
     }
     
+    pub fn take_log_of_both_side(&mut self, base: &Expression) -> bool {
+        let name = generate_take_log_of_both_side_name(base);
+        let expr = self.last_expression().take_log_of_both_side(base);
+        return self.try_push(Action::ApplyAction(name), expr);
+    }
+    
+    pub fn raise_both_side_to_reciprocal_of(&mut self, n: &Expression) -> bool {
+        let name = generate_raise_both_side_to_reciprocal_name(n);
+        let expr = self.last_expression().raise_both_side_to_reciprocal_of(n);
+        return self.try_push(Action::ApplyAction(name), expr);
+    }
+    
     pub fn apply_fraction_arithmetic_at(&mut self, numerator_id: usize, denominator_id: usize, addr: &Address) -> bool {
         let last_expr= self.last_expression();
         let expr = last_expr.apply_fraction_arithmetic_at(numerator_id, denominator_id, addr);
@@ -232,9 +251,18 @@ fn generate_simple_apply_arithmetic_to_both_side_expr(op: &ArithmeticOperator, e
         eb::binary(op.as_str(), eb::variable("X"), expr.clone())
     );
 }
-fn generate_simple_apply_arithmetic_to_both_side_name(op: &ArithmeticOperator, expr: &Expression) -> String {
+pub(crate) fn generate_simple_apply_arithmetic_to_both_side_name(op: &ArithmeticOperator, expr: &Expression) -> String {
     return format!("Apply {}{} to both side", op, expr.to_string(true));
 }
+fn generate_take_log_of_both_side_name(base: &Expression) -> String {
+    return format!("Take log base {} of both side", base.to_string(true));
+}
+fn generate_raise_both_side_to_reciprocal_name(n: &Expression) -> String {
+    return format!("Raise both side to 1/{}", n.to_string(true));
+}
+fn reciprocal_of(expr: &Expression) -> Expression {
+    return eb::binary(ArithmeticOperator::Div.as_str(), eb::constant("1"), expr.clone());
+}
 
 // type GetPossibleActionsFunction = fn(&Expression, &WorksheetContext, Vec<Address>) -> Vec<(Action,Expression)>;
 pub mod get_possible_actions {
@@ -249,7 +277,7 @@ pub mod get_possible_actions {
             expression::get_possible_actions::from_rule_map(expr, context, addr_vec),
             apply_fraction_arithmetic(expr, addr_vec),
             expression::get_possible_actions::swap_position_in_comutative_binary(expr, context, addr_vec),
-            expression::get_possible_actions::swap_position_in_assoc_train(expr, addr_vec),
+            expression::get_possible_actions::swap_position_in_assoc_train(expr, context, addr_vec),
             expression::get_possible_actions::flip_equation(expr, addr_vec),
         ].into_iter().flatten().collect();
     }
@@ -276,9 +304,29 @@ pub mod get_possible_actions {
         let expr_parent = expr.at(&addr_parent).ok()?;
         
         let parent_arithmetic_op = expr_parent.identify_arithmetic_operator()?;
-        let inverse_op = parent_arithmetic_op.inverse();
         let expr_target = expr.at(&addr_target).ok()?;
         
+        use ArithmeticOperator::*;
+        match (&parent_arithmetic_op, addr_target.path.last()?) {
+            // b ^ x = y  =>  log(b, b ^ x) = log(b, y)
+            (Pow, 0) => {
+                let result_expr = expr.take_log_of_both_side(expr_target).ok()?;
+                let action = Action::ApplyAction(generate_take_log_of_both_side_name(expr_target));
+                return Some((action, result_expr));
+            },
+            // x ^ n = y  =>  (x ^ n) ^ (1 / n) = y ^ (1 / n)
+            (Pow, 1) => {
+                let result_expr = expr.raise_both_side_to_reciprocal_of(expr_target).ok()?;
+                let action = Action::ApplyAction(generate_raise_both_side_to_reciprocal_name(expr_target));
+                return Some((action, result_expr));
+            },
+            // root(n, x) = y  =>  root(n, x) ^ n = y ^ n
+            (Root, 0) => {},
+            (Add | Sub | Mul | Div | Negative | AddTrain | MulTrain, _) => {},
+            _ => return None,
+        }
+        let inverse_op = parent_arithmetic_op.inverse()?;
+        
         let result_expr = expr.apply_simple_arithmetic_to_both_side(&inverse_op, expr_target).ok()?;
         let action = Action::ApplyAction(generate_simple_apply_arithmetic_to_both_side_name(&inverse_op, expr_target));
         return Some((action, result_expr));
@@ -1,13 +1,19 @@
+use std::collections::HashMap;
 use std::fmt;
 use crate::expression::{Address, Expression, ExpressionError, ExpressionType, StatementSymbols};
 use crate::worksheet::{WorkableExpressionSequence, Action, WorksheetContext};
+use crate::number::Number;
+use crate::sort::Sort;
+use num_bigint::BigInt;
 use super::expression as exp;
 
 #[derive(PartialEq, Clone)]
 pub enum ArithmeticOperator {
     Add, Sub, Mul, Div,   // standard binary ops
-    Negative,             // standard unary ops
-    // Negative, Reciprocal, // standard unary ops
+    Negative, Reciprocal, // standard unary ops
+    Pow,                  // `x ^ n`
+    Sqrt, Ln,             // unary functions: `sqrt(x)`, `ln(x)`
+    Root, Log,            // binary functions: `root(n, x)`, `log(b, x)`
     AddTrain, MulTrain,   // operator train
 }
 
@@ -24,21 +30,32 @@ impl ArithmeticOperator {
             ArithmeticOperator::Mul => "*",
             ArithmeticOperator::Div => "/",
             ArithmeticOperator::Negative => "-",
-            // ArithmeticOperator::Reciprocal => "/",
+            ArithmeticOperator::Reciprocal => "/",
+            ArithmeticOperator::Pow => "^",
+            ArithmeticOperator::Sqrt => "sqrt",
+            ArithmeticOperator::Ln => "ln",
+            ArithmeticOperator::Root => "root",
+            ArithmeticOperator::Log => "log",
             ArithmeticOperator::AddTrain => "+",
             ArithmeticOperator::MulTrain => "*",
         }
     }
-    pub fn inverse(&self) -> Self {
+    /// the operator that undoes this one,
+    /// * `NOTE`: `None` for `Sqrt`, `Log` and `Ln`, they are undone by a power that is not one of their operands
+    pub fn inverse(&self) -> Option<Self> {
         use ArithmeticOperator::*;
         match self {
-            Add => Sub,
-            Sub => Add,
-            Mul => Div,
-            Div => Mul,
-            AddTrain => Sub,
-            MulTrain => Div,
-            Negative => Negative,
+            Add => Some(Sub),
+            Sub => Some(Add),
+            Mul => Some(Div),
+            Div => Some(Mul),
+            AddTrain => Some(Sub),
+            MulTrain => Some(Div),
+            Negative => Some(Negative),
+            Reciprocal => Some(Reciprocal),
+            Pow => Some(Root),
+            Root => Some(Pow),
+            Sqrt | Log | Ln => None,
         }
     }
 }
@@ -48,6 +65,8 @@ pub enum ArithmeticError {
     ExpressionErr(ExpressionError),
     NotAnArithmeticTrainOperator,
     NotNumeric,
+    NothingToCalculate,
+    InexactResult,
     CalculationError,
 }
 
@@ -60,11 +79,21 @@ impl From<ExpressionError> for ArithmeticError {
 pub fn get_arithmetic_ctx() -> exp::Context {
     use ArithmeticOperator::*;
     exp::Context {
-        // unary_ops:  vec![Negative.to_string(), Reciprocal.to_string()],
-        unary_ops:  vec![Negative.to_string()],
-        binary_ops: vec![Add.to_string(), Sub.to_string(), Mul.to_string(), Div.to_string()],
+        // `root(n, x)` and `log(b, x)` are parsed as n-ary functions
+        unary_ops:  vec![Negative.to_string(), Reciprocal.to_string(), Sqrt.to_string(), Ln.to_string()],
+        binary_ops: vec![Add.to_string(), Sub.to_string(), Mul.to_string(), Div.to_string(), Pow.to_string()],
         assoc_ops: vec![Add.to_string(), Mul.to_string()],
+        commutative_ops: vec![Add.to_string(), Mul.to_string()],
         handle_numerics: true,
+        op_precedence: HashMap::from([
+            (Add.to_string(), 1), (Sub.to_string(), 1),
+            (Mul.to_string(), 2), (Div.to_string(), 2),
+            (Pow.to_string(), 3),
+        ]),
+        right_assoc_ops: vec![Pow.to_string()],
+        // the results of the operators, the numeric constants are `Integer` or `Real` (see `Sort::infer`)
+        sorts: [Add, Sub, Mul, Div, Pow, Reciprocal, Root, Sqrt, Log, Ln].iter()
+            .map(|op| (op.to_string(), Sort::Real)).collect(),
         ..Default::default()
     }
 }
@@ -85,7 +114,9 @@ impl exp::Expression {
               0 => None,
               1 => match symbol {
                   "-" => Some(ArithmeticOperator::Negative),
-                  // "/" => Some(ArithmeticOperator::Reciprocal),
+                  "/" => Some(ArithmeticOperator::Reciprocal),
+                  "sqrt" => Some(ArithmeticOperator::Sqrt),
+                  "ln" => Some(ArithmeticOperator::Ln),
                   _ => None,
               },
               2 => match symbol {
@@ -93,6 +124,9 @@ impl exp::Expression {
                   "-" => Some(ArithmeticOperator::Sub),
                   "*" => Some(ArithmeticOperator::Mul),
                   "/" => Some(ArithmeticOperator::Div),
+                  "^" => Some(ArithmeticOperator::Pow),
+                  "root" => Some(ArithmeticOperator::Root),
+                  "log" => Some(ArithmeticOperator::Log),
                   _ => None,
               },
               _ => None,
@@ -107,7 +141,14 @@ impl exp::Expression {
     
     pub fn is_integer(&self) -> bool {
         return self.exp_type == ExpressionType::ValueConst 
-            && self.symbol.parse::<i64>().is_ok()
+            && self.symbol.parse::<BigInt>().is_ok()
+    }
+    
+    /// a numeric value, or a fraction of two integers (ex: `1/3`)
+    pub fn is_numeric_constant(&self) -> bool {
+        if self.is_numeric() { return true; }
+        return self.identify_arithmetic_operator() == Some(ArithmeticOperator::Div)
+            && self.children.as_ref().unwrap().iter().all(|c| c.is_integer());
     }
     
     pub fn is_arithmetic_train_operator(&self) -> bool {
@@ -121,8 +162,8 @@ impl exp::Expression {
     // (i.e. the children are all numeric values)
     pub fn is_directly_calculatable(&self) -> bool {
         if !self.is_operator() { return false; }
-        // return true if all children are numeric values
-        return self.children.as_ref().unwrap().iter().all(|c| c.is_numeric());
+        // return true if all children are numeric values (or fractions)
+        return self.children.as_ref().unwrap().iter().all(|c| c.is_numeric_constant());
     }
     
     // if the expression is a negative unary operator on a numeric value, 
@@ -132,7 +173,10 @@ impl exp::Expression {
         let op = self.identify_arithmetic_operator();
         if op == Some(ArithmeticOperator::Negative) && self.children.as_ref().unwrap()[0].is_numeric(){
             let child = self.children.as_ref().unwrap()[0].clone();
-            let new_symbol = format!("{}", -child.symbol.parse::<f64>().unwrap());
+            let new_symbol = match Number::parse(&child.symbol).and_then(|n| n.checked_neg()) {
+                Some(n) => n.to_string(),
+                None => return self.clone(),
+            };
             return Expression {
                 symbol: new_symbol,
                 children: None,
@@ -227,11 +271,13 @@ impl exp::Expression {
         };
         let val = normalized_self.calculate_numeric().ok_or(ArithmeticError::CalculationError)?;
         let lhs = self.clone();
-        let rhs = Expression {
-            symbol: format!("{}", val),
-            children: None,
-            exp_type: ExpressionType::ValueConst,
-        };
+        let rhs = val.to_expression();
+        // ex: `1/3` is already a fraction
+        if rhs == normalized_self { return Err(ArithmeticError::NothingToCalculate); }
+        // ex: `sqrt(2)`, keep it exact instead of writing down an approximation
+        let is_exact_input = normalized_self.children.iter().flatten()
+            .all(|c| !matches!(c.calculate_numeric(), Some(Number::Decimal(_))));
+        if is_exact_input && matches!(val, Number::Decimal(_)) { return Err(ArithmeticError::InexactResult); }
         return Ok(Expression {
             symbol: StatementSymbols::Equal.to_string(),
             children: Some(vec![lhs, rhs]),
@@ -271,49 +317,71 @@ impl exp::Expression {
         return Ok(result);
     }
 
-    /// Calculate the value of the expression if it is an arithmetic operation
-    pub fn calculate_numeric(&self) -> Option<f64> {
+    /// Calculate the value of the expression if it is an arithmetic operation,
+    /// the result is exact unless one of the values is a decimal
+    pub fn calculate_numeric(&self) -> Option<Number> {
         use ArithmeticOperator::*;
         // if a numeric value, return the value
-        if self.is_value() { return self.symbol.parse::<f64>().ok(); }
+        if self.is_value() { return Number::parse(&self.symbol); }
         let op = self.identify_arithmetic_operator()?;
         let children = self.children.as_ref().unwrap();
         match op {
             Add => {
                 let left = children[0].calculate_numeric()?;
                 let right = children[1].calculate_numeric()?;
-                Some(left + right)
+                left.checked_add(&right)
             },
             Sub => {
                 let left = children[0].calculate_numeric()?;
                 let right = children[1].calculate_numeric()?;
-                Some(left - right)
+                left.checked_sub(&right)
             },
             Mul => {
                 let left = children[0].calculate_numeric()?;
                 let right = children[1].calculate_numeric()?;
-                Some(left * right)
+                left.checked_mul(&right)
             },
             Div => {
                 let left = children[0].calculate_numeric()?;
                 let right = children[1].calculate_numeric()?;
-                Some(left / right)
+                left.checked_div(&right)
             },
             Negative => {
                 let child = children[0].calculate_numeric()?;
-                Some(-child)
+                child.checked_neg()
+            },
+            Reciprocal => {
+                let child = children[0].calculate_numeric()?;
+                child.checked_reciprocal()
+            },
+            Pow => {
+                let base = children[0].calculate_numeric()?;
+                let exp = children[1].calculate_numeric()?;
+                base.checked_pow(&exp)
+            },
+            Sqrt => {
+                let child = children[0].calculate_numeric()?;
+                child.checked_sqrt()
+            },
+            Ln => {
+                let child = children[0].calculate_numeric()?;
+                child.checked_ln()
+            },
+            Root => {
+                let n = children[0].calculate_numeric()?;
+                let child = children[1].calculate_numeric()?;
+                child.checked_root(&n)
+            },
+            Log => {
+                let base = children[0].calculate_numeric()?;
+                let child = children[1].calculate_numeric()?;
+                child.checked_log(&base)
             },
-            // Reciprocal => {
-            //     let child = children[0].calculate_numeric()?;
-            //     Some(1.0 / child)
-            // },
             AddTrain => {
-                let result = children.iter().map(|c| c.calculate_numeric()).sum::<Option<f64>>()?;
-                Some(result)
+                children.iter().try_fold(Number::integer(0), |acc, c| acc.checked_add(&c.calculate_numeric()?))
             },
             MulTrain => {
-                let result = children.iter().map(|c| c.calculate_numeric()).product::<Option<f64>>()?;
-                Some(result)
+                children.iter().try_fold(Number::integer(1), |acc, c| acc.checked_mul(&c.calculate_numeric()?))
             },
         }
     }
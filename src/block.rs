@@ -10,6 +10,10 @@ pub enum BlockType {
     HorizontalContainer,
     // VerticalContainer,
     FractionContainer,
+    /// children: `[base, exponent]`
+    SuperscriptContainer,
+    /// children: `[radicand]` or `[index, radicand]`
+    RadicalContainer,
 }
 
 /// use `pair_map!` macro to generate `inverse_ops`
@@ -23,6 +27,8 @@ pub enum BlockType {
 pub struct BlockContext {
     pub inverse_ops: HashMap<String, String>,
     pub fraction_ops: Vec<String>,
+    pub superscript_ops: Vec<String>, // binary ops drawn as `base^exponent`, like `^`
+    pub radical_ops: Vec<String>, // unary or binary (index first) ops drawn as a radical, like `sqrt` and `root`
     pub conceal_ops: Vec<String>, // ops that can be hidden, like multiplication
     //TODO: add rules for concealing (e.g. don't conceal * if it appled to numbers)
     pub op_precedence: HashMap<String, usize>,
@@ -89,11 +95,14 @@ impl Block {
             },
             ExpressionType::Variadic | 
             ExpressionType::OperatorUnary => {
-                let operator_block = block_builder::symbol(symbol, addr.clone());
                 let expr_children = expr.children.as_ref().expect("UnaryOps have one child");
                 let operand_addr = addr.append(0);
                 let operand_expr = expr_children.first().expect("UnaryOps have one child");
                 let operand_block = Block::from_expression(operand_expr, operand_addr, ctx);
+                if ctx.radical_ops.contains(&symbol) {
+                    return block_builder::radical_container(vec![operand_block], addr);
+                }
+                let operator_block = block_builder::symbol(symbol, addr.clone());
                 block_builder::horizontal_container(vec![operator_block, operand_block], addr)
             },
             ExpressionType::StatementOperatorBinary |
@@ -114,6 +123,15 @@ impl Block {
                 
                 if ctx.fraction_ops.contains(&symbol) {
                     block_builder::fraction_container(vec![left_block, right_block], addr)
+                } else if ctx.superscript_ops.contains(&symbol) {
+                    // the exponent is already set apart, only the base needs parentheses
+                    let left_block = if left_expr.is_operator() && !left_block.contains_tag(&BlockTag::Parentheses) {
+                        left_block.add_tag(BlockTag::Parentheses)
+                    } else {
+                        left_block
+                    };
+                    let right_block = right_block.remove_tag(&BlockTag::Parentheses);
+                    block_builder::superscript_container(vec![left_block, right_block], addr)
                 } else {
                     let is_conceal = ctx.conceal_ops.contains(&symbol) && !utils::is_number(right_expr.symbol.as_str());
                     let operator_block = block_builder::symbol(symbol, addr.clone());
@@ -128,8 +146,14 @@ impl Block {
                 }
             },
             ExpressionType::OperatorNary => {
-                let operator_block = block_builder::symbol(symbol, addr.clone());
                 let expr_children = expr.children.as_ref().expect("NaryOps have children");
+                if ctx.radical_ops.contains(&symbol) && expr_children.len() <= 2 {
+                    let children_blocks = expr_children.iter().enumerate()
+                        .map(|(i, child)| Block::from_expression(child, addr.append(i), ctx))
+                        .collect();
+                    return block_builder::radical_container(children_blocks, addr);
+                }
+                let operator_block = block_builder::symbol(symbol, addr.clone());
                 let mut children_blocks = Vec::new();
                 for (i, child) in expr_children.iter().enumerate() {
                     let child_addr = addr.append(i);
@@ -229,4 +253,10 @@ pub mod block_builder {
     pub fn fraction_container(children: Vec<Block>, addr: Address) -> Block {
         container(BlockType::FractionContainer, children, addr)
     }
+    pub fn superscript_container(children: Vec<Block>, addr: Address) -> Block {
+        container(BlockType::SuperscriptContainer, children, addr)
+    }
+    pub fn radical_container(children: Vec<Block>, addr: Address) -> Block {
+        container(BlockType::RadicalContainer, children, addr)
+    }
 }
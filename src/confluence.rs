@@ -0,0 +1,123 @@
+use crate::expression::{Address, Expression, ExpressionType, MatchMap};
+use crate::registry;
+use crate::rule::{Rule, RuleSet};
+
+/// an expression where two auto rules overlap, and the results of applying each of them
+/// that can't be rewritten (by the auto rules) into the same expression
+#[derive(Debug, Clone, PartialEq)]
+pub struct CriticalPair {
+    pub rule_ids: (String, String),
+    pub overlap: Expression,
+    /// the normal forms of the results of applying the first and the second rule
+    pub results: (Expression, Expression),
+}
+
+impl RuleSet {
+    /// Knuth-Bendix style check of the auto rules: every overlap of the lhs of two auto rules
+    /// (the lhs of one unifies with a part of the lhs of the other) must rewrite to the same expression,
+    /// the overlaps that don't are returned, so an empty result means the auto rules are (locally) confluent
+    /// * `NOTE`: only equations without conditions or variadics are checked,
+    ///   and the expressions are unified by position (not by commutativity or associativity)
+    pub fn find_critical_pairs(&self) -> Vec<CriticalPair> {
+        let rules = self.rule_vec.iter()
+            .filter(|rule| self.auto_rule_ids.contains(&rule.id))
+            .filter(|rule| rule.expression.is_equation() && rule.conditions.is_empty() && !has_variadic(&rule.expression))
+            .collect::<Vec<&Rule>>();
+        let equations = [rules.iter().map(|rule| rule.expression.clone()).collect(), self.normalization.clone()].concat();
+        let normalization_function = self.normalization_function.as_ref()
+            .and_then(|name| registry::get_normalization_function(name));
+        // the same steps as `WorkableExpressionSequence::normalize` and the auto rules
+        let normalize = |expr: &Expression| {
+            let expr = expr.normalize_to_assoc_train(&self.context.assoc_ops).rewrite_to_fixpoint(&equations)?;
+            return match normalization_function {
+                Some(f) => f(&expr, &self.context).rewrite_to_fixpoint(&equations),
+                None => Ok(expr),
+            };
+        };
+
+        let mut critical_pairs = vec![];
+        for (i, outer) in rules.iter().enumerate() {
+            for (j, inner) in rules.iter().enumerate() {
+                for (addr, overlap, result0, result1) in find_overlaps(outer, inner) {
+                    // overlaps at the root are found twice, as (outer, inner) and (inner, outer)
+                    if addr.path.is_empty() && i > j { continue; }
+                    // the results can't be compared if the rewrites don't terminate
+                    let results = match (normalize(&result0), normalize(&result1)) {
+                        (Ok(normal0), Ok(normal1)) => (normal0, normal1),
+                        _ => continue,
+                    };
+                    if results.0 == results.1 { continue; }
+                    critical_pairs.push(CriticalPair {
+                        rule_ids: (outer.id.clone(), inner.id.clone()),
+                        overlap, results,
+                    });
+                }
+            }
+        }
+        return critical_pairs;
+    }
+}
+
+fn has_variadic(expr: &Expression) -> bool {
+    if expr.exp_type == ExpressionType::Variadic { return true; }
+    return expr.children.as_ref().is_some_and(|children| children.iter().any(has_variadic));
+}
+
+/// the expressions where the lhs of `inner` unifies with a (non variable) part of the lhs of `outer`,
+/// with the results of applying `outer` (at the root) and `inner` (at that part)
+fn find_overlaps(outer: &Rule, inner: &Rule) -> Vec<(Address, Expression, Expression, Expression)> {
+    let inner_expr = rename_variables(&inner.expression, "'");
+    let (outer_lhs, outer_rhs) = (outer.expression.lhs().unwrap(), outer.expression.rhs().unwrap());
+    let (inner_lhs, inner_rhs) = (inner_expr.lhs().unwrap(), inner_expr.rhs().unwrap());
+    let mut overlaps = vec![];
+    // parts of a train (`Address::sub`) are not unified
+    for addr in outer_lhs.get_all_addresses().into_iter().filter(|addr| addr.sub.is_none()) {
+        // a rule always overlaps with itself at the root
+        if addr.path.is_empty() && outer.id == inner.id { continue; }
+        let part = match outer_lhs.at(&addr) {
+            Ok(part) if !part.is_variable() => part,
+            _ => continue,
+        };
+        let mut map = MatchMap::new();
+        if !unify(part, inner_lhs, &mut map) { continue; }
+        let overlap = outer_lhs.apply_match_map(&map);
+        let result0 = outer_rhs.apply_match_map(&map);
+        let result1 = match overlap.replace_expression_at(inner_rhs.apply_match_map(&map), &addr) {
+            Ok(result1) => result1,
+            Err(_) => continue,
+        };
+        overlaps.push((addr, overlap, result0, result1));
+    }
+    return overlaps;
+}
+
+/// add `suffix` to every variable, so the variables of two rules are distinct
+fn rename_variables(expr: &Expression, suffix: &str) -> Expression {
+    let mut expr = expr.clone();
+    if expr.is_variable() { expr.symbol.push_str(suffix); }
+    if let Some(children) = expr.children.as_mut() {
+        for child in children.iter_mut() { *child = rename_variables(child, suffix); }
+    }
+    return expr;
+}
+
+/// syntactic unification, `map` is extended with the most general unifier
+fn unify(a: &Expression, b: &Expression, map: &mut MatchMap) -> bool {
+    let (a, b) = (a.apply_match_map(map), b.apply_match_map(map));
+    if a == b { return true; }
+    if a.is_variable() { return bind(&a.symbol, b, map); }
+    if b.is_variable() { return bind(&b.symbol, a, map); }
+    if a.exp_type != b.exp_type || a.symbol != b.symbol { return false; }
+    return match (&a.children, &b.children) {
+        (Some(ac), Some(bc)) if ac.len() == bc.len() => ac.iter().zip(bc).all(|(x, y)| unify(x, y, map)),
+        _ => false,
+    };
+}
+
+fn bind(variable: &str, expr: Expression, map: &mut MatchMap) -> bool {
+    if expr.get_variable_symbols().iter().any(|v| v == variable) { return false; }
+    let binding = MatchMap::from([(variable.to_string(), expr.clone())]);
+    for value in map.values_mut() { *value = value.apply_match_map(&binding); }
+    map.insert(variable.to_string(), expr);
+    return true;
+}
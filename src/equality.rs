@@ -0,0 +1,118 @@
+use crate::expression::{Context, Expression, ExpressionType, StatementSymbols};
+use crate::number::Number;
+
+/// number of variable assignments tried when the equality can't be decided symbolically
+const NUMBER_OF_NUMERIC_TRIALS: usize = 8;
+/// trials where either side can't be evaluated (ex: `sqrt` of a negative) are skipped,
+/// the expressions are only considered equal if at least this many trials were evaluated
+const MIN_NUMBER_OF_EVALUATED_TRIALS: usize = 3;
+const NUMERIC_TOLERANCE: f64 = 1e-9;
+
+impl Expression {
+    /// a canonical form under associativity of `ctx.assoc_ops` and commutativity of `ctx.commutative_ops`:
+    /// assoc ops are flattened into trains, the children of commutative trains are sorted, and numeric parts are calculated
+    /// * `NOTE`: numeric parts are only calculated if `ctx.handle_numerics`, and only if the result is exact
+    pub fn canonicalize(&self, ctx: &Context) -> Expression {
+        let expr = if ctx.handle_numerics { self.normalize_sub_to_negative() } else { self.clone() };
+        return expr.normalize_to_assoc_train(&ctx.assoc_ops).f_canonicalize(ctx);
+    }
+
+    fn f_canonicalize(&self, ctx: &Context) -> Expression {
+        let mut expr = self.clone();
+        if let Some(children) = expr.children.as_mut() {
+            for child in children.iter_mut() { *child = child.f_canonicalize(ctx); }
+        }
+        if ctx.handle_numerics {
+            if let Some(value @ Number::Rational(_)) = expr.calculate_numeric() {
+                return value.to_expression();
+            }
+        }
+        if expr.exp_type == ExpressionType::AssocTrain && ctx.is_commutative(&expr.symbol) {
+            let children = expr.children.as_mut().unwrap();
+            if ctx.handle_numerics { fold_numeric_children(&expr.symbol, children); }
+            children.sort_by_cached_key(|c| c.to_string(true));
+            if children.len() == 1 { return children.remove(0); }
+        }
+        return expr;
+    }
+
+    /// check if two expressions are equal for every value of their variables,
+    /// ex: `x + 2 * y` and `y * 2 + x`, or `(x + 1) ^ 2` and `x ^ 2 + 2 * x + 1`
+    /// * compare the canonical forms (`Expression::canonicalize`)
+    /// * compare them as polynomials, if both can be converted
+    /// * otherwise, evaluate both with (pseudo) random values for the variables
+    /// * `NOTE`: statements are equal if both sides are, `A = B` is also equal to `B = A`
+    pub fn is_mathematically_equal(&self, other: &Expression, ctx: &Context) -> bool {
+        if self.is_statement() || other.is_statement() {
+            if self.symbol != other.symbol { return false; }
+            let (a, b) = match (&self.children, &other.children) {
+                (Some(a), Some(b)) if a.len() == 2 && b.len() == 2 => (a, b),
+                _ => return false,
+            };
+            let is_same_order = a[0].is_mathematically_equal(&b[0], ctx) && a[1].is_mathematically_equal(&b[1], ctx);
+            let is_symmetric = self.symbol == StatementSymbols::Equal.as_str();
+            return is_same_order || (is_symmetric
+                && a[0].is_mathematically_equal(&b[1], ctx) && a[1].is_mathematically_equal(&b[0], ctx));
+        }
+
+        let (lhs, rhs) = (self.canonicalize(ctx), other.canonicalize(ctx));
+        if lhs == rhs || lhs.is_equivalent_to(&rhs) { return true; }
+
+        let mut vars = lhs.get_variable_symbols();
+        vars.extend(rhs.get_variable_symbols());
+        vars.sort();
+        vars.dedup();
+        if ctx.handle_numerics {
+            if let (Ok(p), Ok(q)) = (lhs.to_polynomial(&vars), rhs.to_polynomial(&vars)) {
+                return p == q;
+            }
+        }
+        return is_numerically_equal(&lhs, &rhs, &vars);
+    }
+}
+
+/// replace the numeric children of an arithmetic train with their combined value, ex: `2 * x * 3` => `6 * x`
+fn fold_numeric_children(symbol: &str, children: &mut Vec<Expression>) {
+    let (numerics, mut others): (Vec<Expression>, Vec<Expression>) = children.drain(..).partition(|c| c.is_numeric());
+    if numerics.len() < 2 {
+        children.extend(numerics);
+        children.append(&mut others);
+        return;
+    }
+    let train = Expression { exp_type: ExpressionType::AssocTrain, symbol: symbol.to_string(), children: Some(numerics.clone()) };
+    match train.calculate_numeric() {
+        Some(value @ Number::Rational(_)) => children.push(value.to_expression()),
+        _ => children.extend(numerics),
+    }
+    children.append(&mut others);
+}
+
+/// xorshift, so that the trials are the same on every run
+struct PseudoRandom(u64);
+impl PseudoRandom {
+    fn next_value(&mut self) -> f64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        // in [-5, 5), with 3 decimals
+        return ((self.0 % 10000) as f64 - 5000.0) / 1000.0;
+    }
+}
+
+fn is_numerically_equal(lhs: &Expression, rhs: &Expression, vars: &[String]) -> bool {
+    let mut random = PseudoRandom(0x2545F4914F6CDD1D);
+    let mut evaluated_trials = 0;
+    for _ in 0..NUMBER_OF_NUMERIC_TRIALS {
+        let values = vars.iter().map(|_| random.next_value().to_string()).collect::<Vec<String>>();
+        let substitute = |expr: &Expression| vars.iter().zip(&values)
+            .fold(expr.clone(), |e, (var, value)| e.substitute_symbol(var.clone(), value.clone()));
+        let (a, b) = match (substitute(lhs).calculate_numeric(), substitute(rhs).calculate_numeric()) {
+            (Some(a), Some(b)) => (a.to_f64(), b.to_f64()),
+            _ => continue,
+        };
+        let scale = a.abs().max(b.abs()).max(1.0);
+        if (a - b).abs() > NUMERIC_TOLERANCE * scale { return false; }
+        evaluated_trials += 1;
+    }
+    return evaluated_trials >= MIN_NUMBER_OF_EVALUATED_TRIALS;
+}
@@ -1,8 +1,10 @@
 use std::{cmp::Ordering, collections::{HashMap, HashSet}, fmt, str::FromStr};
 use regex::Regex;
+use serde::{Serialize, Deserialize};
 use super::utils;
+use crate::sort::Sort;
 
-#[derive(Debug, PartialEq, Clone, Default)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Default, Serialize, Deserialize)]
 pub enum ExpressionType {
     OperatorUnary,
     OperatorBinary,
@@ -71,23 +73,34 @@ impl StatementSymbols {
     }
 }
 
-#[derive(Debug, PartialEq, Clone, Default)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Default, Serialize, Deserialize)]
 pub struct Expression {
     pub exp_type: ExpressionType,
     pub symbol: String,
     pub children: Option<Vec<Expression>>,
 }
 
-#[derive(Clone, Default, Debug, PartialEq)]
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Context {
     pub parameters: Vec<String>,
     pub unary_ops: Vec<String>,
     pub binary_ops: Vec<String>,
     pub assoc_ops: Vec<String>,
+    /// operators whose operands can be reordered, ex: `a + b` -> `b + a`
+    pub commutative_ops: Vec<String>,
     // the rest of the symbols will be considered as n-ary operators (functions)
     // pub inverse_ops: HashMap<String,String>,
     pub handle_numerics: bool,
-    pub flags: HashSet<String>
+    pub flags: HashSet<String>,
+    /// binding strength of binary operators when parsing infix expressions,
+    /// higher binds tighter, operators that are not listed have precedence 0
+    pub op_precedence: HashMap<String, usize>,
+    /// binary operators that group from the right, ex: `a ^ b ^ c` -> `a ^ (b ^ c)`
+    pub right_assoc_ops: Vec<String>,
+    /// sorts of the parameters and (the result of) operators, see `Sort::infer`,
+    /// the sorts of the variables of a rule are in `Rule::sorts`
+    pub sorts: HashMap<String, Sort>,
 }
 
 
@@ -125,10 +138,24 @@ impl Context {
         for f in flags { if !self.flags.contains(&f.to_string()) { return false; } }
         return true;
     }
+    
+    pub fn precedence_of(&self, op: &str) -> usize {
+        return self.op_precedence.get(op).copied().unwrap_or(0);
+    }
+    pub fn is_commutative(&self, op: &str) -> bool {
+        return self.commutative_ops.iter().any(|o| o == op);
+    }
+    /// operators that are both associative and commutative
+    pub fn ac_ops(&self) -> Vec<String> {
+        return self.assoc_ops.iter().filter(|op| self.is_commutative(op)).cloned().collect();
+    }
+    pub fn is_right_assoc(&self, op: &str) -> bool {
+        return self.right_assoc_ops.iter().any(|o| o == op);
+    }
 }
 
 
-#[derive(Clone, Debug, Eq, PartialEq, Default)]
+#[derive(Clone, Debug, Eq, PartialEq, Default, Serialize, Deserialize)]
 pub struct Address {
     pub path: Vec<usize>,
     pub sub: Option<usize>, // sub if for addressing subexpression in AssocTrain
@@ -302,6 +329,34 @@ macro_rules! ctxflag {
 
 pub type MatchMap = HashMap<String,Expression>;
 
+/// how a pattern is matched, see `Expression::pattern_match_this_node_with`
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct MatchOptions {
+    /// operators that are matched regardless of the order and grouping of their children
+    pub ac_ops: Vec<String>,
+    /// the sort that the expression matched by a variable must have
+    pub variable_sorts: HashMap<String, Sort>,
+    /// sorts of the symbols of the matched expression, see `Context::sorts`
+    pub sorts: HashMap<String, Sort>,
+}
+
+impl MatchOptions {
+    /// `true` if `expr` has the sort required for `variable`,
+    /// an unknown sort is only allowed for values (ex: a parameter without a sort), not for operators
+    pub fn is_sort_allowed(&self, variable: &str, expr: &Expression) -> bool {
+        let required = match self.variable_sorts.get(variable) {
+            Some(required) => required,
+            None => return true,
+        };
+        return match Sort::infer(expr, &self.sorts) {
+            Some(sort) => sort.is_subsort_of(required),
+            None => expr.is_value(),
+        };
+    }
+}
+
+const LIMIT_OF_REWRITE_STEPS: usize = 100;
+
 #[derive(Debug)]
 pub enum ExpressionError {
     InvalidAddress,
@@ -315,6 +370,9 @@ pub enum ExpressionError {
     NotAParentOfVariadic,
     InvalidRule,
     InvalidVariadicParam,
+    ConditionNotSatisfied(String),
+    /// the equations are still applicable after the limit of rewrite passes
+    RewriteLimitReached,
 }
 
 impl Expression {
@@ -421,6 +479,9 @@ impl Expression {
         match &self.exp_type {
             ExpressionType::ValueConst => self.symbol.clone(),
             ExpressionType::ValueVar => self.symbol.clone(),
+            // named unary ops are written like functions, ex: `sqrt(x)`
+            ExpressionType::OperatorUnary if self.symbol.chars().all(char::is_alphabetic) =>
+                format!("{}({})", self.symbol, self.children.as_ref().unwrap()[0].to_string(parentheses)),
             ExpressionType::OperatorUnary => 
                 if parentheses {
                     format!("({}{})", self.symbol, 
@@ -565,6 +626,32 @@ impl Expression {
         if !self.is_equation() { return None; }
         return self.at(&address![1]).ok();
     }
+    
+    /// number of nodes in the expression tree
+    pub fn size(&self) -> usize {
+        let children_size: usize = self.children.iter().flatten().map(|c| c.size()).sum();
+        return 1 + children_size;
+    }
+    
+    /// addresses of every node (in pre-order), including the sub addresses of AssocTrain
+    pub fn get_all_addresses(&self) -> Vec<Address> {
+        let mut result = vec![];
+        self.f_get_all_addresses(&Address::default(), &mut result);
+        return result;
+    }
+    fn f_get_all_addresses(&self, current_address: &Address, result: &mut Vec<Address>) {
+        result.push(current_address.clone());
+        let children = match &self.children {
+            Some(children) => children,
+            None => return,
+        };
+        if self.is_assoc_train() {
+            for i in 0..children.len().saturating_sub(1) { result.push(current_address.sub(i)); }
+        }
+        for (i, child) in children.iter().enumerate() {
+            child.f_get_all_addresses(&current_address.append(i), result);
+        }
+    }
 
     /// Get the expression from the address
     pub fn at(&self, address: &Address) -> Result<&Expression, ExpressionError> {
@@ -677,16 +764,21 @@ impl Expression {
     }
 
     pub fn get_pattern_matches(&self, pattern: &Expression) -> Vec<(Address,MatchMap)> {
-        return self.f_get_patten_matches(pattern, &Address::default(), true);
+        return self.get_pattern_matches_with(pattern, &MatchOptions::default());
+    }
+    /// same as `get_pattern_matches`, but matched with `pattern_match_this_node_with`
+    pub fn get_pattern_matches_with(&self, pattern: &Expression, options: &MatchOptions) -> Vec<(Address,MatchMap)> {
+        return self.f_get_patten_matches(pattern, &Address::default(), true, options);
     }
     
     /// Try to match the pattern expression with this expression, and all its children.
     /// Returns a list of maps, where each map represents a match.
-    fn f_get_patten_matches(&self, pattern: &Expression, current_address: &Address, check_children: bool) -> Vec<(Address,MatchMap)> {
+    fn f_get_patten_matches(&self, pattern: &Expression, current_address: &Address, check_children: bool, options: &MatchOptions)
+    -> Vec<(Address,MatchMap)> {
         let mut result = Vec::new();
 
         // try to match the root node
-        let root_map = self.pattern_match_this_node(pattern);
+        let root_map = self.pattern_match_this_node_with(pattern, options);
         if let Some(map) = root_map { result.push((current_address.clone(), map)); }
         
         // try to match the subexpression (if is a train)
@@ -694,7 +786,7 @@ impl Expression {
             for i in 0..self.children.as_ref().unwrap().len()-1 {
                 let subexpr = self.generate_subexpr_from_train(i);
                 if let Ok(sub) = subexpr {
-                    let sub_matches = sub.f_get_patten_matches(pattern, &current_address.sub(i), false);
+                    let sub_matches = sub.f_get_patten_matches(pattern, &current_address.sub(i), false, options);
                     sub_matches.iter().for_each(|m| {
                         result.push(m.clone());
                     });
@@ -707,7 +799,7 @@ impl Expression {
             let children = self.children.as_ref().unwrap();
             for (i,c) in children.iter().enumerate() {
                 let child_address = current_address.append(i);
-                let child_matches = c.f_get_patten_matches(pattern, &child_address, true);
+                let child_matches = c.f_get_patten_matches(pattern, &child_address, true, options);
                 // push the child matches to the result
                 child_matches.iter().for_each(|m| {
                     result.push(m.clone());
@@ -735,6 +827,16 @@ impl Expression {
     /// Try to match the pattern expression with this expression. (match the root node)
     /// Returns a map of the symbols, or None if there is the pattern does not match.
     pub fn pattern_match_this_node(&self, pattern: &Expression) -> Option<MatchMap> {
+        return self.pattern_match_this_node_with(pattern, &MatchOptions::default());
+    }
+
+    /// same as `pattern_match_this_node`, but
+    /// * the operators in `options.ac_ops` (commutative and associative) are matched regardless of the order of their children,
+    ///   and a variable of the pattern can take the remainder of a train as a whole,
+    ///   ex: `X + 0` matches `a + 0 + b` with `X = a + b`
+    /// * the variables in `options.variable_sorts` only match expressions of that sort (or values of an unknown sort)
+    /// * `NOTE`: the children are matched by position first, the first match found is returned
+    pub fn pattern_match_this_node_with(&self, pattern: &Expression, options: &MatchOptions) -> Option<MatchMap> {
         use ExpressionType::*;
         match pattern.exp_type {
             // if the pattern is a constant parameter, then it must match exactly with this expression
@@ -747,6 +849,7 @@ impl Expression {
             },
             // if the pattern is a variable, then it must mapped to this expression
             ValueVar => {
+                if !options.is_sort_allowed(&pattern.symbol, self) { return None; }
                 let mut map = HashMap::new();
                 map.insert(pattern.symbol.clone(), self.clone());
                 Some(map)
@@ -758,14 +861,14 @@ impl Expression {
                     let children = self.children.as_ref()?;
                     let children_len = children.len();
                     let new_pattern = pattern.expand_variadic(children_len, None, false).ok()?;
-                    let mut match_map = self.pattern_match_this_node(&new_pattern)?;
+                    let mut match_map = self.pattern_match_this_node_with(&new_pattern, options)?;
                     match_map.insert(
                         ExpressionType::variadic_string(), 
                         expression_builder::constant(&children_len.to_string()));
                     return Some(match_map);
                 } else if self.exp_type == ExpressionType::OperatorBinary {
                     let new_pattern = pattern.expand_variadic(2, None, true).ok()?;
-                    let mut match_map = self.pattern_match_this_node(&new_pattern)?;
+                    let mut match_map = self.pattern_match_this_node_with(&new_pattern, options)?;
                     match_map.insert(
                         ExpressionType::variadic_string(), 
                         expression_builder::constant(&ExpressionType::variadic_binary_tag_string()));
@@ -777,31 +880,110 @@ impl Expression {
             // if the pattern is an operator, then it must match
             // then, pattern match each child
             StatementOperatorBinary | OperatorUnary | OperatorBinary | OperatorNary | AssocTrain => {
-                // invalid if the symbol or type is different
+                // invalid if the symbol is different
                 if pattern.symbol != self.symbol { return None; }
-                if pattern.exp_type != self.exp_type { return None; }
-                // invalid if one of them does not have children (operator must have children)
-                if self.children.is_none() || pattern.children.is_none() { return None; }
-                // invalid if the number of children is different
-                let self_children = self.children.as_ref().unwrap();
-                let pattern_children = pattern.children.as_ref().unwrap();
-                if self_children.len() != pattern_children.len() { return None; }
-                // pattern match each child
-                let mut map = HashMap::new();
-                for i in 0..self_children.len() {
-                    let child_map = self_children[i].pattern_match_this_node(&pattern_children[i])?;
-                    // invalid if the child maps clash
-                    if !utils::is_hashmap_no_clash(&map, &child_map) { return None; }
-                    // merge the child map with the current map
-                    for (k,v) in child_map { map.insert(k,v); }
-                }
-                Some(map)
+                let map = self.f_pattern_match_children(pattern, options);
+                if map.is_some() || !options.ac_ops.contains(&pattern.symbol) { return map; }
+                return self.f_pattern_match_children_ac(pattern, options);
             },
             Variadic => {
                 todo!()
             }
         }
     }
+
+    /// match the children of the operator by position
+    fn f_pattern_match_children(&self, pattern: &Expression, options: &MatchOptions) -> Option<MatchMap> {
+        // invalid if the type is different
+        if pattern.exp_type != self.exp_type { return None; }
+        // invalid if one of them does not have children (operator must have children)
+        if self.children.is_none() || pattern.children.is_none() { return None; }
+        // invalid if the number of children is different
+        let self_children = self.children.as_ref().unwrap();
+        let pattern_children = pattern.children.as_ref().unwrap();
+        if self_children.len() != pattern_children.len() { return None; }
+        // pattern match each child
+        let mut map = HashMap::new();
+        for i in 0..self_children.len() {
+            let child_map = self_children[i].pattern_match_this_node_with(&pattern_children[i], options)?;
+            // invalid if the child maps clash
+            if !utils::is_hashmap_no_clash(&map, &child_map) { return None; }
+            // merge the child map with the current map
+            for (k,v) in child_map { map.insert(k,v); }
+        }
+        return Some(map);
+    }
+
+    /// match the children of a commutative and associative operator in any order,
+    /// if the train is longer than the pattern, one variable of the pattern takes the remaining children
+    fn f_pattern_match_children_ac(&self, pattern: &Expression, options: &MatchOptions) -> Option<MatchMap> {
+        use ExpressionType::*;
+        let is_ac_node = |expr: &Expression| matches!(expr.exp_type, OperatorBinary | AssocTrain);
+        if !is_ac_node(self) || !is_ac_node(pattern) { return None; }
+        let self_children = self.children.as_ref()?;
+        let pattern_children = pattern.children.as_ref()?;
+        if pattern_children.len() > self_children.len() { return None; }
+
+        let rest_candidates: Vec<Option<usize>> = if pattern_children.len() == self_children.len() {
+            vec![None]
+        } else {
+            (0..pattern_children.len()).filter(|i| pattern_children[*i].exp_type == ValueVar).map(Some).collect()
+        };
+        for rest in rest_candidates {
+            let fixed: Vec<&Expression> = pattern_children.iter().enumerate()
+                .filter(|(i,_)| Some(*i) != rest)
+                .map(|(_,c)| c)
+                .collect();
+            let rest_pattern = rest.map(|i| &pattern_children[i]);
+            let mut used = vec![false; self_children.len()];
+            let map = self.f_pattern_match_ac_assignment(&fixed, rest_pattern, &mut used, HashMap::new(), options);
+            if map.is_some() { return map; }
+        }
+        return None;
+    }
+
+    /// assign each of the `fixed` patterns to an unused child (backtracking),
+    /// then match the `rest` pattern with the children that are left
+    fn f_pattern_match_ac_assignment(&self, fixed: &[&Expression], rest: Option<&Expression>,
+        used: &mut Vec<bool>, map: MatchMap, options: &MatchOptions
+    ) -> Option<MatchMap> {
+        let self_children = self.children.as_ref()?;
+        if fixed.is_empty() {
+            let rest = match rest {
+                Some(rest) => rest,
+                None => return Some(map),
+            };
+            let mut rest_children: Vec<Expression> = self_children.iter().zip(used.iter())
+                .filter(|(_,is_used)| !**is_used)
+                .map(|(c,_)| c.clone())
+                .collect();
+            let rest_expr = match rest_children.len() {
+                0 => return None,
+                1 => rest_children.remove(0),
+                2 => expression_builder::binary(&self.symbol, rest_children.remove(0), rest_children.remove(0)),
+                _ => Expression { exp_type: ExpressionType::AssocTrain, symbol: self.symbol.clone(), children: Some(rest_children) },
+            };
+            let rest_map = rest_expr.pattern_match_this_node_with(rest, options)?;
+            if !utils::is_hashmap_no_clash(&map, &rest_map) { return None; }
+            let mut map = map;
+            map.extend(rest_map);
+            return Some(map);
+        }
+        for i in 0..self_children.len() {
+            if used[i] { continue; }
+            let child_map = match self_children[i].pattern_match_this_node_with(fixed[0], options) {
+                Some(child_map) if utils::is_hashmap_no_clash(&map, &child_map) => child_map,
+                _ => continue,
+            };
+            let mut new_map = map.clone();
+            new_map.extend(child_map);
+            used[i] = true;
+            let result = self.f_pattern_match_ac_assignment(&fixed[1..], rest, used, new_map, options);
+            used[i] = false;
+            if result.is_some() { return result; }
+        }
+        return None;
+    }
     
     // apply match map to the expression
     // use case: self is a "rule expression" e.g. X + 0 = X
@@ -923,6 +1105,19 @@ impl Expression {
     pub fn apply_equation_rtl_this_node(&self, equation: &Expression) -> Result<Expression, ExpressionError> {
         return self.apply_equation_ltr_this_node(&equation.flip_equation());
     }
+    /// same as `apply_equation_ltr_this_node`, but the lhs is matched with `pattern_match_this_node_with`
+    pub fn apply_equation_with_options_this_node(&self, equation: &Expression, options: &MatchOptions)
+    -> Result<Expression, ExpressionError> {
+        if !equation.is_equation() { return Err(ExpressionError::NotAnEquation); }
+        let lhs = equation.lhs().ok_or(ExpressionError::InvalidAddress)?;
+        if lhs == self { return equation.rhs().cloned().ok_or(ExpressionError::InvalidAddress); }
+        let match_map = self.pattern_match_this_node_with(lhs, options)
+            .ok_or(ExpressionError::PatternDoesNotMatch)?;
+        let equation = equation.apply_match_map(&match_map);
+        if options.ac_ops.is_empty() { return self.apply_equation_ltr_this_node(&equation); }
+        // the lhs with the match map applied might be grouped differently than `self`
+        return equation.rhs().cloned().ok_or(ExpressionError::InvalidAddress);
+    }
     pub fn apply_equation_ltr_this_node(&self, equation: &Expression) -> Result<Expression, ExpressionError> {
         if !equation.is_equation() { return Err(ExpressionError::NotAnEquation); }
         
@@ -946,27 +1141,60 @@ impl Expression {
         return self.apply_equation_ltr_at(&equation.clone().flip_equation(), addr);
     }
     pub fn apply_equation_ltr_at(&self, equation: &Expression, addr: &Address) -> Result<Expression, ExpressionError> {
+        return self.apply_equation_with_options_at(equation, addr, &MatchOptions::default());
+    }
+    /// same as `apply_equation_ltr_at`, but the lhs is matched with `pattern_match_this_node_with`
+    pub fn apply_equation_with_options_at(&self, equation: &Expression, addr: &Address, options: &MatchOptions)
+    -> Result<Expression, ExpressionError> {
         let expr = self.at(addr)?;
-        if addr.sub.is_none() {
-            let new_expr = expr.apply_equation_this_node(equation)?;
+        if let Some(sub) = addr.sub {
+            // AssocTrain
+            let subexpr = expr.generate_subexpr_from_train(sub)?;
+            let new_expr = subexpr.apply_equation_with_options_this_node(equation, options)?;
             return self.replace_expression_at(new_expr, addr);
         } else {
-            // AssocTrain
-            let subexpr = expr.generate_subexpr_from_train(addr.sub.unwrap())?;
-            let new_expr = subexpr.apply_equation_this_node(equation)?;
+            let new_expr = expr.apply_equation_with_options_this_node(equation, options)?;
             return self.replace_expression_at(new_expr, addr);
         }
     }
     
     pub fn get_possible_equation_application_addresses(&self, equation: &Expression) -> Vec<Address> {
+        return self.get_possible_equation_application_addresses_with(equation, &MatchOptions::default());
+    }
+    /// same as `get_possible_equation_application_addresses`, but matched with `pattern_match_this_node_with`
+    pub fn get_possible_equation_application_addresses_with(&self, equation: &Expression, options: &MatchOptions) -> Vec<Address> {
         if !equation.is_equation() { return vec![]; }
         let eq_children = equation.children.as_ref();
         if eq_children.is_none() { return vec![]; }
         let lhs = &eq_children.unwrap()[0];
-        let pattern_matches = self.get_pattern_matches(lhs);
+        let pattern_matches = self.get_pattern_matches_with(lhs, options);
         return pattern_matches.iter().map(|(addr,_)| addr.clone()).collect()
     }
     
+    /// apply the `equations` (left to right) until none of them can be applied anymore
+    /// * `NOTE`: `ExpressionError::RewriteLimitReached` if they can still be applied after `LIMIT_OF_REWRITE_STEPS` passes
+    pub fn rewrite_to_fixpoint(&self, equations: &[Expression]) -> Result<Expression, ExpressionError> {
+        return self.rewrite_to_fixpoint_with_limit(equations, LIMIT_OF_REWRITE_STEPS);
+    }
+    /// same as `rewrite_to_fixpoint`, with at most `limit` passes
+    pub fn rewrite_to_fixpoint_with_limit(&self, equations: &[Expression], limit: usize) -> Result<Expression, ExpressionError> {
+        let mut expr = self.clone();
+        for _ in 0..limit {
+            let prev_expr = expr.clone();
+            for e in equations {
+                let possible_eq_addr = expr.get_possible_equation_application_addresses(e);
+                if possible_eq_addr.is_empty() { continue; }
+                let addr = possible_eq_addr.first()
+                    .expect("Expression::get_possible_equation_application_address is valid");
+                if let Ok(new_expr) = expr.apply_equation_at(e, addr) {
+                    expr = new_expr;
+                }
+            }
+            if expr == prev_expr { return Ok(expr); }
+        }
+        return Err(ExpressionError::RewriteLimitReached);
+    }
+    
     pub fn apply_implication(&self, implication: &Expression) -> Result<Expression, ExpressionError>{
         if !implication.is_implication() { return Err(ExpressionError::NotAnImplication); }
         
@@ -1077,7 +1305,7 @@ pub mod get_possible_actions {
             let rule = rule_map.get(rule_id).cloned();
             if rule.is_none() { continue; }
             let rule = &rule.unwrap();
-            if let Ok(new_expr) = expr.apply_rule_at(rule, addr) {
+            if let Ok((new_expr, _)) = expr.apply_rule_in_context_at(rule, addr, &context.expression_context) {
                 let action = Action::ApplyRule(rule.label.clone());
                 possible_actions.push((action, new_expr));
             }
@@ -1099,8 +1327,7 @@ pub mod get_possible_actions {
         return vec![(Action::ApplyAction("Flip the equation".to_string()), flipped_expr)];
     }
     
-    // TODO: differentiate between associativity and commutativity
-    pub fn swap_position_in_assoc_train(expr: &Expression, addr_vec: &[Address]) 
+    pub fn swap_position_in_assoc_train(expr: &Expression, context: &WorksheetContext, addr_vec: &[Address]) 
     -> Vec<(Action, Expression)> 
     {
         if addr_vec.len() != 2 { return vec![]; }
@@ -1120,6 +1347,7 @@ pub mod get_possible_actions {
         
         let ancestor_expr = expr.at(&ancestor_addr);
         if ancestor_expr.is_err() { return vec![]; }
+        if !context.expression_context.is_commutative(&ancestor_expr.unwrap().symbol) { return vec![]; }
         
         let new_expr = expr.swap_assoc_train_children_at(index0, index1, &ancestor_addr);
         if new_expr.is_err() { return vec![]; }
@@ -1128,7 +1356,6 @@ pub mod get_possible_actions {
         return vec![(Action::ApplyAction("Reorder".to_string()), new_expr)];
     }
     
-    // TODO: differentiate between associativity and commutativity
     pub fn swap_position_in_comutative_binary(expr: &Expression, context: &WorksheetContext, addr_vec: &[Address]) 
     -> Vec<(Action, Expression)> 
     {
@@ -1143,7 +1370,7 @@ pub mod get_possible_actions {
         
         let ancestor_expr = ancestor_expr.unwrap();
         if ancestor_expr.exp_type != ExpressionType::OperatorBinary { return vec![]; }
-        if !context.expression_context.assoc_ops.contains(&ancestor_expr.symbol) { return vec![]; }
+        if !context.expression_context.is_commutative(&ancestor_expr.symbol) { return vec![]; }
         
         let left = ancestor_expr.children.as_ref().unwrap()[0].clone();
         let right = ancestor_expr.children.as_ref().unwrap()[1].clone();
@@ -1159,7 +1386,7 @@ pub mod get_possible_actions {
         return vec![
             from_rule_map(expr, context, addr_vec),
             swap_position_in_comutative_binary(expr, context, addr_vec),
-            swap_position_in_assoc_train(expr, addr_vec),
+            swap_position_in_assoc_train(expr, context, addr_vec),
             flip_equation(expr, addr_vec),
         ].into_iter().flatten().collect();
     }
@@ -0,0 +1,116 @@
+use serde::{Serialize, Deserialize};
+use crate::worksheet::ExpressionLine;
+
+/// every line of every branch of a sequence, each line is a child of the line it was derived from
+/// * a branch is identified by its last line (a leaf of the tree)
+/// * `NOTE`: lines are never removed when going back, only when the auto rules after a line don't terminate
+#[derive(Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryTree {
+    nodes: Vec<HistoryNode>,
+    /// the last line of the current branch
+    current: Option<usize>,
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryNode {
+    pub line: ExpressionLine,
+    pub parent: Option<usize>,
+    pub children: Vec<usize>,
+}
+
+/// two branches share the lines up to the point where one was forked from the other
+#[derive(Clone, PartialEq)]
+pub struct BranchComparison {
+    /// number of lines that both branches start with
+    pub common_len: usize,
+    pub only_in_first: Vec<ExpressionLine>,
+    pub only_in_second: Vec<ExpressionLine>,
+    /// both branches end with equivalent expressions (`Expression::is_equivalent_to`)
+    pub same_result: bool,
+}
+
+impl HistoryTree {
+    /// a tree with a single branch
+    pub fn from_lines(lines: &[ExpressionLine]) -> HistoryTree {
+        let mut tree = HistoryTree::default();
+        for line in lines { tree.push(line.clone()); }
+        return tree;
+    }
+
+    /// add `line` after the current line, and make it the current line
+    pub fn push(&mut self, line: ExpressionLine) -> usize {
+        let id = self.nodes.len();
+        if let Some(parent) = self.current { self.nodes[parent].children.push(id); }
+        self.nodes.push(HistoryNode { line, parent: self.current, children: vec![] });
+        self.current = Some(id);
+        return id;
+    }
+
+    pub fn get(&self, id: usize) -> Option<&HistoryNode> {
+        return self.nodes.get(id);
+    }
+    pub fn get_line_mut(&mut self, id: usize) -> Option<&mut ExpressionLine> {
+        return self.nodes.get_mut(id).map(|node| &mut node.line);
+    }
+    pub fn len(&self) -> usize {
+        return self.nodes.len();
+    }
+    pub fn is_empty(&self) -> bool {
+        return self.nodes.is_empty();
+    }
+    pub fn lines(&self) -> impl Iterator<Item = &ExpressionLine> {
+        return self.nodes.iter().map(|node| &node.line);
+    }
+
+    pub fn current(&self) -> Option<usize> {
+        return self.current;
+    }
+    /// `false` if there is no line `id`
+    pub fn set_current(&mut self, id: usize) -> bool {
+        if id >= self.nodes.len() { return false; }
+        self.current = Some(id);
+        return true;
+    }
+
+    /// the ids of the lines from the first line to `id`
+    pub fn path_to(&self, id: usize) -> Vec<usize> {
+        let mut path = vec![];
+        let mut node = Some(id).filter(|id| *id < self.nodes.len());
+        while let Some(id) = node {
+            path.push(id);
+            node = self.nodes[id].parent;
+        }
+        path.reverse();
+        return path;
+    }
+    pub fn lines_to(&self, id: usize) -> Vec<ExpressionLine> {
+        return self.path_to(id).into_iter().map(|id| self.nodes[id].line.clone()).collect();
+    }
+
+    /// the ids of the last lines of every branch, in the order the branches were created
+    pub fn branches(&self) -> Vec<usize> {
+        return (0..self.nodes.len()).filter(|id| self.nodes[*id].children.is_empty()).collect();
+    }
+
+    /// `None` if either branch doesn't exist
+    pub fn compare_branches(&self, first: usize, second: usize) -> Option<BranchComparison> {
+        if first >= self.nodes.len() || second >= self.nodes.len() { return None; }
+        let (path0, path1) = (self.path_to(first), self.path_to(second));
+        let common_len = path0.iter().zip(&path1).take_while(|(a, b)| a == b).count();
+        let lines = |path: &[usize]| path.iter().map(|id| self.nodes[*id].line.clone()).collect::<Vec<ExpressionLine>>();
+        let same_result = self.nodes[first].line.expr.is_equivalent_to(&self.nodes[second].line.expr);
+        return Some(BranchComparison {
+            common_len,
+            only_in_first: lines(&path0[common_len..]),
+            only_in_second: lines(&path1[common_len..]),
+            same_result,
+        });
+    }
+
+    /// remove the lines added after the first `len` lines, the current line moves to `current`
+    pub fn truncate(&mut self, len: usize, current: Option<usize>) {
+        self.nodes.truncate(len);
+        for node in self.nodes.iter_mut() { node.children.retain(|id| *id < len); }
+        self.current = current.filter(|id| *id < len);
+    }
+}
@@ -1,12 +1,24 @@
-#![allow(clippy::needless_return)]
+#![allow(clippy::needless_return, clippy::unnecessary_unwrap, clippy::collapsible_match)]
 
 pub mod expression;
 pub mod rule;
 pub mod utils;
+pub mod number;
 pub mod arithmetic;
 pub mod algebra;
 pub mod worksheet;
 pub mod block;
+pub mod registry;
+pub mod search;
+pub mod solver;
+pub mod polynomial;
+pub mod equality;
+pub mod sort;
+pub mod library;
+pub mod validation;
+pub mod confluence;
+pub mod history;
+pub mod verify;
 
 // parser
 pub mod parser;
\ No newline at end of file
@@ -0,0 +1,117 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use crate::rule::{parse_ruleset_from_json_with_loader, FileRulesetLoader, ParserError, Rule, RuleSet, RulesetLoader};
+use crate::validation::ValidationError;
+
+/// a collection of rulesets, indexed by name and by rule id
+/// * the name of a ruleset is the name it is loaded with (for a directory, the file name without `.json`),
+///   the same name used in `imports`, which is not necessarily the `name` in its JSON
+#[derive(Clone, Default)]
+pub struct RuleLibrary {
+    names: Vec<String>,
+    rulesets: HashMap<String, RuleSet>,
+    /// rule id => the names of the rulesets that contain the rule, a rule can be imported by several rulesets
+    rule_index: HashMap<String, Vec<String>>,
+}
+
+#[derive(Debug)]
+pub enum LibraryError {
+    /// the directory (or an entry of it) can't be read
+    Io(String),
+    InvalidRuleset { name: String, error: ParserError },
+    /// the ruleset is parsed, but `RuleSet::validate` fails
+    InvalidRules { name: String, errors: Vec<ValidationError> },
+}
+impl fmt::Display for LibraryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LibraryError::Io(err) => write!(f, "io error: {}", err),
+            LibraryError::InvalidRuleset { name, error } => write!(f, "{}: {}", name, error),
+            LibraryError::InvalidRules { name, errors } => {
+                let errors = errors.iter().map(|e| e.to_string()).collect::<Vec<String>>();
+                write!(f, "{}: {}", name, errors.join("; "))
+            },
+        }
+    }
+}
+impl From<std::io::Error> for LibraryError {
+    fn from(err: std::io::Error) -> Self {
+        LibraryError::Io(err.to_string())
+    }
+}
+
+impl RuleLibrary {
+    /// load every `*.json` file in `dir`, imports are resolved from the same directory, see `load`
+    pub fn load_dir(dir: impl AsRef<Path>) -> (RuleLibrary, Vec<LibraryError>) {
+        let dir = dir.as_ref();
+        let mut names = Vec::new();
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(err) => return (RuleLibrary::default(), vec![LibraryError::from(err)]),
+        };
+        let mut errors = Vec::new();
+        for entry in entries {
+            let path = match entry {
+                Ok(entry) => entry.path(),
+                Err(err) => { errors.push(LibraryError::from(err)); continue; },
+            };
+            if !path.is_file() || path.extension().and_then(|ext| ext.to_str()) != Some("json") { continue; }
+            if let Some(name) = path.file_stem().and_then(|s| s.to_str()) {
+                names.push(name.to_string());
+            }
+        }
+        let (library, load_errors) = RuleLibrary::load(&names, &FileRulesetLoader::new(dir));
+        errors.extend(load_errors);
+        return (library, errors);
+    }
+
+    /// load the rulesets named `names` from `loader`, each ruleset is also checked with `RuleSet::validate`
+    /// * `NOTE`: the invalid rulesets are left out of the library, and all of them are reported, not only the first one
+    pub fn load(names: &[String], loader: &dyn RulesetLoader) -> (RuleLibrary, Vec<LibraryError>) {
+        let mut names = names.to_vec();
+        names.sort();
+        names.dedup();
+
+        let mut library = RuleLibrary::default();
+        let mut errors = Vec::new();
+        for name in names {
+            let ruleset = loader.load(&name)
+                .and_then(|json| parse_ruleset_from_json_with_loader(&json, loader));
+            match ruleset.map(|ruleset| (ruleset.validate(), ruleset)) {
+                Ok((Ok(()), ruleset)) => library.insert(name, ruleset),
+                Ok((Err(validation_errors), _)) => errors.push(LibraryError::InvalidRules { name, errors: validation_errors }),
+                Err(error) => errors.push(LibraryError::InvalidRuleset { name, error }),
+            }
+        }
+        return (library, errors);
+    }
+
+    fn insert(&mut self, name: String, ruleset: RuleSet) {
+        for id in ruleset.rule_ids.iter() {
+            self.rule_index.entry(id.clone()).or_default().push(name.clone());
+        }
+        self.names.push(name.clone());
+        self.rulesets.insert(name, ruleset);
+    }
+
+    /// the names of the rulesets, sorted
+    pub fn names(&self) -> &[String] {
+        return &self.names;
+    }
+
+    pub fn get_ruleset(&self, name: &str) -> Option<&RuleSet> {
+        return self.rulesets.get(name);
+    }
+
+    /// the rule with the id `id`, from the first ruleset (by name) that contains it
+    pub fn get_rule(&self, id: &str) -> Option<&Rule> {
+        let name = self.rule_index.get(id)?.first()?;
+        return self.rulesets.get(name)?.rule_vec.iter().find(|rule| rule.id == id);
+    }
+
+    /// the names of the rulesets that contain the rule with the id `id`
+    pub fn get_rulesets_of_rule(&self, id: &str) -> Vec<String> {
+        return self.rule_index.get(id).cloned().unwrap_or_default();
+    }
+}
@@ -1,33 +1,33 @@
 use std::env;
-use equaio::rule;
+use equaio::library::RuleLibrary;
 
 fn main() {
     let args: Vec<String> = env::args().collect();
+    
+    // the rulesets that fail to load are reported, the others are still available
+    let (library, errors) = RuleLibrary::load_dir("rules");
+    for error in errors.iter() {
+        println!("Error loading rules: {}", error);
+    }
+    
     if args.len() < 2 {
         println!("Usage: {} rulename", args[0]);
+        println!("Available rulesets: {}", library.names().join(", "));
         return;
     }
     
     let rulename = &args[1];
     println!("Rulename: {}", rulename);
     
-    let filepath = format!("rules/{}.json", rulename);
-    let rulestr = std::fs::read_to_string(&filepath);
-    if rulestr.is_err() {
-        println!("Error reading file: {}", &filepath);
-        return;
-    }
-    let rulestr = rulestr.unwrap();
-    
-    let ruleset = rule::parse_ruleset_from_json(&rulestr);
-    if ruleset.is_err() {
-        println!("Error parsing rule: {}", &filepath);
+    let ruleset = library.get_ruleset(rulename);
+    if ruleset.is_none() {
+        println!("Ruleset not found: {}", rulename);
         return;
     }
     let ruleset = ruleset.unwrap();
     
-    let rule_vec = ruleset.rule_vec;
-    let ctx = ruleset.context;
+    let rule_vec = &ruleset.rule_vec;
+    let ctx = &ruleset.context;
         
     println!("Context:");
     println!("{:?}", ctx);
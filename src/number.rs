@@ -0,0 +1,270 @@
+use std::cmp::Ordering;
+use std::fmt;
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_traits::{One, Pow, Signed, ToPrimitive, Zero};
+use crate::expression::{Expression, expression_builder as eb};
+
+/// exponents above this are not calculated exactly, to avoid building enormous integers
+const LIMIT_OF_EXACT_EXPONENT: u32 = 4096;
+/// logarithms are only calculated exactly if their denominator is at most this
+const LIMIT_OF_EXACT_LOG_DENOMINATOR: u32 = 64;
+
+/// exact fraction `numer / denom`, always reduced and with `denom > 0`
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Rational {
+    numer: BigInt,
+    denom: BigInt,
+}
+
+impl Rational {
+    /// `None` if `denom` is zero
+    pub fn new(numer: impl Into<BigInt>, denom: impl Into<BigInt>) -> Option<Rational> {
+        let (numer, denom) = (numer.into(), denom.into());
+        if denom.is_zero() { return None; }
+        let g = numer.gcd(&denom);
+        let sign = if denom.is_negative() { -BigInt::one() } else { BigInt::one() };
+        return Some(Rational {
+            numer: numer / &g * &sign,
+            denom: denom / &g * &sign,
+        });
+    }
+    pub fn from_integer(n: impl Into<BigInt>) -> Rational {
+        return Rational { numer: n.into(), denom: BigInt::one() };
+    }
+    pub fn numer(&self) -> &BigInt { return &self.numer; }
+    pub fn denom(&self) -> &BigInt { return &self.denom; }
+    pub fn is_integer(&self) -> bool { return self.denom.is_one(); }
+    pub fn to_f64(&self) -> f64 {
+        let to_f64 = |n: &BigInt| n.to_f64().unwrap_or(f64::NAN);
+        return to_f64(&self.numer) / to_f64(&self.denom);
+    }
+
+    pub fn checked_add(&self, other: &Rational) -> Option<Rational> {
+        let numer = &self.numer * &other.denom + &other.numer * &self.denom;
+        return Rational::new(numer, &self.denom * &other.denom);
+    }
+    pub fn checked_sub(&self, other: &Rational) -> Option<Rational> {
+        return self.checked_add(&other.checked_neg()?);
+    }
+    pub fn checked_mul(&self, other: &Rational) -> Option<Rational> {
+        return Rational::new(&self.numer * &other.numer, &self.denom * &other.denom);
+    }
+    /// `None` if `other` is zero
+    pub fn checked_div(&self, other: &Rational) -> Option<Rational> {
+        return Rational::new(&self.numer * &other.denom, &self.denom * &other.numer);
+    }
+    pub fn checked_neg(&self) -> Option<Rational> {
+        return Some(Rational { numer: -&self.numer, denom: self.denom.clone() });
+    }
+    /// `None` if the exponent is too large, or if raising zero to a negative exponent
+    pub fn checked_pow(&self, exp: &BigInt) -> Option<Rational> {
+        let e = exp.magnitude().to_u32().filter(|e| *e <= LIMIT_OF_EXACT_EXPONENT)?;
+        let result = Rational::new(Pow::pow(&self.numer, e), Pow::pow(&self.denom, e))?;
+        if exp.is_negative() { return Rational::from_integer(1).checked_div(&result); }
+        return Some(result);
+    }
+    /// `None` if the root is not rational
+    pub fn checked_nth_root(&self, n: u32) -> Option<Rational> {
+        if n == 0 || (self.numer.is_negative() && n.is_multiple_of(2)) { return None; }
+        let (numer, denom) = (self.numer.nth_root(n), self.denom.nth_root(n));
+        if Pow::pow(&numer, n) != self.numer || Pow::pow(&denom, n) != self.denom { return None; }
+        return Rational::new(numer, denom);
+    }
+}
+
+impl fmt::Display for Rational {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_integer() { return write!(f, "{}", self.numer); }
+        return write!(f, "{}/{}", self.numer, self.denom);
+    }
+}
+
+/// the value of a numeric expression,
+/// integers (of any size), fractions and decimal literals (ex: `0.5` is `1/2`) are exact,
+/// inexact results (ex: `sqrt(2)`) are kept as `f64`
+/// * `NOTE`: an operation with a decimal operand results in a decimal
+#[derive(Debug, Clone)]
+pub enum Number {
+    Rational(Rational),
+    Decimal(f64),
+}
+
+impl Number {
+    pub fn integer(n: impl Into<BigInt>) -> Number {
+        return Number::Rational(Rational::from_integer(n));
+    }
+
+    /// parse a constant symbol, finite decimal literals (ex: `0.1` or `2.5e-3`) are parsed exactly
+    /// * `NOTE`: other symbols that `f64` can parse (ex: `inf`, or a too large exponent) are parsed as decimals
+    pub fn parse(symbol: &str) -> Option<Number> {
+        if let Ok(n) = symbol.parse::<BigInt>() { return Some(Number::integer(n)); }
+        if let Some(r) = parse_decimal_literal(symbol) { return Some(Number::Rational(r)); }
+        return symbol.parse::<f64>().ok().map(Number::Decimal);
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        return match self {
+            Number::Rational(r) => r.to_f64(),
+            Number::Decimal(d) => *d,
+        };
+    }
+    pub fn is_zero(&self) -> bool {
+        return match self {
+            Number::Rational(r) => r.numer.is_zero(),
+            Number::Decimal(d) => *d == 0.0,
+        };
+    }
+    pub fn is_one(&self) -> bool {
+        return match self {
+            Number::Rational(r) => r.numer == r.denom,
+            Number::Decimal(d) => *d == 1.0,
+        };
+    }
+    pub fn is_negative(&self) -> bool {
+        return match self {
+            Number::Rational(r) => r.numer.is_negative(),
+            Number::Decimal(d) => *d < 0.0,
+        };
+    }
+    pub fn is_integer(&self) -> bool {
+        return match self {
+            Number::Rational(r) => r.is_integer(),
+            Number::Decimal(d) => d.fract() == 0.0,
+        };
+    }
+
+    fn operate(&self, other: &Number,
+        rational_op: fn(&Rational, &Rational) -> Option<Rational>, decimal_op: fn(f64, f64) -> f64
+    ) -> Option<Number> {
+        return match (self, other) {
+            (Number::Rational(a), Number::Rational(b)) => rational_op(a, b).map(Number::Rational),
+            _ => Some(Number::Decimal(decimal_op(self.to_f64(), other.to_f64()))),
+        };
+    }
+    pub fn checked_add(&self, other: &Number) -> Option<Number> {
+        return self.operate(other, Rational::checked_add, |a, b| a + b);
+    }
+    pub fn checked_sub(&self, other: &Number) -> Option<Number> {
+        return self.operate(other, Rational::checked_sub, |a, b| a - b);
+    }
+    pub fn checked_mul(&self, other: &Number) -> Option<Number> {
+        return self.operate(other, Rational::checked_mul, |a, b| a * b);
+    }
+    /// `None` if dividing an exact number by zero
+    pub fn checked_div(&self, other: &Number) -> Option<Number> {
+        return self.operate(other, Rational::checked_div, |a, b| a / b);
+    }
+    pub fn checked_neg(&self) -> Option<Number> {
+        return match self {
+            Number::Rational(r) => r.checked_neg().map(Number::Rational),
+            Number::Decimal(d) => Some(Number::Decimal(-d)),
+        };
+    }
+
+    /// exact if the result is rational, ex: `4 ^ (3/2) = 8`
+    pub fn checked_pow(&self, exp: &Number) -> Option<Number> {
+        if let (Number::Rational(base), Number::Rational(exp)) = (self, exp) {
+            let exact = exp.denom().to_u32()
+                .and_then(|n| base.checked_nth_root(n))
+                .and_then(|root| root.checked_pow(exp.numer()));
+            if let Some(exact) = exact { return Some(Number::Rational(exact)); }
+            if base.numer().is_zero() && exp.numer().is_negative() { return None; }
+        }
+        return Number::decimal(self.to_f64().powf(exp.to_f64()));
+    }
+    /// `n`-th root, with `n` a non-zero integer
+    pub fn checked_root(&self, n: &Number) -> Option<Number> {
+        if !n.is_integer() || n.is_zero() { return None; }
+        return self.checked_pow(&Number::integer(1).checked_div(n)?);
+    }
+    pub fn checked_sqrt(&self) -> Option<Number> {
+        return self.checked_root(&Number::integer(2));
+    }
+    /// logarithm with base `base`, exact if the result is rational, ex: `log(2, 8) = 3` and `log(4, 2) = 1/2`
+    pub fn checked_log(&self, base: &Number) -> Option<Number> {
+        if self.is_zero() || self.is_negative() || base.is_zero() || base.is_negative() || base.is_one() { return None; }
+        let result = self.to_f64().ln() / base.to_f64().ln();
+        if let (Number::Rational(x), Number::Rational(b)) = (self, base) {
+            // `log(b, x) = p/q` exactly if `x ^ q = b ^ p`
+            for q in 1..=LIMIT_OF_EXACT_LOG_DENOMINATOR {
+                let p = result * q as f64;
+                if !p.is_finite() || (p - p.round()).abs() > 1e-9 * q as f64 { continue; }
+                let p = BigInt::from(p.round() as i64);
+                let is_exact = x.checked_pow(&BigInt::from(q)).is_some_and(|x_q| b.checked_pow(&p).is_some_and(|b_p| b_p == x_q));
+                if is_exact { return Rational::new(p, q).map(Number::Rational); }
+            }
+        }
+        return Number::decimal(result);
+    }
+    pub fn checked_ln(&self) -> Option<Number> {
+        if self.to_f64() <= 0.0 { return None; }
+        if self.is_one() { return Some(Number::integer(0)); }
+        return Number::decimal(self.to_f64().ln());
+    }
+    pub fn checked_reciprocal(&self) -> Option<Number> {
+        return Number::integer(1).checked_div(self);
+    }
+    /// `None` for `NaN` and infinities
+    fn decimal(value: f64) -> Option<Number> {
+        if !value.is_finite() { return None; }
+        return Some(Number::Decimal(value));
+    }
+
+    /// a constant, or a `/` expression of two integer constants for a fraction
+    pub fn to_expression(&self) -> Expression {
+        return match self {
+            Number::Rational(r) if !r.is_integer() => eb::binary("/",
+                eb::constant(&r.numer().to_string()), eb::constant(&r.denom().to_string())),
+            _ => eb::constant(&self.to_string()),
+        };
+    }
+}
+
+/// `digits[.digits][e[+-]digits]` with an optional sign, as an exact fraction
+fn parse_decimal_literal(symbol: &str) -> Option<Rational> {
+    let (mantissa, exponent) = match symbol.split_once(['e', 'E']) {
+        Some((mantissa, exponent)) => (mantissa, exponent.parse::<i64>().ok()?),
+        None => (symbol, 0),
+    };
+    let (sign, mantissa) = match mantissa.strip_prefix('-') {
+        Some(rest) => (-1, rest),
+        None => (1, mantissa.strip_prefix('+').unwrap_or(mantissa)),
+    };
+    let (int_part, frac_part) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let is_digits = |s: &str| s.chars().all(|c| c.is_ascii_digit());
+    if int_part.len() + frac_part.len() == 0 || !is_digits(int_part) || !is_digits(frac_part) { return None; }
+    let digits = format!("{}{}", int_part, frac_part).parse::<BigInt>().ok()?;
+    let scale = exponent.checked_sub(frac_part.len() as i64)?;
+    let power = Pow::pow(BigInt::from(10), scale.unsigned_abs().to_u32().filter(|e| *e <= LIMIT_OF_EXACT_EXPONENT)?);
+    let numer = digits * sign;
+    if scale >= 0 { return Some(Rational::from_integer(numer * power)); }
+    return Rational::new(numer, power);
+}
+
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Number::Rational(r) => write!(f, "{}", r),
+            // avoid `-0`
+            Number::Decimal(d) if *d == 0.0 => write!(f, "0"),
+            Number::Decimal(d) => write!(f, "{}", d),
+        }
+    }
+}
+
+impl PartialEq for Number {
+    fn eq(&self, other: &Self) -> bool {
+        return self.partial_cmp(other) == Some(Ordering::Equal);
+    }
+}
+
+impl PartialOrd for Number {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        if let (Number::Rational(a), Number::Rational(b)) = (self, other) {
+            // denominators are positive
+            return Some((&a.numer * &b.denom).cmp(&(&b.numer * &a.denom)));
+        }
+        return self.to_f64().partial_cmp(&other.to_f64());
+    }
+}
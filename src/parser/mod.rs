@@ -1,3 +1,4 @@
+pub mod parse_error;
 pub mod parser_prefix;
 #[allow(clippy::module_inception)]
 pub mod parser;
\ No newline at end of file
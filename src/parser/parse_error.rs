@@ -0,0 +1,58 @@
+use std::fmt;
+use std::ops::Range;
+
+/// byte range in the parsed text
+pub type Span = Range<usize>;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ParseErrorKind {
+    UnbalancedParenthesis,
+    MissingOperand,
+    UnknownOperator,
+    MisplacedVariadic,
+    UnexpectedToken,
+    EmptyExpression,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    pub span: Span,
+    pub token: String,
+}
+
+impl fmt::Display for ParseErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+impl ParseErrorKind {
+    pub fn as_str(&self) -> &str {
+        match self {
+            ParseErrorKind::UnbalancedParenthesis => "unbalanced parenthesis",
+            ParseErrorKind::MissingOperand => "missing operand",
+            ParseErrorKind::UnknownOperator => "unknown operator",
+            ParseErrorKind::MisplacedVariadic => "misplaced `...`",
+            ParseErrorKind::UnexpectedToken => "unexpected token",
+            ParseErrorKind::EmptyExpression => "empty expression",
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}..{}", self.kind, self.span.start, self.span.end)?;
+        if !self.token.is_empty() { write!(f, " (`{}`)", self.token)?; }
+        Ok(())
+    }
+}
+
+impl ParseError {
+    pub fn new(kind: ParseErrorKind, span: Span, token: impl ToString) -> Self {
+        return ParseError { kind, span, token: token.to_string() };
+    }
+    /// the part of `text` that this error points to
+    pub fn source_in<'a>(&self, text: &'a str) -> &'a str {
+        return text.get(self.span.clone()).unwrap_or("");
+    }
+}
@@ -1,51 +1,68 @@
 use std::str::FromStr;
 use super::super::expression::{Expression, ExpressionType, Context, StatementSymbols, expression_builder as eb};
-use super::parser_prefix::{Token, tokenize, get_value_expression};
+use super::parse_error::{ParseError, ParseErrorKind, Span};
+use super::parser_prefix::{Token, SpannedToken, tokenize_with_spans, check_parentheses, get_value_expression};
 
 #[derive(Debug, Clone)]
 pub enum TokenItem {
-    Token(Token),
-    Group(Vec<TokenItem>),
+    Token(Token, Span),
+    Group(Vec<TokenItem>, Span), // the span includes the parentheses
 }
 impl TokenItem {
     pub fn get_symbol(&self) -> String {
-        if let TokenItem::Token(Token::Symbol(s)) = self { return s.clone(); }
+        if let TokenItem::Token(Token::Symbol(s), _) = self { return s.clone(); }
         return "".to_string();
     }
+    pub fn span(&self) -> Span {
+        match self {
+            TokenItem::Token(_, span) | TokenItem::Group(_, span) => span.clone(),
+        }
+    }
+    /// the text of the token, groups are shown as `(...)`
+    pub fn as_str(&self) -> &str {
+        match self {
+            TokenItem::Token(t, _) => t.as_str(),
+            TokenItem::Group(_, _) => "(...)",
+        }
+    }
     pub fn is_group(&self) -> bool {
-        if let TokenItem::Group(_) = self { return true; }
+        if let TokenItem::Group(_, _) = self { return true; }
         return false;
     }
     pub fn is_comma(&self) -> bool {
-        if let TokenItem::Token(t) = self { return t == &Token::Comma; }
+        if let TokenItem::Token(t, _) = self { return t == &Token::Comma; }
         return false;
     }
-    
+
     pub fn is_function_parameter(&self) -> bool {
-        if let TokenItem::Group(group) = self {
+        if let TokenItem::Group(group, _) = self {
             return group.iter().any(|t| t.is_comma());
         }
         return false;
     }
-    
+
     pub fn is_simple_value(&self, ctx: &Context) -> bool {
-        return !(self.is_group() || self.is_unary_op(ctx) || self.is_effectively_binary_op(ctx));
+        return !(self.is_group() || self.is_comma() || self.is_unary_op(ctx) || self.is_effectively_binary_op(ctx));
     }
-    
+
     pub fn is_unary_op(&self, ctx: &Context) -> bool {
-        if let TokenItem::Token(Token::Symbol(s)) = self {
+        if let TokenItem::Token(Token::Symbol(s), _) = self {
             if ctx.unary_ops.contains(s) { return true; }
         }
         return false;
     }
     pub fn is_effectively_binary_op(&self, ctx: &Context) -> bool {
-        if let TokenItem::Token(Token::Symbol(s)) = self {
+        if let TokenItem::Token(Token::Symbol(s), _) = self {
             if ctx.binary_ops.contains(s) { return true; }
             if ctx.assoc_ops.contains(s) { return true; }
             if StatementSymbols::from_str(s.as_str()).is_ok() { return true; }
         }
         return false;
     }
+
+    fn error(&self, kind: ParseErrorKind) -> ParseError {
+        return ParseError::new(kind, self.span(), self.as_str());
+    }
 }
 
 // 2 + f(x + 3) = -(x + 70)
@@ -59,46 +76,84 @@ impl TokenItem {
 ///     2  + f(x + g(x,y))   = -(x + 70)
 /// -> [2] [+] [f] [(x+g(x,y))] [=] [-] [(x+70)]
 /// ->              [x] [+] [g] [(x,y)]
-fn group_tokens_by_parentheses(tokens: &[Token]) -> TokenItem {
+/// * `NOTE`: the parentheses must be balanced (see `check_parentheses`)
+fn group_tokens_by_parentheses(tokens: &[SpannedToken], span: Span) -> TokenItem {
     let mut result: Vec<TokenItem> = Vec::new();
-    let mut current: Vec<Token> = Vec::new();
+    let mut current: Vec<SpannedToken> = Vec::new();
     let mut paren_count = 0;
-    for t in tokens {
+    let mut group_start = 0;
+    for (t, t_span) in tokens {
         match t {
-            Token::OpenParen => { 
-                if paren_count != 0 { current.push(t.clone()); }
-                paren_count += 1; 
+            Token::OpenParen => {
+                if paren_count != 0 { current.push((t.clone(), t_span.clone())); }
+                if paren_count == 0 { group_start = t_span.start; }
+                paren_count += 1;
             },
-            Token::CloseParen => { 
-                paren_count -= 1; 
-                if paren_count != 0 {  current.push(t.clone()); }
+            Token::CloseParen => {
+                paren_count -= 1;
+                if paren_count != 0 {  current.push((t.clone(), t_span.clone())); }
                 if paren_count == 0 {
-                    result.push(group_tokens_by_parentheses(&current));
+                    result.push(group_tokens_by_parentheses(&current, group_start..t_span.end));
                     current.clear();
                 }
             },
             _ => {
                 if paren_count != 0 {
-                    current.push(t.clone());
+                    current.push((t.clone(), t_span.clone()));
                 } else {
-                    result.push(TokenItem::Token(t.clone()));
+                    result.push(TokenItem::Token(t.clone(), t_span.clone()));
                     current.clear();
                 }
             },
         }
     }
-    return TokenItem::Group(result);
+    return TokenItem::Group(result, span);
+}
+
+/// split each symbol token after every occurence of a unary operator
+/// -b => - b
+/// -a + -b => - a + - b
+/// * `NOTE`: named unary ops are only split when they are the rest of the token (ex: `-sqrt(x)`, but not `lnx`)
+fn split_unary_ops(tokens: Vec<SpannedToken>, ctx: &Context) -> Vec<SpannedToken> {
+    let mut result = Vec::new();
+    for (t, span) in tokens {
+        let symbol = match &t {
+            Token::Symbol(s) => s,
+            _ => { result.push((t, span)); continue; },
+        };
+        let mut piece_start = 0;
+        let mut i = 0;
+        while i < symbol.len() {
+            let op = ctx.unary_ops.iter().find(|op| {
+                if op.is_empty() { return false; }
+                if op.chars().all(char::is_alphabetic) { return i == piece_start && &symbol[i..] == op.as_str(); }
+                return symbol[i..].starts_with(op.as_str());
+            });
+            if let Some(op) = op {
+                i += op.len();
+                result.push((Token::Symbol(symbol[piece_start..i].to_string()), span.start+piece_start..span.start+i));
+                piece_start = i;
+            } else {
+                i += symbol[i..].chars().next().map(|c| c.len_utf8()).unwrap_or(1);
+            }
+        }
+        if piece_start < symbol.len() {
+            result.push((Token::Symbol(symbol[piece_start..].to_string()), span.start+piece_start..span.end));
+        }
+    }
+    return result;
 }
 
 #[derive(Debug)]
 enum SemanticSymbol {
     Value(String),
-    ValueGroup(Vec<SemanticSymbol>), // expression inside parentheses (practically is a Value)
-    UnaryOp(String, Vec<SemanticSymbol>),
+    ValueGroup(Vec<SpannedSemanticSymbol>), // expression inside parentheses (practically is a Value)
+    UnaryOp(String, Vec<SpannedSemanticSymbol>),
     BinaryOp(String),
-    Nary(String, Vec<Vec<SemanticSymbol>>),
+    Nary(String, Vec<Vec<SpannedSemanticSymbol>>),
     Variadic,
 }
+type SpannedSemanticSymbol = (SemanticSymbol, Span);
 
 #[allow(clippy::enum_variant_names)]
 enum SemanticParsingState {
@@ -118,53 +173,64 @@ impl SemanticSymbol {
         }
     }
 }
-fn parse_semantic_symbol(token_items: &[TokenItem], ctx: &Context) -> Option<Vec<SemanticSymbol>> {
-    let mut result: Vec<SemanticSymbol> = Vec::new();
+
+/// `span` is the span of the whole `token_items`, used for reporting empty expressions
+fn parse_semantic_symbol(token_items: &[TokenItem], span: &Span, ctx: &Context)
+-> Result<Vec<SpannedSemanticSymbol>, ParseError> {
+    let mut result: Vec<SpannedSemanticSymbol> = Vec::new();
     let mut state = SemanticParsingState::LookingForLeftElement;
     let mut temp_nary_token: Option<&TokenItem> = None;
     let mut temp_unary_token: Option<&TokenItem> = None;
-    
+
+    if token_items.is_empty() {
+        return Err(ParseError::new(ParseErrorKind::EmptyExpression, span.clone(), ""));
+    }
+
     let mut iter = token_items.iter().peekable();
     while let Some(t) = iter.next() {
-        // dbg!(&result);
         match state {
             SemanticParsingState::LookingForNaryParameter => {
-                if let TokenItem::Group(group) = t {
+                let nary_token = temp_nary_token.ok_or(t.error(ParseErrorKind::UnexpectedToken))?;
+                if let TokenItem::Group(group, group_span) = t {
                     let params = split_token_item_by_comma(group);
-                    let params_semantic = params.iter().map(|p| parse_semantic_symbol(p, ctx))
-                        .collect::<Option<Vec<Vec<SemanticSymbol>>>>();
-                    if temp_nary_token.is_none() || params_semantic.is_none() { return None; }
-                    let nary_symbol = temp_nary_token.unwrap().get_symbol();
-                    result.push(SemanticSymbol::Nary(nary_symbol, params_semantic.unwrap()));
+                    let mut params_semantic = Vec::new();
+                    let mut param_start = group_span.start + 1;
+                    for p in &params {
+                        let param_end = p.last().map(|t| t.span().end).unwrap_or(param_start);
+                        params_semantic.push(parse_semantic_symbol(p, &(param_start..param_end), ctx)?);
+                        // skip the comma
+                        param_start = param_end + 1;
+                    }
+                    let nary_symbol = nary_token.get_symbol();
+                    let nary_span = nary_token.span().start..group_span.end;
+                    result.push((SemanticSymbol::Nary(nary_symbol, params_semantic), nary_span));
                     temp_nary_token = None;
                     state = SemanticParsingState::LookingForBinaryOp;
                     continue;
                 } else {
-                    return None;
+                    return Err(t.error(ParseErrorKind::UnexpectedToken));
                 }
             },
             SemanticParsingState::LookingForUnaryParameter => {
-                temp_unary_token.as_ref()?;
-                let unary_symbol = temp_unary_token.unwrap().get_symbol();
-                
+                let unary_token = temp_unary_token.ok_or(t.error(ParseErrorKind::UnexpectedToken))?;
+                let unary_symbol = unary_token.get_symbol();
+                let unary_span = unary_token.span().start..t.span().end;
+
                 if t.is_simple_value(ctx) {
                     let value = SemanticSymbol::value_or_variadic(t.get_symbol());
-                    result.push(SemanticSymbol::UnaryOp(unary_symbol, vec![value]));
+                    result.push((SemanticSymbol::UnaryOp(unary_symbol, vec![(value, t.span())]), unary_span));
+                    temp_unary_token = None;
+                    state = SemanticParsingState::LookingForBinaryOp;
+                    continue;
+                } else if let TokenItem::Group(group, group_span) = t {
+                    let group_semantic = parse_semantic_symbol(group, group_span, ctx)?;
+                    let value = SemanticSymbol::ValueGroup(group_semantic);
+                    result.push((SemanticSymbol::UnaryOp(unary_symbol, vec![(value, group_span.clone())]), unary_span));
                     temp_unary_token = None;
                     state = SemanticParsingState::LookingForBinaryOp;
                     continue;
-                } else if let TokenItem::Group(group) = t {
-                    if let Some(group_semantic) = parse_semantic_symbol(group, ctx) {
-                        let value = SemanticSymbol::ValueGroup(group_semantic);
-                        result.push(SemanticSymbol::UnaryOp(unary_symbol, vec![value]));
-                        temp_unary_token = None;
-                        state = SemanticParsingState::LookingForBinaryOp;
-                        continue;
-                    } else {
-                        return None;
-                    }
                 } else {
-                    return None;
+                    return Err(t.error(ParseErrorKind::MissingOperand));
                 }
             },
             SemanticParsingState::LookingForLeftElement => {
@@ -173,40 +239,51 @@ fn parse_semantic_symbol(token_items: &[TokenItem], ctx: &Context) -> Option<Vec
                     temp_unary_token = Some(t);
                     state = SemanticParsingState::LookingForUnaryParameter;
                     continue;
-                } else if iter.peek().is_some() && iter.peek().unwrap().is_group() {
+                } else if t.is_effectively_binary_op(ctx) || t.is_comma() {
+                    // 2 + * x
+                    return Err(t.error(ParseErrorKind::MissingOperand));
+                } else if !t.is_group() && iter.peek().is_some_and(|next| next.is_group()) {
                     // f(x,y) + 3
                     temp_nary_token = Some(t);
                     state = SemanticParsingState::LookingForNaryParameter;
                     continue;
-                } else if let TokenItem::Group(group) = t {
+                } else if let TokenItem::Group(group, group_span) = t {
                     // (2 * 3) + f(x)
-                    if let Some(group_semantic) = parse_semantic_symbol(group, ctx) {
-                        result.push(SemanticSymbol::ValueGroup(group_semantic));
-                        state = SemanticParsingState::LookingForBinaryOp;
-                        continue;
-                    } else {
-                        return None;
-                    }
+                    let group_semantic = parse_semantic_symbol(group, group_span, ctx)?;
+                    result.push((SemanticSymbol::ValueGroup(group_semantic), group_span.clone()));
+                    state = SemanticParsingState::LookingForBinaryOp;
+                    continue;
                 } else {
                     // 2 + f(x)
-                    result.push(SemanticSymbol::value_or_variadic(t.get_symbol()));
+                    result.push((SemanticSymbol::value_or_variadic(t.get_symbol()), t.span()));
                     state = SemanticParsingState::LookingForBinaryOp;
                     continue;
                 }
             },
             SemanticParsingState::LookingForBinaryOp => {
                 if t.is_effectively_binary_op(ctx) {
-                    result.push(SemanticSymbol::BinaryOp(t.get_symbol()));
+                    result.push((SemanticSymbol::BinaryOp(t.get_symbol()), t.span()));
                     state = SemanticParsingState::LookingForLeftElement;
                     continue;
                 } else {
-                    return None;
+                    return Err(t.error(ParseErrorKind::UnknownOperator));
                 }
-                
+
             }
         }
     }
-    return Some(result);
+
+    match state {
+        SemanticParsingState::LookingForBinaryOp => Ok(result),
+        SemanticParsingState::LookingForUnaryParameter | SemanticParsingState::LookingForNaryParameter => {
+            let dangling = temp_unary_token.or(temp_nary_token).or(token_items.last()).expect("token_items is not empty");
+            Err(dangling.error(ParseErrorKind::MissingOperand))
+        },
+        SemanticParsingState::LookingForLeftElement => {
+            let dangling = token_items.last().expect("token_items is not empty");
+            Err(dangling.error(ParseErrorKind::MissingOperand))
+        },
+    }
 }
 
 pub fn split_token_item_by_comma(tokens: &Vec<TokenItem>) -> Vec<Vec<TokenItem>> {
@@ -215,186 +292,218 @@ pub fn split_token_item_by_comma(tokens: &Vec<TokenItem>) -> Vec<Vec<TokenItem>>
     let mut paren_count = 0;
     for t in tokens {
         match t {
-            TokenItem::Token(Token::OpenParen) => { paren_count += 1; current.push(t.clone()); },
-            TokenItem::Token(Token::CloseParen) => { paren_count -= 1; current.push(t.clone()); },
-            TokenItem::Token(Token::Comma) => {
-                if paren_count == 0 {
-                    result.push(current);
-                    current = Vec::new();
-                } else {
-                    current.push(t.clone());
-                }
+            TokenItem::Token(Token::OpenParen, _) => { paren_count += 1; current.push(t.clone()); },
+            TokenItem::Token(Token::CloseParen, _) => { paren_count -= 1; current.push(t.clone()); },
+            TokenItem::Token(Token::Comma, _) if paren_count == 0 => {
+                result.push(current);
+                current = Vec::new();
             },
             _ => {
                 current.push(t.clone());
             },
         }
     }
-    if !current.is_empty() { result.push(current); }
+    if !current.is_empty() || !result.is_empty() { result.push(current); }
     result
 }
 
-#[derive(PartialEq)]
-enum ExpressionParsingState {
-    Initial,
-    AfterValue,
-    AfterOp,
+enum Operand {
+    Expr(Expression),
+    Variadic,
 }
+type SpannedOperand = (Operand, Span);
+/// an operand with the symbolic prefix unary op (ex: `-`) in front of it, see `InfixParser::next_operand`
+type PrefixedOperand = (Option<(String, Span)>, SpannedOperand);
 
-fn semantic_to_expression(semantic: &[SemanticSymbol], ctx: &Context) -> Option<Expression> {
-    let mut left_expr: Option<Expression> = None;
-    let mut op_symbol: Option<String> = None;
-    let mut state: ExpressionParsingState = ExpressionParsingState::Initial;
-    for s in semantic {
-        match state {
-            ExpressionParsingState::Initial => {
-                match s {
-                    SemanticSymbol::Value(v) => {
-                        left_expr = Some(get_value_expression(v, ctx));
-                        state = ExpressionParsingState::AfterValue;
-                    },
-                    SemanticSymbol::ValueGroup(group) => {
-                        left_expr = semantic_to_expression(group, ctx);
-                        state = ExpressionParsingState::AfterValue;
-                    },
-                    SemanticSymbol::UnaryOp(op,param) => {
-                        let child = semantic_to_expression(param, ctx);
-                        left_expr = Some(eb::unary(op, child.unwrap()));
-                        state = ExpressionParsingState::AfterValue;
-                    },
-                    SemanticSymbol::Nary(op, params) => {
-                        let children = params.iter().map(|p| semantic_to_expression(p, ctx))
-                            .collect::<Option<Vec<Expression>>>();
-                        children.as_ref()?;
-                        left_expr = Some(eb::nary(op, children.unwrap()));
-                        state = ExpressionParsingState::AfterValue;
-                    },
-                    SemanticSymbol::BinaryOp(_) | SemanticSymbol::Variadic => {
-                        // invalid
-                        return None
-                    },
-                }
-            },
-            ExpressionParsingState::AfterValue => {
-                match s {
-                    SemanticSymbol::BinaryOp(v) => {
-                        op_symbol = Some(v.clone());
-                    },
-                    _ => {
-                        return None;
-                    }
-                }
-                state = ExpressionParsingState::AfterOp;
-            },
-            ExpressionParsingState::AfterOp => {
-                let right_expr: Option<Expression> = match s {
-                    SemanticSymbol::Value(v) => {
-                        Some(get_value_expression(v, ctx))
-                    },
-                    SemanticSymbol::ValueGroup(group) => {
-                        semantic_to_expression(group, ctx)
-                    },
-                    SemanticSymbol::UnaryOp(op,param) => {
-                        let child = semantic_to_expression(param, ctx);
-                        Some(eb::unary(op, child.unwrap()))
-                    },
-                    SemanticSymbol::BinaryOp(_) => {
-                        return None;
-                    },
-                    SemanticSymbol::Nary(op, params) => {
-                        let children = params.iter().map(|p| semantic_to_expression(p, ctx))
-                            .collect::<Option<Vec<Expression>>>();
-                        children.as_ref()?;
-                        Some(eb::nary(op, children.unwrap()))
-                    },
-                    SemanticSymbol::Variadic => {
-                        // A + ... => +(...(A))
-                        let symbol = op_symbol.as_ref()?;
-                        if !ctx.assoc_ops.contains(symbol) { return None; }
-                        left_expr.as_ref()?;
-                        
-                        left_expr = Some(eb::unary(symbol, eb::variadic(left_expr.unwrap())));
-                        state = ExpressionParsingState::AfterValue;
-                        // early exit for variadic
-                        continue;
-                    }
-                };
-                
-                right_expr.as_ref()?;
-                op_symbol.as_ref()?;
-                let symbol = op_symbol.clone().unwrap();
-                if left_expr.is_none(){
-                    // unary op
-                    left_expr = Some(eb::unary(symbol.as_str(), right_expr.unwrap()))
+fn semantic_operand_to_expression(symbol: &SpannedSemanticSymbol, ctx: &Context, statement_aware: bool)
+-> Result<PrefixedOperand, ParseError> {
+    let (symbol, span) = symbol;
+    if let SemanticSymbol::UnaryOp(op, param) = symbol {
+        // named unary ops are applied like functions, ex: `sqrt(x) ^ 2`
+        if !op.chars().all(char::is_alphabetic) {
+            let child = semantic_to_expression(param, ctx, statement_aware)?;
+            let child_span = param.first().map(|(_, s)| s.clone()).unwrap_or(span.clone());
+            let op_span = span.start..child_span.start;
+            return Ok((Some((op.clone(), op_span)), (Operand::Expr(child), child_span)));
+        }
+    }
+    let operand = match symbol {
+        SemanticSymbol::Value(v) => Operand::Expr(get_value_expression(v, ctx)),
+        SemanticSymbol::ValueGroup(group) => {
+            Operand::Expr(semantic_to_expression(group, ctx, statement_aware)?)
+        },
+        SemanticSymbol::UnaryOp(op, param) => {
+            let child = semantic_to_expression(param, ctx, statement_aware)?;
+            Operand::Expr(eb::unary(op, child))
+        },
+        SemanticSymbol::Nary(op, params) => {
+            let children = params.iter().map(|p| semantic_to_expression(p, ctx, statement_aware))
+                .collect::<Result<Vec<Expression>, ParseError>>()?;
+            Operand::Expr(eb::nary(op, children))
+        },
+        SemanticSymbol::Variadic => Operand::Variadic,
+        SemanticSymbol::BinaryOp(op) => {
+            return Err(ParseError::new(ParseErrorKind::MissingOperand, span.clone(), op));
+        },
+    };
+    return Ok((None, (operand, span.clone())));
+}
+
+/// precedence of the binary operator `op`,
+/// when `statement_aware` is set, `=>` and `=` bind looser than every other operator (in that order)
+fn binding_power(op: &str, ctx: &Context, statement_aware: bool) -> i64 {
+    if statement_aware {
+        match StatementSymbols::from_str(op) {
+            Ok(StatementSymbols::Implies) => return -2,
+            Ok(StatementSymbols::Equal) => return -1,
+            Err(_) => {},
+        }
+    }
+    // doubled to leave room for `prefix_binding_power`
+    return 2 * ctx.precedence_of(op) as i64;
+}
+
+/// precedence of the symbolic prefix unary ops,
+/// they bind looser than the right associative operators (`-2 ^ 2` is `-(2 ^ 2)`) and tighter than the others
+fn prefix_binding_power(ctx: &Context) -> i64 {
+    return ctx.right_assoc_ops.iter().map(|op| 2 * ctx.precedence_of(op) as i64 - 1).max().unwrap_or(i64::MAX);
+}
+
+fn combine_operands(op: &(String, Span), left: SpannedOperand, right: SpannedOperand, ctx: &Context)
+-> Result<SpannedOperand, ParseError> {
+    let (op, op_span) = op;
+    let (left, left_span) = left;
+    let (right, right_span) = right;
+    let span = left_span.start..right_span.end;
+    let left = match left {
+        Operand::Expr(expr) => expr,
+        Operand::Variadic => {
+            return Err(ParseError::new(ParseErrorKind::MisplacedVariadic, left_span, ExpressionType::variadic_string()));
+        },
+    };
+    match right {
+        Operand::Variadic => {
+            // A + ... => +(...(A))
+            if !ctx.assoc_ops.iter().any(|o| o == op) {
+                return Err(ParseError::new(ParseErrorKind::MisplacedVariadic, op_span.start..right_span.end, op));
+            }
+            Ok((Operand::Expr(eb::unary(op, eb::variadic(left))), span))
+        },
+        Operand::Expr(right) if StatementSymbols::from_str(op).is_ok() => {
+            Ok((Operand::Expr(eb::binary_statement(op, left, right)), span))
+        },
+        Operand::Expr(right) => Ok((Operand::Expr(eb::binary(op, left, right)), span)),
+    }
+}
+
+/// precedence climbing over the alternating list `operands[0] ops[0] operands[1] ops[1] ...`
+/// operators with the same precedence are grouped from the left unless they are in `ctx.right_assoc_ops`
+struct InfixParser<'a> {
+    operands: std::vec::IntoIter<PrefixedOperand>,
+    ops: std::iter::Peekable<std::vec::IntoIter<(String, Span)>>,
+    ctx: &'a Context,
+    statement_aware: bool,
+}
+
+impl InfixParser<'_> {
+    fn power(&self, op: &str) -> i64 {
+        return binding_power(op, self.ctx, self.statement_aware);
+    }
+
+    /// the next operand, with its prefix unary op applied to it and to the operators that bind tighter
+    fn next_operand(&mut self) -> Option<Result<SpannedOperand, ParseError>> {
+        let (prefix, operand) = self.operands.next()?;
+        let (op, op_span) = match prefix {
+            Some(prefix) => prefix,
+            None => return Some(Ok(operand)),
+        };
+        let prefix_power = prefix_binding_power(self.ctx);
+        let operand = if prefix_power < i64::MAX { self.parse(operand, prefix_power + 1) } else { Ok(operand) };
+        return Some(operand.and_then(|(operand, span)| match operand {
+            Operand::Expr(expr) => Ok((Operand::Expr(eb::unary(&op, expr)), op_span.start..span.end)),
+            Operand::Variadic => Err(ParseError::new(ParseErrorKind::MisplacedVariadic, span, ExpressionType::variadic_string())),
+        }));
+    }
+
+    fn parse(&mut self, left: SpannedOperand, min_power: i64) -> Result<SpannedOperand, ParseError> {
+        let mut left = left;
+        while let Some(op) = self.ops.peek().cloned() {
+            let power = self.power(&op.0);
+            if power < min_power { break; }
+            self.ops.next();
+            let mut right = self.next_operand()
+                .ok_or(ParseError::new(ParseErrorKind::MissingOperand, op.1.clone(), &op.0))??;
+            while let Some((next_op, _)) = self.ops.peek().cloned() {
+                let next_power = self.power(&next_op);
+                if next_power > power {
+                    right = self.parse(right, power + 1)?;
+                } else if next_power == power && self.ctx.is_right_assoc(&next_op) {
+                    right = self.parse(right, power)?;
                 } else {
-                    // binary op
-                    if StatementSymbols::from_str(symbol.as_str()).is_ok() {
-                        left_expr = Some(eb::binary_statement(symbol.as_str(), left_expr.unwrap(), right_expr.unwrap()))
-                    } else {
-                        left_expr = Some(eb::binary(symbol.as_str(), left_expr.unwrap(), right_expr.unwrap()))
-                    }
+                    break;
                 }
-                state = ExpressionParsingState::AfterValue;
             }
+            left = combine_operands(&op, left, right, self.ctx)?;
         }
+        return Ok(left);
     }
-    
-    if state != ExpressionParsingState::AfterValue { return None; }
-    return left_expr;
 }
 
-pub fn to_expression_raw<T: AsRef<str>>(text: T, ctx: &Context) -> Option<Expression> {
-    let s : String = text.as_ref().to_string();
-    let tokens = tokenize(s, false);
-    // dbg!(&tokens);
-    let token_items = group_tokens_by_parentheses(&tokens);
-    // dbg!(&token_items);
-    if let TokenItem::Group(group) = &token_items {
-        let semantic = parse_semantic_symbol(group, ctx);
-        // dbg!(&semantic);
-        if let Some(semantic) = semantic {
-            return semantic_to_expression(&semantic, ctx);
+fn semantic_to_expression(semantic: &[SpannedSemanticSymbol], ctx: &Context, statement_aware: bool)
+-> Result<Expression, ParseError> {
+    // `parse_semantic_symbol` guarantees that the symbols alternate between operand and binary operator
+    let mut operands: Vec<PrefixedOperand> = Vec::new();
+    let mut ops: Vec<(String, Span)> = Vec::new();
+    for (i, s) in semantic.iter().enumerate() {
+        match (i % 2, s) {
+            (1, (SemanticSymbol::BinaryOp(op), span)) => ops.push((op.clone(), span.clone())),
+            (1, (_, span)) => return Err(ParseError::new(ParseErrorKind::UnexpectedToken, span.clone(), "")),
+            _ => operands.push(semantic_operand_to_expression(s, ctx, statement_aware)?),
         }
     }
-    return None;
-}
 
-fn preprocess_statement<T: AsRef<str>>(text: T) -> String {
-    if text.as_ref().contains("=>") {
-        let split = text.as_ref().split("=>").collect::<Vec<&str>>();
-        if split.len() != 2 { return text.as_ref().to_string() }
-        let left = split[0].trim();
-        let right = split[1].trim();
-        let processed_left = preprocess_statement(left);
-        let processed_right = preprocess_statement(right);
-        return format!("({}) => ({})", processed_left, processed_right);
-    } else if text.as_ref().contains("=") {
-        let split = text.as_ref().split("=").collect::<Vec<&str>>();
-        if split.len() != 2 { return text.as_ref().to_string() }
-        let left = split[0].trim();
-        let right = split[1].trim();
-        return format!("({}) = ({})", left, right);
-    } else {
-        return text.as_ref().to_string();
+    let mut parser = InfixParser {
+        operands: operands.into_iter(),
+        ops: ops.into_iter().peekable(),
+        ctx,
+        statement_aware,
+    };
+    let first = parser.next_operand()
+        .ok_or(ParseError::new(ParseErrorKind::EmptyExpression, 0..0, ""))??;
+    let (result, span) = parser.parse(first, i64::MIN)?;
+    match result {
+        Operand::Expr(expr) => Ok(expr),
+        Operand::Variadic => Err(ParseError::new(ParseErrorKind::MisplacedVariadic, span, ExpressionType::variadic_string())),
     }
 }
-fn preprocess_unary<T: AsRef<str>>(text: T, ctx: &Context) -> String {
-    // if there's a unary op that is stuck to a symbol, split it
-    // -b => - b
-    // -a + -b => - a + - b
-    let mut result = text.as_ref().to_string();
-    for op in &ctx.unary_ops {
-        result = result.replace(op, &format!("{} ", op));
+
+fn to_expression_with_mode<T: AsRef<str>>(text: T, ctx: &Context, statement_aware: bool, split_unary: bool)
+-> Result<Expression, ParseError> {
+    let s = text.as_ref();
+    let tokens = tokenize_with_spans(s, false);
+    let tokens = if split_unary { split_unary_ops(tokens, ctx) } else { tokens };
+    check_parentheses(&tokens)?;
+    let span = 0..s.len();
+    if let TokenItem::Group(group, _) = group_tokens_by_parentheses(&tokens, span.clone()) {
+        let semantic = parse_semantic_symbol(&group, &span, ctx)?;
+        return semantic_to_expression(&semantic, ctx, statement_aware);
     }
-    return result;
+    unreachable!("group_tokens_by_parentheses always returns a group");
+}
+
+/// parse the infix `text` without any preprocessing,
+/// statement symbols (`=`, `=>`) are treated like any other binary operator
+pub fn to_expression_raw<T: AsRef<str>>(text: T, ctx: &Context) -> Result<Expression, ParseError> {
+    return to_expression_with_mode(text, ctx, false, false);
 }
 
-pub fn to_expression<T: AsRef<str>>(text: T, ctx: &Context) -> Option<Expression> {
-    let preprocessed = preprocess_statement(text);
-    let preprocessed = preprocess_unary(preprocessed, ctx);
-    // dbg!(&preprocessed);
-    let expr = to_expression_raw(preprocessed, ctx)?;
+/// parse the infix `text` using the operator precedence in `ctx`,
+/// `=>` binds looser than `=`, which binds looser than every other operator
+///
+/// the spans in the returned error are byte ranges in `text`
+pub fn to_expression<T: AsRef<str>>(text: T, ctx: &Context) -> Result<Expression, ParseError> {
+    let expr = to_expression_with_mode(text, ctx, true, true)?;
     let result = expr.normalize_to_assoc_train(&ctx.assoc_ops)
         .normalize_two_children_assoc_train_to_binary_op(&ctx.assoc_ops);
-    return Some(result);
-}
\ No newline at end of file
+    return Ok(result);
+}
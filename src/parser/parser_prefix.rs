@@ -1,5 +1,6 @@
 use std::str::FromStr;
 use super::super::expression::{Expression, ExpressionType, Context, StatementSymbols};
+use super::parse_error::{ParseError, ParseErrorKind, Span};
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Token {
@@ -9,53 +10,94 @@ pub enum Token {
     Symbol(String),
 }
 
+impl Token {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Token::OpenParen => "(",
+            Token::CloseParen => ")",
+            Token::Comma => ",",
+            Token::Symbol(s) => s,
+        }
+    }
+}
+
+pub type SpannedToken = (Token, Span);
+
 pub fn tokenize(s: String, ignore_whitespace: bool) -> Vec<Token> {
+    return tokenize_with_spans(&s, ignore_whitespace).into_iter().map(|(t, _)| t).collect();
+}
+
+/// same as `tokenize`, but every token also carries its byte range in `s`
+pub fn tokenize_with_spans(s: &str, ignore_whitespace: bool) -> Vec<SpannedToken> {
     let mut tokens = Vec::new();
     let mut current_symbol = String::new();
+    let mut current_start = 0;
+    let mut current_end = 0;
 
-    let push_symbol_if_any = |current_symbol: &mut String, tokens: &mut Vec<Token>| {
+    let push_symbol_if_any = |current_symbol: &mut String, tokens: &mut Vec<SpannedToken>, span: Span| {
         if !current_symbol.is_empty() {
-            tokens.push(Token::Symbol(current_symbol.clone()));
+            tokens.push((Token::Symbol(current_symbol.clone()), span));
             current_symbol.clear();
         }
     };
-    for c in s.chars() {
+    for (i, c) in s.char_indices() {
+        let char_span = i..i+c.len_utf8();
         match c {
             '(' => {
-                push_symbol_if_any(&mut current_symbol, &mut tokens);
-                tokens.push(Token::OpenParen);
+                push_symbol_if_any(&mut current_symbol, &mut tokens, current_start..current_end);
+                tokens.push((Token::OpenParen, char_span));
             },
             ')' => {
-                push_symbol_if_any(&mut current_symbol, &mut tokens);
-                tokens.push(Token::CloseParen);
+                push_symbol_if_any(&mut current_symbol, &mut tokens, current_start..current_end);
+                tokens.push((Token::CloseParen, char_span));
             },
             ',' => {
-                push_symbol_if_any(&mut current_symbol, &mut tokens);
-                tokens.push(Token::Comma);
+                push_symbol_if_any(&mut current_symbol, &mut tokens, current_start..current_end);
+                tokens.push((Token::Comma, char_span));
             },
             _ if c.is_whitespace() => {
                 if !ignore_whitespace {
-                    push_symbol_if_any(&mut current_symbol, &mut tokens);
+                    push_symbol_if_any(&mut current_symbol, &mut tokens, current_start..current_end);
                 }
             },
             _ => {
+                if current_symbol.is_empty() { current_start = i; }
                 current_symbol.push(c);
+                current_end = char_span.end;
             },
         }
     };
-    push_symbol_if_any(&mut current_symbol, &mut tokens);
+    push_symbol_if_any(&mut current_symbol, &mut tokens, current_start..current_end);
     tokens
 }
 
+/// check that every open parenthesis has a matching close parenthesis
+pub fn check_parentheses(tokens: &[SpannedToken]) -> Result<(), ParseError> {
+    let mut open_spans: Vec<&Span> = Vec::new();
+    for (t, span) in tokens {
+        match t {
+            Token::OpenParen => open_spans.push(span),
+            Token::CloseParen if open_spans.pop().is_none() => {
+                return Err(ParseError::new(ParseErrorKind::UnbalancedParenthesis, span.clone(), t.as_str()));
+            },
+            _ => {},
+        }
+    }
+    if let Some(span) = open_spans.pop() {
+        return Err(ParseError::new(ParseErrorKind::UnbalancedParenthesis, span.clone(), "("));
+    }
+    return Ok(());
+}
+
 /// split the tokens by commas
 /// 1,2,f(3,4),g(f(1,2),3)
 /// -> [[1], [2], [f(3,4)], [g(f(1,2),3)]]
-pub fn split_tokens_by_comma(tokens: &Vec<Token>) -> Vec<Vec<Token>> {
-    let mut result  : Vec<Vec<Token>> = Vec::new();
-    let mut current : Vec<Token> = Vec::new();
+pub fn split_tokens_by_comma<T: Clone>(tokens: &[T], token_of: impl Fn(&T) -> &Token) -> Vec<Vec<T>> {
+    let mut result  : Vec<Vec<T>> = Vec::new();
+    let mut current : Vec<T> = Vec::new();
     let mut paren_count = 0;
     for t in tokens {
-        match t {
+        match token_of(t) {
             Token::OpenParen => { paren_count += 1; current.push(t.clone()); },
             Token::CloseParen => { paren_count -= 1; current.push(t.clone()); },
             Token::Comma => {
@@ -71,7 +113,7 @@ pub fn split_tokens_by_comma(tokens: &Vec<Token>) -> Vec<Vec<Token>> {
             },
         }
     }
-    if !current.is_empty() { result.push(current); }
+    if !current.is_empty() || !result.is_empty() { result.push(current); }
     result
 }
 
@@ -90,40 +132,70 @@ pub fn get_value_expression(s: &String, ctx: &Context) -> Expression {
     };
 }
 
-fn tokens_to_expression(tokens: &[Token], ctx: &Context) -> Option<Expression> {
+/// `span` is the span of the whole `tokens`, used for reporting empty operands
+fn tokens_to_expression(tokens: &[SpannedToken], span: Span, ctx: &Context) -> Result<Expression, ParseError> {
+    let (first, first_span) = tokens.first()
+        .ok_or(ParseError::new(ParseErrorKind::MissingOperand, span, ""))?;
     // first token must be a symbol
-    if let Token::Symbol(ref s) = tokens[0] {
-        if tokens.len() == 1 {
-            return Some(get_value_expression(s, ctx));
+    let s = match first {
+        Token::Symbol(s) => s,
+        _ => return Err(ParseError::new(ParseErrorKind::UnexpectedToken, first_span.clone(), first.as_str())),
+    };
+    if tokens.len() == 1 {
+        return Ok(get_value_expression(s, ctx));
+    }
+    // must be an operator, which mean
+    // the second token must be a open paren
+    // the last token must be a close paren
+    let (second, second_span) = &tokens[1];
+    if second != &Token::OpenParen {
+        return Err(ParseError::new(ParseErrorKind::UnexpectedToken, second_span.clone(), second.as_str()));
+    }
+    let (last, last_span) = &tokens[tokens.len() - 1];
+    if last != &Token::CloseParen {
+        return Err(ParseError::new(ParseErrorKind::UnexpectedToken, last_span.clone(), last.as_str()));
+    }
+    // get a slice of the tokens between the open and close paren
+    let inner_tokens = &tokens[2..tokens.len() - 1];
+    let child_tokens = split_tokens_by_comma(inner_tokens, |(t, _)| t);
+    // every child starts right after the open paren or its comma
+    let mut child_starts = vec![second_span.end];
+    let mut paren_count = 0;
+    for (t, span) in inner_tokens {
+        match t {
+            Token::OpenParen => paren_count += 1,
+            Token::CloseParen => paren_count -= 1,
+            Token::Comma if paren_count == 0 => child_starts.push(span.end),
+            _ => {},
         }
-        // must be an operator, which mean
-        // the second token must be a open paren
-        // the last token must be a close paren
-        if tokens[1] != Token::OpenParen { return None; }
-        if tokens[tokens.len() - 1] != Token::CloseParen { return None; }
-        // get a slice of the tokens between the open and close paren
-        let inner_tokens = tokens[2..tokens.len() - 1].to_vec();
-        let child_tokens = split_tokens_by_comma(&inner_tokens);
-        let children = child_tokens.iter().map(
-            |t| tokens_to_expression(t,ctx)).collect::<Option<Vec<Expression>>>()?;
-        let exp_type = match children.len() {
-            1 if ExpressionType::is_variadic_str(s) => ExpressionType::Variadic,
-            1 if ctx.unary_ops.contains(s) => ExpressionType::OperatorUnary,
-            2 if ctx.binary_ops.contains(s) => ExpressionType::OperatorBinary,
-            2 if StatementSymbols::from_str(s.as_str()).is_ok() => ExpressionType::StatementOperatorBinary,
-            _ if ctx.assoc_ops.contains(s) => ExpressionType::AssocTrain,
-            _ => ExpressionType::OperatorNary,
-        };
-        return Some(Expression {
-            exp_type,
-            symbol: s.clone(),
-            children: Some(children),
-        });
     }
-    None
+    let mut children = Vec::new();
+    for (t, child_start) in child_tokens.iter().zip(child_starts) {
+        let child_end = t.last().map(|(_, span)| span.end).unwrap_or(child_start);
+        children.push(tokens_to_expression(t, child_start..child_end, ctx)?);
+    }
+    let exp_type = match children.len() {
+        1 if ExpressionType::is_variadic_str(s) => ExpressionType::Variadic,
+        1 if ctx.unary_ops.contains(s) => ExpressionType::OperatorUnary,
+        2 if ctx.binary_ops.contains(s) => ExpressionType::OperatorBinary,
+        2 if StatementSymbols::from_str(s.as_str()).is_ok() => ExpressionType::StatementOperatorBinary,
+        _ if ExpressionType::is_variadic_str(s) => {
+            return Err(ParseError::new(ParseErrorKind::MisplacedVariadic, first_span.clone(), s));
+        },
+        _ if ctx.assoc_ops.contains(s) => ExpressionType::AssocTrain,
+        _ => ExpressionType::OperatorNary,
+    };
+    return Ok(Expression {
+        exp_type,
+        symbol: s.clone(),
+        children: Some(children),
+    });
 }
 
-pub fn to_expression<T: AsRef<str>>(text: T, ctx: &Context) -> Option<Expression> {
-    let s : String = text.as_ref().to_string();
-    tokens_to_expression(&tokenize(s, true), ctx)
+pub fn to_expression<T: AsRef<str>>(text: T, ctx: &Context) -> Result<Expression, ParseError> {
+    let s = text.as_ref();
+    let tokens = tokenize_with_spans(s, true);
+    if tokens.is_empty() { return Err(ParseError::new(ParseErrorKind::EmptyExpression, 0..s.len(), "")); }
+    check_parentheses(&tokens)?;
+    tokens_to_expression(&tokens, 0..s.len(), ctx)
 }
@@ -0,0 +1,354 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use num_traits::{Signed, ToPrimitive, Zero};
+use crate::arithmetic::ArithmeticOperator;
+use crate::expression::{Address, Expression, ExpressionError, ExpressionType, expression_builder as eb};
+use crate::number::{Number, Rational};
+use crate::worksheet::{Action, WorkableExpressionSequence};
+
+/// exponents above this are not expanded, to avoid building enormous polynomials
+const LIMIT_OF_EXPANSION_EXPONENT: u32 = 64;
+
+#[derive(Debug)]
+pub enum PolynomialError {
+    ExpressionErr(ExpressionError),
+    /// a symbol that is not one of the variables, or an operator that a polynomial can't have
+    NotAPolynomial(String),
+    /// a decimal coefficient (or a constant that can't be calculated exactly, ex: `sqrt(2)`)
+    InexactCoefficient(String),
+    ExponentTooLarge,
+    /// there are not at least two terms to combine
+    NoLikeTerms,
+}
+
+impl From<ExpressionError> for PolynomialError {
+    fn from(err: ExpressionError) -> Self {
+        return PolynomialError::ExpressionErr(err);
+    }
+}
+
+/// a sparse polynomial with exact coefficients,
+/// each term maps the exponents of `vars` (in the same order) to a non-zero coefficient
+#[derive(Debug, PartialEq, Clone)]
+pub struct Polynomial {
+    pub vars: Vec<String>,
+    pub terms: BTreeMap<Vec<u32>, Rational>,
+}
+
+impl Polynomial {
+    pub fn zero(vars: &[String]) -> Polynomial {
+        return Polynomial { vars: vars.to_vec(), terms: BTreeMap::new() };
+    }
+    pub fn constant(vars: &[String], c: Rational) -> Polynomial {
+        let mut result = Polynomial::zero(vars);
+        result.add_term(vec![0; vars.len()], c);
+        return result;
+    }
+    pub fn var(vars: &[String], index: usize) -> Polynomial {
+        let mut exponents = vec![0; vars.len()];
+        exponents[index] = 1;
+        let mut result = Polynomial::zero(vars);
+        result.add_term(exponents, Rational::from_integer(1));
+        return result;
+    }
+
+    pub fn is_zero(&self) -> bool { return self.terms.is_empty(); }
+    /// the value if there are no variables in any term
+    pub fn as_constant(&self) -> Option<Rational> {
+        if self.is_zero() { return Some(Rational::from_integer(0)); }
+        if self.terms.len() != 1 { return None; }
+        let (exponents, c) = self.terms.iter().next()?;
+        if exponents.iter().any(|e| *e != 0) { return None; }
+        return Some(c.clone());
+    }
+    pub fn degree(&self) -> u32 {
+        return self.terms.keys().map(|e| e.iter().sum()).max().unwrap_or(0);
+    }
+
+    fn add_term(&mut self, exponents: Vec<u32>, c: Rational) {
+        let sum = match self.terms.get(&exponents) {
+            Some(existing) => existing.checked_add(&c).expect("addition of rationals doesn't fail"),
+            None => c,
+        };
+        if sum.numer().is_zero() {
+            self.terms.remove(&exponents);
+        } else {
+            self.terms.insert(exponents, sum);
+        }
+    }
+
+    pub fn add(&self, other: &Polynomial) -> Polynomial {
+        let mut result = self.clone();
+        for (exponents, c) in &other.terms { result.add_term(exponents.clone(), c.clone()); }
+        return result;
+    }
+    pub fn neg(&self) -> Polynomial {
+        let terms = self.terms.iter()
+            .map(|(e, c)| (e.clone(), c.checked_neg().expect("negation of rationals doesn't fail")))
+            .collect();
+        return Polynomial { vars: self.vars.clone(), terms };
+    }
+    pub fn sub(&self, other: &Polynomial) -> Polynomial {
+        return self.add(&other.neg());
+    }
+    pub fn mul(&self, other: &Polynomial) -> Polynomial {
+        let mut result = Polynomial::zero(&self.vars);
+        for (e1, c1) in &self.terms {
+            for (e2, c2) in &other.terms {
+                let exponents = e1.iter().zip(e2).map(|(a, b)| a + b).collect();
+                result.add_term(exponents, c1.checked_mul(c2).expect("multiplication of rationals doesn't fail"));
+            }
+        }
+        return result;
+    }
+    pub fn pow(&self, exp: u32) -> Polynomial {
+        let mut result = Polynomial::constant(&self.vars, Rational::from_integer(1));
+        for _ in 0..exp { result = result.mul(self); }
+        return result;
+    }
+
+    /// terms ordered by total degree (highest first), then by the exponents of the first variables
+    pub fn sorted_terms(&self) -> Vec<(&Vec<u32>, &Rational)> {
+        let mut terms = self.terms.iter().collect::<Vec<_>>();
+        terms.sort_by(|(e1, _), (e2, _)| {
+            let d1: u32 = e1.iter().sum();
+            let d2: u32 = e2.iter().sum();
+            return d2.cmp(&d1).then_with(|| e2.cmp(e1));
+        });
+        return terms;
+    }
+}
+
+impl fmt::Display for Polynomial {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Expression::from_polynomial(self).to_string(false))
+    }
+}
+
+fn assoc_train(op: &ArithmeticOperator, mut children: Vec<Expression>) -> Expression {
+    return match children.len() {
+        1 => children.remove(0),
+        2 => eb::binary(op.as_str(), children.remove(0), children.remove(0)),
+        _ => Expression { exp_type: ExpressionType::AssocTrain, symbol: op.to_string(), children: Some(children) },
+    };
+}
+
+impl Expression {
+    /// the symbols of the values that are not numeric, sorted and without duplicates
+    pub fn get_variable_symbols(&self) -> Vec<String> {
+        let mut result = vec![];
+        self.f_get_variable_symbols(&mut result);
+        result.sort();
+        result.dedup();
+        return result;
+    }
+    fn f_get_variable_symbols(&self, result: &mut Vec<String>) {
+        if self.is_value() {
+            if !self.is_numeric() { result.push(self.symbol.clone()); }
+            return;
+        }
+        for child in self.children.iter().flatten() { child.f_get_variable_symbols(result); }
+    }
+
+    /// convert an arithmetic expression into a polynomial in `vars`,
+    /// parts without any of `vars` must calculate to an exact number
+    pub fn to_polynomial(&self, vars: &[String]) -> Result<Polynomial, PolynomialError> {
+        use ArithmeticOperator::*;
+        if self.is_value() {
+            if let Some(index) = vars.iter().position(|v| v == &self.symbol) {
+                return Ok(Polynomial::var(vars, index));
+            }
+        }
+        if !self.get_variable_symbols().iter().any(|s| vars.contains(s)) {
+            return match self.calculate_numeric() {
+                Some(Number::Rational(c)) => Ok(Polynomial::constant(vars, c)),
+                Some(Number::Decimal(_)) => Err(PolynomialError::InexactCoefficient(self.to_string(true))),
+                None => Err(PolynomialError::NotAPolynomial(self.to_string(true))),
+            };
+        }
+        let not_a_polynomial = || PolynomialError::NotAPolynomial(self.to_string(true));
+        let op = self.identify_arithmetic_operator().ok_or_else(not_a_polynomial)?;
+        let children = self.children.as_ref().ok_or_else(not_a_polynomial)?.iter()
+            .map(|c| c.to_polynomial(vars))
+            .collect::<Result<Vec<Polynomial>, PolynomialError>>()?;
+        return match op {
+            Add | AddTrain => Ok(children.iter().fold(Polynomial::zero(vars), |acc, p| acc.add(p))),
+            Sub => Ok(children[0].sub(&children[1])),
+            Negative => Ok(children[0].neg()),
+            Mul | MulTrain => Ok(children.iter()
+                .fold(Polynomial::constant(vars, Rational::from_integer(1)), |acc, p| acc.mul(p))),
+            Div => {
+                let denominator = children[1].as_constant().ok_or_else(not_a_polynomial)?;
+                let reciprocal = Rational::from_integer(1).checked_div(&denominator).ok_or_else(not_a_polynomial)?;
+                Ok(children[0].mul(&Polynomial::constant(vars, reciprocal)))
+            },
+            Pow => {
+                let exp = children[1].as_constant().filter(|e| e.is_integer() && !e.numer().is_negative())
+                    .ok_or_else(not_a_polynomial)?;
+                let exp = exp.numer().to_u32().filter(|e| *e <= LIMIT_OF_EXPANSION_EXPONENT)
+                    .ok_or(PolynomialError::ExponentTooLarge)?;
+                Ok(children[0].pow(exp))
+            },
+            _ => Err(not_a_polynomial()),
+        };
+    }
+
+    /// the canonical expression of a polynomial, ex: `3 * x ^ 2 + (-2) * x * y + 1`
+    pub fn from_polynomial(poly: &Polynomial) -> Expression {
+        let terms = poly.sorted_terms().into_iter()
+            .map(|(exponents, c)| monomial_to_expression(&poly.vars, exponents, c))
+            .collect::<Vec<Expression>>();
+        if terms.is_empty() { return eb::constant("0"); }
+        return assoc_train(&ArithmeticOperator::AddTrain, terms);
+    }
+
+    /// combine the terms of a sum that only differ by their coefficient, ex: `2 * x + y + 3 * x` => `5 * x + y`,
+    /// unlike `expand`, products of sums are kept as they are
+    pub fn collect_like_terms(&self) -> Result<Expression, PolynomialError> {
+        let terms = match self.identify_arithmetic_operator() {
+            Some(ArithmeticOperator::Add | ArithmeticOperator::AddTrain) => self.children.as_ref().unwrap(),
+            _ => return Err(PolynomialError::NotAPolynomial(self.to_string(true))),
+        };
+        let vars = self.get_variable_symbols();
+        let mut collected = Polynomial::zero(&vars);
+        let mut collected_count = 0;
+        let mut others = vec![];
+        for term in terms {
+            match term.to_polynomial(&vars) {
+                Ok(poly) if poly.terms.len() <= 1 => {
+                    collected = collected.add(&poly);
+                    collected_count += 1;
+                },
+                _ => others.push(term.clone()),
+            }
+        }
+        if collected_count < 2 { return Err(PolynomialError::NoLikeTerms); }
+        let mut children = vec![];
+        if !collected.is_zero() || others.is_empty() { children.push(Expression::from_polynomial(&collected)); }
+        children.extend(others);
+        // flatten the sum of the collected terms into the train
+        let children = children.into_iter().flat_map(|c| match c.identify_arithmetic_operator() {
+            Some(ArithmeticOperator::Add | ArithmeticOperator::AddTrain) => c.children.unwrap(),
+            _ => vec![c],
+        }).collect();
+        return Ok(assoc_train(&ArithmeticOperator::AddTrain, children));
+    }
+
+    /// multiply out every product and power of sums, and collect the like terms
+    pub fn expand(&self) -> Result<Expression, PolynomialError> {
+        let vars = self.get_variable_symbols();
+        return Ok(Expression::from_polynomial(&self.to_polynomial(&vars)?));
+    }
+
+    /// `expand` both side of an equation, or the expression itself
+    pub fn fully_simplify(&self) -> Result<Expression, PolynomialError> {
+        if let (true, Some(lhs), Some(rhs)) = (self.is_statement(), self.lhs(), self.rhs()) {
+            return Ok(eb::binary_statement(&self.symbol, lhs.expand()?, rhs.expand()?));
+        }
+        return self.expand();
+    }
+
+    pub fn collect_like_terms_at(&self, addr: &Address) -> Result<Expression, PolynomialError> {
+        let new_expr = self.at(addr)?.collect_like_terms()?;
+        return Ok(self.replace_expression_at(new_expr, addr)?);
+    }
+    pub fn expand_at(&self, addr: &Address) -> Result<Expression, PolynomialError> {
+        let new_expr = self.at(addr)?.expand()?;
+        return Ok(self.replace_expression_at(new_expr, addr)?);
+    }
+    pub fn fully_simplify_at(&self, addr: &Address) -> Result<Expression, PolynomialError> {
+        let new_expr = self.at(addr)?.fully_simplify()?;
+        return Ok(self.replace_expression_at(new_expr, addr)?);
+    }
+}
+
+/// `c * x ^ a * y ^ b`, the coefficient is left out if it's `1` (or `-1`, as a negation)
+fn monomial_to_expression(vars: &[String], exponents: &[u32], c: &Rational) -> Expression {
+    let factors = vars.iter().zip(exponents).filter(|(_, e)| **e > 0).map(|(v, e)| {
+        if *e == 1 { return eb::constant(v); }
+        return eb::binary(ArithmeticOperator::Pow.as_str(), eb::constant(v), eb::constant(&e.to_string()));
+    }).collect::<Vec<Expression>>();
+    let c_number = Number::Rational(c.clone());
+    if factors.is_empty() { return c_number.to_expression(); }
+    if c_number.is_one() { return assoc_train(&ArithmeticOperator::MulTrain, factors); }
+    if c_number.checked_neg().is_some_and(|c| c.is_one()) {
+        return eb::unary(ArithmeticOperator::Negative.as_str(), assoc_train(&ArithmeticOperator::MulTrain, factors));
+    }
+    let mut children = vec![c_number.to_expression()];
+    children.extend(factors);
+    return assoc_train(&ArithmeticOperator::MulTrain, children);
+}
+
+impl WorkableExpressionSequence {
+    pub fn collect_like_terms_at(&mut self, addr: &Address) -> bool {
+        let expr = self.last_expression().collect_like_terms_at(addr);
+        return self.try_push(Action::ApplyAction("Collect like terms".to_string()), expr);
+    }
+    pub fn expand_at(&mut self, addr: &Address) -> bool {
+        let expr = self.last_expression().expand_at(addr);
+        return self.try_push(Action::ApplyAction("Expand".to_string()), expr);
+    }
+    pub fn fully_simplify_at(&mut self, addr: &Address) -> bool {
+        let expr = self.last_expression().fully_simplify_at(addr);
+        return self.try_push(Action::ApplyAction("Fully simplify".to_string()), expr);
+    }
+}
+
+pub mod get_possible_actions {
+    use super::*;
+    use crate::algebra;
+    use crate::worksheet::WorksheetContext;
+    use std::cmp::{min, max};
+
+    /// the `algebra` actions, with the polynomial actions first and the negation of both side last
+    pub fn algebra_with_polynomial(expr: &Expression, context: &WorksheetContext, addr_vec: &[Address]) -> Vec<(Action, Expression)> {
+        return vec![
+            polynomial(expr, addr_vec),
+            algebra::get_possible_actions::algebra(expr, context, addr_vec),
+            negate_both_side(expr, addr_vec),
+        ].into_iter().flatten().collect();
+    }
+    
+    /// "Apply /-1 to both side" if the equation and a negation (or its operand) are selected,
+    /// ex: `-x = 5` => `-x / -1 = 5 / -1`
+    pub fn negate_both_side(expr: &Expression, addr_vec: &[Address]) -> Vec<(Action, Expression)> {
+        if addr_vec.len() != 2 || !expr.is_equation() { return vec![]; }
+        let addr0 = min(&addr_vec[0], &addr_vec[1]);
+        let addr1 = max(&addr_vec[0], &addr_vec[1]).no_sub();
+        if !addr0.is_empty() || addr1.is_empty() { return vec![]; }
+        let is_negation = |addr: &Address| expr.at(addr).is_ok_and(|e|
+            matches!(e.identify_arithmetic_operator(), Some(ArithmeticOperator::Negative)));
+        if !is_negation(&addr1) && !is_negation(&addr1.parent()) { return vec![]; }
+        
+        let (op, minus_one) = (ArithmeticOperator::Div, Number::integer(-1).to_expression());
+        return match expr.apply_simple_arithmetic_to_both_side(&op, &minus_one) {
+            Ok(new_expr) => {
+                let name = algebra::generate_simple_apply_arithmetic_to_both_side_name(&op, &minus_one);
+                vec![(Action::ApplyAction(name), new_expr)]
+            },
+            Err(_) => vec![],
+        };
+    }
+
+    /// "Collect like terms", "Expand" and "Fully simplify" at the common ancestor of the selection,
+    /// only the ones that change the expression
+    pub fn polynomial(expr: &Expression, addr_vec: &[Address]) -> Vec<(Action, Expression)> {
+        if addr_vec.is_empty() { return vec![]; }
+        let addr = &Address::common_virtual_ancestor_from_vec(addr_vec, expr).no_sub();
+        let candidates: [(&str, Result<Expression, PolynomialError>); 3] = [
+            ("Collect like terms", expr.collect_like_terms_at(addr)),
+            ("Expand", expr.expand_at(addr)),
+            ("Fully simplify", expr.fully_simplify_at(addr)),
+        ];
+        let mut result: Vec<(Action, Expression)> = vec![];
+        for (name, new_expr) in candidates {
+            let new_expr = match new_expr {
+                Ok(new_expr) => new_expr,
+                Err(_) => continue,
+            };
+            if &new_expr == expr || result.iter().any(|(_, e)| e == &new_expr) { continue; }
+            result.push((Action::ApplyAction(name.to_string()), new_expr));
+        }
+        return result;
+    }
+}
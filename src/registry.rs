@@ -0,0 +1,66 @@
+use std::{collections::HashMap, sync::RwLock};
+use lazy_static::lazy_static;
+use crate::worksheet::{NormalizationFunction, GetPossibleActionsFunction};
+use crate::{algebra, arithmetic, expression, polynomial};
+
+// the registry maps names to the functions used by `WorksheetContext`,
+// so that a worksheet (or a ruleset) can refer to them in data
+
+lazy_static! {
+    static ref NORMALIZATION_FUNCTIONS: RwLock<HashMap<String, NormalizationFunction>> = {
+        let mut map: HashMap<String, NormalizationFunction> = HashMap::new();
+        map.insert("algebra".to_string(), |expr, ctx| expr.normalize_algebra(ctx));
+        return RwLock::new(map);
+    };
+    static ref GET_POSSIBLE_ACTIONS_FUNCTIONS: RwLock<HashMap<String, GetPossibleActionsFunction>> = {
+        let mut map: HashMap<String, GetPossibleActionsFunction> = HashMap::new();
+        map.insert("algebra".to_string(), |expr, ctx, addr_vec|
+            algebra::get_possible_actions::algebra(expr, ctx, addr_vec));
+        map.insert("arithmetic".to_string(), |expr, ctx, addr_vec|
+            arithmetic::get_possible_actions::arithmetic(expr, ctx, addr_vec));
+        map.insert("basic".to_string(), |expr, ctx, addr_vec|
+            expression::get_possible_actions::basic(expr, ctx, addr_vec));
+        map.insert("polynomial".to_string(), |expr, ctx, addr_vec|
+            polynomial::get_possible_actions::algebra_with_polynomial(expr, ctx, addr_vec));
+        return RwLock::new(map);
+    };
+}
+
+/// register (or replace) a normalization function under `name`
+pub fn register_normalization_function(name: &str, f: NormalizationFunction) {
+    NORMALIZATION_FUNCTIONS.write().unwrap().insert(name.to_string(), f);
+}
+
+/// register (or replace) a possible actions function under `name`
+pub fn register_get_possible_actions_function(name: &str, f: GetPossibleActionsFunction) {
+    GET_POSSIBLE_ACTIONS_FUNCTIONS.write().unwrap().insert(name.to_string(), f);
+}
+
+pub fn get_normalization_function(name: &str) -> Option<NormalizationFunction> {
+    return NORMALIZATION_FUNCTIONS.read().unwrap().get(name).copied();
+}
+
+pub fn get_possible_actions_function(name: &str) -> Option<GetPossibleActionsFunction> {
+    return GET_POSSIBLE_ACTIONS_FUNCTIONS.read().unwrap().get(name).copied();
+}
+
+/// the name `f` is registered under, the first one in alphabetical order if there are several
+/// * `NOTE`: functions are compared by address, so a closure is only found if it is the one that was registered
+pub fn find_normalization_function_name(f: NormalizationFunction) -> Option<String> {
+    let map = NORMALIZATION_FUNCTIONS.read().unwrap();
+    return map.iter().filter(|(_, g)| std::ptr::fn_addr_eq(**g, f)).map(|(name, _)| name.clone()).min();
+}
+
+/// the name `f` is registered under, see `find_normalization_function_name`
+pub fn find_get_possible_actions_function_name(f: GetPossibleActionsFunction) -> Option<String> {
+    let map = GET_POSSIBLE_ACTIONS_FUNCTIONS.read().unwrap();
+    return map.iter().filter(|(_, g)| std::ptr::fn_addr_eq(**g, f)).map(|(name, _)| name.clone()).min();
+}
+
+pub fn contains_normalization_function(name: &str) -> bool {
+    return NORMALIZATION_FUNCTIONS.read().unwrap().contains_key(name);
+}
+
+pub fn contains_get_possible_actions_function(name: &str) -> bool {
+    return GET_POSSIBLE_ACTIONS_FUNCTIONS.read().unwrap().contains_key(name);
+}
@@ -1,38 +1,185 @@
 use std::collections::HashMap;
+use std::path::PathBuf;
 use crate::arithmetic::get_arithmetic_ctx;
-use crate::expression::{Address, Context, Expression, ExpressionError};
+use crate::expression::{Address, Context, Expression, ExpressionError, ExpressionType, MatchMap, MatchOptions, StatementSymbols};
 use crate::parser::{parser_prefix, parser};
+use crate::parser::parse_error::ParseError;
+use crate::registry;
+use crate::sort::Sort;
+use crate::worksheet::Provenance;
 use serde::{Serialize, Deserialize};
+use std::fmt;
 
-#[derive(Clone, Default, PartialEq)]
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct Rule {
     pub id: String,
     pub expression: Expression,
     pub label: String,
+    /// side conditions on the variables of `expression`, ex: `X != 0`, `is_integer(X)`
+    #[serde(default)]
+    pub conditions: Vec<Expression>,
+    /// operators that are matched regardless of the order and grouping of their children,
+    /// see `Expression::pattern_match_this_node_with`
+    #[serde(default)]
+    pub ac_ops: Vec<String>,
+    /// the sorts that the variables of `expression` must have, ex: `n : Integer`
+    #[serde(default)]
+    pub sorts: HashMap<String, Sort>,
 }
 
-#[derive(Clone, Default, PartialEq)]
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct RuleSet {
     pub name: String,
     pub context: Context,
     pub rule_vec: Vec<Rule>, 
     pub rule_ids: Vec<String>, // to preserve the order of the rules
     pub auto_rule_ids: Vec<String>, // list of rules that needs to be automatically applied
+    pub normalization: Vec<Expression>, // equations applied (left to right) to every new expression
+    pub normalization_function: Option<String>, // name in the `registry`
+    pub get_possible_actions_function: Option<String>, // name in the `registry`
 }
 
 pub type RuleMap = HashMap<String, Rule>;
 
+/// binary operators that can be used in the conditions of a rule
+const CONDITION_OPS: [&str; 5] = ["!=", "<", "<=", ">", ">="];
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ConditionStatus {
+    Satisfied,
+    Violated,
+    Undecided,
+}
+
 impl Expression {
     pub fn apply_rule_expr_at(&self, rule_expr: &Expression, addr: &Address) -> Result<Expression, ExpressionError> {
+        return self.apply_rule_expr_with_options_at(rule_expr, addr, &MatchOptions::default());
+    }
+    /// * `NOTE`: `options` only apply to equations, implications are always matched by position
+    fn apply_rule_expr_with_options_at(&self, rule_expr: &Expression, addr: &Address, options: &MatchOptions)
+    -> Result<Expression, ExpressionError> {
         if rule_expr.is_equation() {
-            return self.apply_equation_at(rule_expr, addr);
+            return self.apply_equation_with_options_at(rule_expr, addr, options);
         } else if rule_expr.is_implication() {
             return self.apply_implication(rule_expr);
         }
         return Err(ExpressionError::InvalidRule);
     }
     pub fn apply_rule_at(&self, rule: &Rule, addr: &Address) -> Result<Expression, ExpressionError> {
-        return self.apply_rule_expr_at(&rule.expression, addr)
+        return self.apply_rule_with_assumptions_at(rule, addr).map(|(expr, _)| expr);
+    }
+    
+    /// same as `apply_rule_at`, but also returns the conditions of the rule that can't be decided,
+    /// which the result depends on (with the variables substituted)
+    pub fn apply_rule_with_assumptions_at(&self, rule: &Rule, addr: &Address)
+    -> Result<(Expression, Vec<Expression>), ExpressionError> {
+        return self.apply_rule_in_context_at(rule, addr, &Context::default());
+    }
+    
+    /// same as `apply_rule_with_assumptions_at`, but the sorts of the matched expressions are inferred from `ctx`,
+    /// see `Rule::sorts`
+    pub fn apply_rule_in_context_at(&self, rule: &Rule, addr: &Address, ctx: &Context)
+    -> Result<(Expression, Vec<Expression>), ExpressionError> {
+        let options = rule.match_options(ctx);
+        let mut assumptions = vec![];
+        if !rule.conditions.is_empty() || !rule.sorts.is_empty() {
+            let match_map = self.get_rule_match_map_at(rule, addr, &options)?;
+            for condition in &rule.conditions {
+                let condition = condition.apply_match_map(&match_map);
+                match condition.check_condition() {
+                    ConditionStatus::Satisfied => {},
+                    ConditionStatus::Violated => {
+                        return Err(ExpressionError::ConditionNotSatisfied(condition.to_string(true)));
+                    },
+                    ConditionStatus::Undecided => assumptions.push(condition),
+                }
+            }
+        }
+        let expr = self.apply_rule_expr_with_options_at(&rule.expression, addr, &options)?;
+        return Ok((expr, assumptions));
+    }
+    
+    /// the provenance of applying `rule` at `addr`, with the bindings of the variables of the rule
+    /// * `NOTE`: the bindings are empty if the rule doesn't match
+    pub fn get_rule_provenance_at(&self, rule: &Rule, addr: &Address, ctx: &Context) -> Provenance {
+        let bindings = self.get_rule_match_map_at(rule, addr, &rule.match_options(ctx)).unwrap_or_default();
+        return Provenance::Rule { rule_id: rule.id.clone(), address: addr.clone(), bindings };
+    }
+    
+    /// the addresses where the (equation) rule can be applied, in an expression of the context `ctx`
+    pub fn get_possible_rule_application_addresses(&self, rule: &Rule, ctx: &Context) -> Vec<Address> {
+        return self.get_possible_equation_application_addresses_with(&rule.expression, &rule.match_options(ctx));
+    }
+    
+    /// the match map of the lhs of the rule to the expression that it will be applied to
+    fn get_rule_match_map_at(&self, rule: &Rule, addr: &Address, options: &MatchOptions) -> Result<MatchMap, ExpressionError> {
+        let rule_expr = &rule.expression;
+        let lhs = rule_expr.at(&Address::new(vec![0], None))?;
+        let target = if rule_expr.is_implication() {
+            self.clone()
+        } else if let Some(sub) = addr.sub {
+            self.at(addr)?.generate_subexpr_from_train(sub)?
+        } else {
+            self.at(addr)?.clone()
+        };
+        if &target == lhs { return Ok(MatchMap::new()); }
+        let options = if rule_expr.is_implication() {
+            &MatchOptions { ac_ops: vec![], ..options.clone() }
+        } else {
+            options
+        };
+        return target.pattern_match_this_node_with(lhs, options).ok_or(ExpressionError::PatternDoesNotMatch);
+    }
+    
+    /// decide a condition whose variables are already substituted
+    /// * `is_numeric(X)`, `is_integer(X)`
+    /// * `X = Y`, `X != Y`, `X < Y`, `X <= Y`, `X > Y`, `X >= Y`
+    pub fn check_condition(&self) -> ConditionStatus {
+        let children = match &self.children {
+            Some(children) => children,
+            None => return ConditionStatus::Undecided,
+        };
+        let to_status = |b: bool| if b { ConditionStatus::Satisfied } else { ConditionStatus::Violated };
+        if self.exp_type == ExpressionType::OperatorNary && children.len() == 1 {
+            let value = children[0].calculate_numeric();
+            return match (self.symbol.as_str(), value) {
+                ("is_numeric", Some(_)) => ConditionStatus::Satisfied,
+                ("is_integer", Some(value)) => to_status(value.is_integer()),
+                _ => ConditionStatus::Undecided,
+            };
+        }
+        if children.len() != 2 { return ConditionStatus::Undecided; }
+        let is_equal_symbol = self.symbol == StatementSymbols::Equal.as_str();
+        let is_same_expression = children[0] == children[1];
+        match (self.symbol.as_str(), is_same_expression) {
+            ("!=", true) => return ConditionStatus::Violated,
+            (_, true) if is_equal_symbol => return ConditionStatus::Satisfied,
+            _ => {},
+        }
+        let (lhs, rhs) = match (children[0].calculate_numeric(), children[1].calculate_numeric()) {
+            (Some(lhs), Some(rhs)) => (lhs, rhs),
+            _ => return ConditionStatus::Undecided,
+        };
+        return match self.symbol.as_str() {
+            _ if is_equal_symbol => to_status(lhs == rhs),
+            "!=" => to_status(lhs != rhs),
+            "<"  => to_status(lhs < rhs),
+            "<=" => to_status(lhs <= rhs),
+            ">"  => to_status(lhs > rhs),
+            ">=" => to_status(lhs >= rhs),
+            _ => ConditionStatus::Undecided,
+        };
+    }
+}
+
+impl Rule {
+    /// how the lhs of the rule is matched, in an expression of the context `ctx`
+    pub fn match_options(&self, ctx: &Context) -> MatchOptions {
+        return MatchOptions {
+            ac_ops: self.ac_ops.clone(),
+            variable_sorts: self.sorts.clone(),
+            sorts: ctx.sorts.clone(),
+        };
     }
 }
 
@@ -46,6 +193,26 @@ impl RuleSet {
 pub enum ParserError {
     InvalidJSON(String),
     InvalidRule(String),
+    UnknownFunction(String),
+    /// `id` is the id of the rule (or `variations`) that contains the expression `expr`
+    InvalidExpression { id: String, expr: String, error: ParseError },
+    ImportNotFound(String),
+    /// the chain of imports, the last one is already being imported
+    ImportCycle(Vec<String>),
+}
+impl fmt::Display for ParserError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParserError::InvalidJSON(err) => write!(f, "invalid JSON: {}", err),
+            ParserError::InvalidRule(rule) => write!(f, "invalid rule: {}", rule),
+            ParserError::UnknownFunction(name) => write!(f, "unknown function: {}", name),
+            ParserError::InvalidExpression { id, expr, error } => {
+                write!(f, "invalid expression in `{}`: {} in `{}`", id, error, expr)
+            },
+            ParserError::ImportNotFound(name) => write!(f, "import not found: {}", name),
+            ParserError::ImportCycle(names) => write!(f, "import cycle: {}", names.join(" -> ")),
+        }
+    }
 }
 impl From<serde_json::Error> for ParserError {
     fn from(err: serde_json::Error) -> Self {
@@ -53,12 +220,42 @@ impl From<serde_json::Error> for ParserError {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// resolves the `imports` of a ruleset JSON
+pub trait RulesetLoader {
+    /// the JSON string of the ruleset named `name`
+    fn load(&self, name: &str) -> Result<String, ParserError>;
+}
+
+/// rulesets in memory, by name
+impl RulesetLoader for HashMap<String, String> {
+    fn load(&self, name: &str) -> Result<String, ParserError> {
+        return self.get(name).cloned().ok_or(ParserError::ImportNotFound(name.to_string()));
+    }
+}
+
+/// rulesets in the directory `dir`, the name is the file name with or without `.json`
+pub struct FileRulesetLoader {
+    pub dir: PathBuf,
+}
+impl FileRulesetLoader {
+    pub fn new(dir: impl Into<PathBuf>) -> FileRulesetLoader {
+        return FileRulesetLoader { dir: dir.into() };
+    }
+}
+impl RulesetLoader for FileRulesetLoader {
+    fn load(&self, name: &str) -> Result<String, ParserError> {
+        let file_name = if name.ends_with(".json") { name.to_string() } else { format!("{}.json", name) };
+        return std::fs::read_to_string(self.dir.join(file_name))
+            .map_err(|_| ParserError::ImportNotFound(name.to_string()));
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 struct RulesetVariationJSON {
     expr_prefix: Option<String>,
     expr: Option<String>
 }
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 struct RulesetNormalizationJSON {
     expr_prefix: Option<String>,
     expr: Option<String>
@@ -70,44 +267,192 @@ struct ContextJSON {
     unary_ops: Option<Vec<String>>,
     binary_ops: Option<Vec<String>>,
     assoc_ops: Option<Vec<String>>,
+    commutative_ops: Option<Vec<String>>,
     handle_numerics: Option<bool>,
-    flags: Option<Vec<String>>
+    flags: Option<Vec<String>>,
+    op_precedence: Option<HashMap<String, usize>>,
+    right_assoc_ops: Option<Vec<String>>,
+    sorts: Option<HashMap<String, Sort>>,
 }
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 struct RuleJSON {
+    /// the name of the ruleset that defines the rule, `None` for the ruleset being parsed
+    #[serde(skip)]
+    namespace: Option<String>,
     id: String,
     label: Option<String>,
     expr_prefix: Option<String>,
     expr: Option<String>,
     variations: Option<Vec<RulesetVariationJSON>>,
+    variate_rhs: Option<bool>,
+    ac_matching: Option<bool>,
+    conditions: Option<Vec<String>>,
+    sorts: Option<HashMap<String, Sort>>,
     auto: Option<bool>,
+    /// the `sorts` of the ruleset that defines the rule
+    #[serde(skip)]
+    ruleset_sorts: HashMap<String, Sort>,
+}
+#[derive(Serialize, Deserialize, Debug)]
+struct ImportJSON {
+    name: String,
+    /// ids of the imported rules to leave out
+    exclude: Option<Vec<String>>,
 }
 #[derive(Serialize, Deserialize, Debug)]
 struct RulesetJSON {
     name: String,
+    imports: Option<Vec<ImportJSON>>,
     context: Option<ContextJSON>,
+    /// the sorts of the variables of every rule, unless overridden by the `sorts` of the rule,
+    /// the sorts of the parameters and operators are in the `context`
+    sorts: Option<HashMap<String, Sort>>,
     variations: Option<Vec<RulesetVariationJSON>>,
+    variate_rhs: Option<bool>,
+    ac_matching: Option<bool>,
     normalization: Option<Vec<RulesetNormalizationJSON>>,
+    normalization_function: Option<String>,
+    get_possible_actions_function: Option<String>,
     rules: Vec<RuleJSON>
 }
 
-fn resolve_context_json(context_json: Option<ContextJSON>) -> Result<Context, ParserError> {
-    if context_json.is_none() { return Ok(Context::default()); }
+/// a ruleset JSON with its imports resolved,
+/// the imported rules keep their own namespace and variations, and the imported contexts are merged
+struct ResolvedRulesetJSON {
+    name: String,
+    context: Context,
+    variations: Option<Vec<RulesetVariationJSON>>,
+    variate_rhs: Option<bool>,
+    ac_matching: Option<bool>,
+    normalization: Vec<RulesetNormalizationJSON>,
+    normalization_function: Option<String>,
+    get_possible_actions_function: Option<String>,
+    rules: Vec<RuleJSON>,
+}
+
+/// `import_stack` is the chain of imports being resolved, to detect cycles
+fn resolve_ruleset_json(
+    ruleset_json: RulesetJSON, loader: &dyn RulesetLoader, import_stack: &mut Vec<String>
+) -> Result<ResolvedRulesetJSON, ParserError> {
+    let mut context = Context::default();
+    let mut rules: Vec<RuleJSON> = vec![];
+    let mut normalization = vec![];
+    let mut normalization_function = None;
+    let mut get_possible_actions_function = None;
+    for import in ruleset_json.imports.unwrap_or_default() {
+        if import_stack.contains(&import.name) {
+            return Err(ParserError::ImportCycle([import_stack.clone(), vec![import.name]].concat()));
+        }
+        let import_json: RulesetJSON = serde_json::from_str(&loader.load(&import.name)?)?;
+        import_stack.push(import.name.clone());
+        let imported = resolve_ruleset_json(import_json, loader, import_stack)?;
+        import_stack.pop();
+        
+        let exclude = import.exclude.unwrap_or_default();
+        for mut rule in imported.rules {
+            let namespace = rule.namespace.get_or_insert(imported.name.clone()).clone();
+            let full_id = format!("{}/{}", namespace, rule.id);
+            if exclude.contains(&rule.id) || exclude.contains(&full_id) { continue; }
+            // the same ruleset can be imported more than once
+            if rules.iter().any(|r| r.namespace.as_ref() == Some(&namespace) && r.id == rule.id) { continue; }
+            rule.variations.get_or_insert(imported.variations.clone().unwrap_or_default());
+            rule.variate_rhs = rule.variate_rhs.or(imported.variate_rhs);
+            rule.ac_matching = rule.ac_matching.or(imported.ac_matching);
+            rules.push(rule);
+        }
+        merge_context(&mut context, imported.context);
+        normalization.extend(imported.normalization);
+        normalization_function = imported.normalization_function.or(normalization_function);
+        get_possible_actions_function = imported.get_possible_actions_function.or(get_possible_actions_function);
+    }
+    
+    // a rule with the id of an imported rule overrides it, the fields that are not set are inherited
+    // * `NOTE`: an imported rule is overridden at most once, a duplicate local id is kept (see `RuleSet::validate`)
+    let mut overridden: Vec<usize> = vec![];
+    for mut rule in ruleset_json.rules {
+        rule.ruleset_sorts = ruleset_json.sorts.clone().unwrap_or_default();
+        let position = rules.iter().enumerate()
+            .position(|(i, r)| r.namespace.is_some() && r.id == rule.id && !overridden.contains(&i));
+        match position {
+            Some(i) => {
+                rules[i] = override_rule_json(rule, &rules[i]);
+                overridden.push(i);
+            },
+            None => rules.push(rule),
+        }
+    }
+    normalization.extend(ruleset_json.normalization.unwrap_or_default());
+    return Ok(ResolvedRulesetJSON {
+        name: ruleset_json.name,
+        context: resolve_context_json(ruleset_json.context, context)?,
+        variations: ruleset_json.variations,
+        variate_rhs: ruleset_json.variate_rhs,
+        ac_matching: ruleset_json.ac_matching,
+        normalization,
+        normalization_function: ruleset_json.normalization_function.or(normalization_function),
+        get_possible_actions_function: ruleset_json.get_possible_actions_function.or(get_possible_actions_function),
+        rules,
+    });
+}
+
+/// `rule` with the unset fields taken from `base`, it keeps the namespace of `base`
+fn override_rule_json(rule: RuleJSON, base: &RuleJSON) -> RuleJSON {
+    let base = base.clone();
+    let has_expr = rule.expr.is_some() || rule.expr_prefix.is_some();
+    return RuleJSON {
+        namespace: base.namespace,
+        id: rule.id,
+        label: rule.label.or(base.label),
+        expr_prefix: if has_expr { rule.expr_prefix } else { base.expr_prefix },
+        expr: if has_expr { rule.expr } else { base.expr },
+        variations: rule.variations.or(base.variations),
+        variate_rhs: rule.variate_rhs.or(base.variate_rhs),
+        ac_matching: rule.ac_matching.or(base.ac_matching),
+        conditions: rule.conditions.or(base.conditions),
+        sorts: rule.sorts.or(base.sorts),
+        auto: rule.auto.or(base.auto),
+        ruleset_sorts: base.ruleset_sorts.into_iter().chain(rule.ruleset_sorts).collect(),
+    };
+}
+
+/// add the symbols, operators and sorts of `other` to `ctx`
+fn merge_context(ctx: &mut Context, other: Context) {
+    let extend = |vec: &mut Vec<String>, other: Vec<String>| {
+        for s in other { if !vec.contains(&s) { vec.push(s); } }
+    };
+    extend(&mut ctx.parameters, other.parameters);
+    extend(&mut ctx.unary_ops, other.unary_ops);
+    extend(&mut ctx.binary_ops, other.binary_ops);
+    extend(&mut ctx.assoc_ops, other.assoc_ops);
+    extend(&mut ctx.commutative_ops, other.commutative_ops);
+    extend(&mut ctx.right_assoc_ops, other.right_assoc_ops);
+    ctx.handle_numerics |= other.handle_numerics;
+    ctx.flags.extend(other.flags);
+    ctx.op_precedence.extend(other.op_precedence);
+    ctx.sorts.extend(other.sorts);
+}
+
+/// `inherited` is the context of the imports, the `base` and the rest of `context_json` are added to it
+fn resolve_context_json(context_json: Option<ContextJSON>, inherited: Context) -> Result<Context, ParserError> {
+    if context_json.is_none() { return Ok(inherited); }
     let context_json = context_json.unwrap();
     
-    let mut ctx = if let Some(base) = context_json.base {
+    let mut ctx = inherited;
+    if let Some(base) = context_json.base {
         match base.as_str() {
-            "arithmetic" => get_arithmetic_ctx(),
+            "arithmetic" => merge_context(&mut ctx, get_arithmetic_ctx()),
             _ => return Err(ParserError::InvalidRule(base)),
         }
-    } else {
-        Context::default()
-    };
+    }
     if let Some(parameters) = context_json.parameters { ctx.parameters.extend(parameters); };
     if let Some(unary_ops) = context_json.unary_ops { ctx.unary_ops.extend(unary_ops); };
     if let Some(binary_ops) = context_json.binary_ops { ctx.binary_ops.extend(binary_ops); };
     if let Some(assoc_ops) = context_json.assoc_ops { ctx.assoc_ops.extend(assoc_ops); };
+    if let Some(commutative_ops) = context_json.commutative_ops { ctx.commutative_ops.extend(commutative_ops); };
     if let Some(handle_numerics) = context_json.handle_numerics { ctx.handle_numerics = handle_numerics; };
+    if let Some(op_precedence) = context_json.op_precedence { ctx.op_precedence.extend(op_precedence); };
+    if let Some(right_assoc_ops) = context_json.right_assoc_ops { ctx.right_assoc_ops.extend(right_assoc_ops); };
+    if let Some(sorts) = context_json.sorts { ctx.sorts.extend(sorts); };
     if let Some(flags) = context_json.flags { 
         for flag in flags {
             ctx.flags.insert(flag);
@@ -116,7 +461,81 @@ fn resolve_context_json(context_json: Option<ContextJSON>) -> Result<Context, Pa
     
     return Ok(ctx);
 }
+fn parse_expression_json(
+    id: &str, expr: Option<String>, expr_prefix: Option<String>, context: &Context
+) -> Result<Expression, ParserError> {
+    let to_parser_error = |expr: String| {
+        let id = id.to_string();
+        move |error: ParseError| ParserError::InvalidExpression { id, expr, error }
+    };
+    if let Some(expr) = expr {
+        return parser::to_expression(&expr, context).map_err(to_parser_error(expr));
+    } else if let Some(expr_prefix) = expr_prefix {
+        return parser_prefix::to_expression(&expr_prefix, context).map_err(to_parser_error(expr_prefix));
+    } else {
+        return Err(ParserError::InvalidRule(format!("{}: missing expr or expr_prefix", id)));
+    }
+}
+
+fn resolve_normalization_json(
+    normalization_json: Option<Vec<RulesetNormalizationJSON>>,
+    context: &Context
+) -> Result<Vec<Expression>, ParserError> {
+    let mut normalization: Vec<Expression> = vec![];
+    for normalization_json in normalization_json.unwrap_or_default() {
+        let expr = parse_expression_json("normalization", normalization_json.expr, normalization_json.expr_prefix, context)?;
+        if !expr.is_equation() {
+            return Err(ParserError::InvalidRule(format!("normalization: `{}` is not an equation", expr.to_string(true))));
+        }
+        normalization.push(expr);
+    }
+    return Ok(normalization);
+}
+
+/// the conditions are parsed with the comparison operators added to the `context`
+fn resolve_conditions_json(
+    id: &str, conditions_json: Option<Vec<String>>, context: &Context
+) -> Result<Vec<Expression>, ParserError> {
+    let conditions_json = conditions_json.unwrap_or_default();
+    if conditions_json.is_empty() { return Ok(vec![]); }
+    let mut condition_ctx = context.clone();
+    for op in CONDITION_OPS {
+        if !condition_ctx.binary_ops.iter().any(|o| o == op) { condition_ctx.binary_ops.push(op.to_string()); }
+    }
+    let mut conditions = vec![];
+    for condition in conditions_json {
+        conditions.push(parse_expression_json(id, Some(condition), None, &condition_ctx)?);
+    }
+    return Ok(conditions);
+}
+
+/// the sorts of the variables of `expr`, from the `sorts` of the ruleset unless overridden by `sorts_json`
+fn resolve_rule_sorts(
+    expr: &Expression, sorts_json: Option<HashMap<String, Sort>>, ruleset_sorts: &HashMap<String, Sort>
+) -> HashMap<String, Sort> {
+    let mut sorts: HashMap<String, Sort> = expr.collect_var(None).iter()
+        .filter_map(|var| ruleset_sorts.get(&var.symbol).map(|sort| (var.symbol.clone(), *sort)))
+        .collect();
+    sorts.extend(sorts_json.unwrap_or_default());
+    return sorts;
+}
+
+/// the operator whose operands are reordered by the variation, ex: `+` for `A + B = B + A`
+fn find_reordered_operator(lhs: &Expression, rhs: &Expression) -> Option<String> {
+    let (lhs_children, rhs_children) = match (&lhs.children, &rhs.children) {
+        (Some(l), Some(r)) if lhs.symbol == rhs.symbol && l.len() == r.len() => (l, r),
+        _ => return None,
+    };
+    let is_permutation = lhs_children.iter().all(|c| rhs_children.contains(c))
+        && rhs_children.iter().all(|c| lhs_children.contains(c));
+    if lhs_children != rhs_children && is_permutation { return Some(lhs.symbol.clone()); }
+    return lhs_children.iter().zip(rhs_children).find_map(|(l, r)| find_reordered_operator(l, r));
+}
+
+/// `id` is the id of the rule that owns the variations, or `variations` for the ruleset variations
+/// * `NOTE`: a variation can only reorder the operands of operators in `context.commutative_ops`
 fn resolve_variations_json(
+    id: &str,
     variations_json: Option<Vec<RulesetVariationJSON>>,
     context: &Context
 ) -> Result<Vec<Expression>, ParserError> {
@@ -124,23 +543,21 @@ fn resolve_variations_json(
     let variations_json = variations_json.unwrap();
     let mut variations: Vec<Expression> = vec![];
     for variation_json in variations_json {
-        let expr = if let Some(expr) = variation_json.expr {
-            parser::to_expression(&expr, context)
-                .ok_or(ParserError::InvalidRule(expr))
-        } else if let Some(expr_prefix) = variation_json.expr_prefix {
-            parser_prefix::to_expression(&expr_prefix, context)
-                .ok_or(ParserError::InvalidRule(expr_prefix))
-        } else {
-            Err(ParserError::InvalidRule("missing expr or expr_prefix".to_string()))
-        }?;
+        let expr = parse_expression_json(id, variation_json.expr, variation_json.expr_prefix, context)?;
+        if let (Some(lhs), Some(rhs)) = (expr.lhs(), expr.rhs()) {
+            if let Some(op) = find_reordered_operator(lhs, rhs).filter(|op| !context.is_commutative(op)) {
+                return Err(ParserError::InvalidRule(format!("{}: `{}` is not commutative", id, op)));
+            }
+        }
         variations.push(expr);
     }
     return Ok(variations);
 }
 
-//TODO: support implication
+/// `expr` is a statement (equation or implication),
+/// the variations are applied to its lhs, and also to its rhs if `variate_rhs` is set
 fn f_generate_variation(
-    expr: &Expression, variation_rule: &Expression,  last_modified_address: &Address
+    expr: &Expression, variation_rule: &Expression,  last_modified_address: &Address, variate_rhs: bool
 ) -> Vec<Expression> {
     //NOTE: this assumes that the possible addresses are sorted
     let possible_addresses = expr.get_possible_equation_application_addresses(variation_rule);
@@ -156,63 +573,48 @@ fn f_generate_variation(
     if to_modify_address.is_none() { return vec![expr.clone()]; }
     let to_modify_address = to_modify_address.unwrap();
     
-    //TODO: maybe check if we want to also do variations on the rhs
-    if !to_modify_address.is_empty() && to_modify_address.head() > 0 {
+    // the addresses are sorted, so the rest of the addresses are also in the rhs
+    if !variate_rhs && !to_modify_address.is_empty() && to_modify_address.head() > 0 {
         return vec![expr.clone()];
     }
     
     let new_expr = expr.apply_equation_at(variation_rule, &to_modify_address)
         .expect("Expression::get_possible_equation_application_addresses should return valid addresses");
     
-    //NOTE: only check the equivalence of the lhs
-    let is_equivalent = match (expr.lhs(), new_expr.lhs()) {
-        (Some(expr_lhs), Some(new_lhs)) => expr_lhs.is_equivalent_to(new_lhs),
-        _ => false,
-    };
-        
-    if is_equivalent{
-        let variation0 = f_generate_variation(expr, variation_rule, &to_modify_address);
+    if expr.is_equivalent_to(&new_expr) {
+        let variation0 = f_generate_variation(expr, variation_rule, &to_modify_address, variate_rhs);
         return variation0;
     } else {
-        let variation0 = f_generate_variation(expr, variation_rule, &to_modify_address);
-        let variation1 = f_generate_variation(&new_expr, variation_rule, &to_modify_address);
+        let variation0 = f_generate_variation(expr, variation_rule, &to_modify_address, variate_rhs);
+        let variation1 = f_generate_variation(&new_expr, variation_rule, &to_modify_address, variate_rhs);
         return [variation0, variation1].concat();
     }
 }
-fn generate_variations_from_single_rule(base_expr: &Expression, variation_rule: &Expression) -> Vec<Expression> {
-    let variations = f_generate_variation(base_expr, variation_rule, &Address::default());
+fn generate_variations_from_single_rule(
+    base_expr: &Expression, variation_rule: &Expression, variate_rhs: bool
+) -> Vec<Expression> {
+    let variations = f_generate_variation(base_expr, variation_rule, &Address::default(), variate_rhs);
     return variations;
 }
 
+/// keep the last of the variations that are equivalent (as a whole statement)
 fn filter_for_unique_variations(expr_variations: &[Expression]) -> Vec<Expression> {
-    if expr_variations.len() <= 2 { return expr_variations.to_vec(); }
-    if !expr_variations[0].is_equation() { return expr_variations.to_vec(); }
-    
     let mut unique_variations = Vec::new();
     for i in 0..expr_variations.len() {
         let expr1 = &expr_variations[i];
-        let mut is_unique = true;
-        for expr2 in expr_variations.iter().skip(i+1) {
-            //NOTE: only check the equivalence of the lhs
-            if let (Some(expr1_lhs), Some(expr2_lhs)) = (expr1.lhs(), expr2.lhs()) {
-                if expr1_lhs.is_equivalent_to(expr2_lhs) {
-                    is_unique = false;
-                    break;
-                }
-            }
-        }
+        let is_unique = !expr_variations.iter().skip(i+1).any(|expr2| expr1.is_equivalent_to(expr2));
         if is_unique { unique_variations.push(expr1.clone()); }
     }
     return unique_variations;
 }
 
 // C*(A+B)
-fn generate_variations(base: &Rule, variation_rules: Vec<Expression>) -> Vec<Rule> {
+fn generate_variations(base: &Rule, variation_rules: Vec<Expression>, variate_rhs: bool) -> Vec<Rule> {
     let mut expr_variations: Vec<Expression> = vec![base.expression.clone()];
     for variation_rule in variation_rules {
         let mut new_expr_variations: Vec<Expression> = vec![];
         for expr in expr_variations {
-            let new_expr_variations0 = generate_variations_from_single_rule(&expr, &variation_rule);
+            let new_expr_variations0 = generate_variations_from_single_rule(&expr, &variation_rule, variate_rhs);
             new_expr_variations.extend(new_expr_variations0);
         }
         expr_variations = new_expr_variations;
@@ -224,21 +626,28 @@ fn generate_variations(base: &Rule, variation_rules: Vec<Expression>) -> Vec<Rul
             id: format!("{}/{}", base.id, i),
             label: base.label.clone(),
             expression: expr.clone(),
+            conditions: base.conditions.clone(),
+            ac_ops: base.ac_ops.clone(),
+            sorts: base.sorts.clone(),
         }
     }).collect();
     return rules;
 }
 
+/// `ruleset_ac_matching` enables AC matching of `context.ac_ops()`, see `Rule::ac_ops`
 fn parse_rule_vector(
-    rules_json: Vec<RuleJSON>,  name: String, ruleset_variations: Vec<Expression>, context: &Context
+    rules_json: Vec<RuleJSON>,  name: String, ruleset_variations: Vec<Expression>, ruleset_variate_rhs: bool,
+    ruleset_ac_matching: bool, context: &Context
 ) -> Result<(Vec<Rule>, Vec<String>), ParserError> {
     let mut rules: Vec<Rule> = vec![];
     let mut auto_rule_ids: Vec<String> = vec![];
     for rule_json in rules_json {
-        let id = format!("{}/{}", name, rule_json.id);
+        let id = format!("{}/{}", rule_json.namespace.as_ref().unwrap_or(&name), rule_json.id);
+        let ac_ops = if rule_json.ac_matching.unwrap_or(ruleset_ac_matching) { context.ac_ops() } else { vec![] };
         let label = rule_json.label.unwrap_or_default();
-        let rule_variations = rule_json.variations.map(|v| resolve_variations_json(Some(v), context));
+        let rule_variations = rule_json.variations.map(|v| resolve_variations_json(&id, Some(v), context));
         let auto = rule_json.auto.unwrap_or(false);
+        let variate_rhs = rule_json.variate_rhs.unwrap_or(ruleset_variate_rhs);
         
         let variations = match rule_variations {
             Some(Ok(variations)) => variations,
@@ -246,17 +655,12 @@ fn parse_rule_vector(
             None => ruleset_variations.clone(),
         };
         
-        let expression = if let Some(expr) = rule_json.expr {
-            parser::to_expression(&expr, context)
-                .ok_or(ParserError::InvalidRule(expr))
-        } else if let Some(expr_prefix) = rule_json.expr_prefix {
-            parser_prefix::to_expression(&expr_prefix, context)
-                .ok_or(ParserError::InvalidRule(expr_prefix))
-        } else {
-            Err(ParserError::InvalidRule("missing rule".to_string()))
-        }?;
+        let expression = parse_expression_json(&id, rule_json.expr, rule_json.expr_prefix, context)?;
+        let conditions = resolve_conditions_json(&id, rule_json.conditions, context)?;
+        let sorts = resolve_rule_sorts(&expression, rule_json.sorts, &rule_json.ruleset_sorts);
         
-        let var_rules = generate_variations(&Rule {id: id.clone(), label, expression}, variations);
+        let base_rule = Rule {id: id.clone(), label, expression, conditions, ac_ops, sorts};
+        let var_rules = generate_variations(&base_rule, variations, variate_rhs);
         if var_rules.len() == 1 {
             let mut rule = var_rules.first().unwrap().clone();
             rule.id.clone_from(&id);
@@ -270,14 +674,36 @@ fn parse_rule_vector(
     return Ok((rules, auto_rule_ids));
 }
 
+/// * `NOTE`: the ruleset can't have `imports`, use `parse_ruleset_from_json_with_loader` for that
 pub fn parse_ruleset_from_json(json_string: &str) -> Result<RuleSet, ParserError> {
+    return parse_ruleset_from_json_with_loader(json_string, &HashMap::new());
+}
+
+/// the `imports` of the ruleset are loaded with `loader`
+pub fn parse_ruleset_from_json_with_loader(json_string: &str, loader: &dyn RulesetLoader) -> Result<RuleSet, ParserError> {
     let ruleset_json: RulesetJSON = serde_json::from_str(json_string)?;
+    let ruleset_json = resolve_ruleset_json(ruleset_json, loader, &mut vec![])?;
     
     let name = ruleset_json.name;
     let rules_json = ruleset_json.rules;
-    let context = resolve_context_json(ruleset_json.context)?;
-    let ruleset_variations = resolve_variations_json(ruleset_json.variations, &context)?;
-    let (rule_vec, auto_rule_ids) = parse_rule_vector(rules_json, name.clone(), ruleset_variations, &context)?;
+    let context = ruleset_json.context;
+    let ruleset_variations = resolve_variations_json("variations", ruleset_json.variations, &context)?;
+    let ruleset_variate_rhs = ruleset_json.variate_rhs.unwrap_or(false);
+    let ruleset_ac_matching = ruleset_json.ac_matching.unwrap_or(false);
+    let (rule_vec, auto_rule_ids) = parse_rule_vector(
+        rules_json, name.clone(), ruleset_variations, ruleset_variate_rhs, ruleset_ac_matching, &context)?;
     let rule_ids = rule_vec.iter().map(|rule| rule.id.clone()).collect();
-    return Ok(RuleSet {name, context, rule_vec, rule_ids, auto_rule_ids});
+    let normalization = resolve_normalization_json(Some(ruleset_json.normalization), &context)?;
+    let normalization_function = ruleset_json.normalization_function;
+    if let Some(f) = normalization_function.as_ref().filter(|f| !registry::contains_normalization_function(f)) {
+        return Err(ParserError::UnknownFunction(f.clone()));
+    }
+    let get_possible_actions_function = ruleset_json.get_possible_actions_function;
+    if let Some(f) = get_possible_actions_function.as_ref().filter(|f| !registry::contains_get_possible_actions_function(f)) {
+        return Err(ParserError::UnknownFunction(f.clone()));
+    }
+    return Ok(RuleSet {
+        name, context, rule_vec, rule_ids, auto_rule_ids, normalization,
+        normalization_function, get_possible_actions_function,
+    });
 }
\ No newline at end of file
@@ -0,0 +1,100 @@
+use std::collections::{HashSet, VecDeque};
+use crate::address;
+use crate::expression::{Address, Expression};
+use crate::rule::RuleSet;
+use crate::worksheet::{Action, WorkableExpressionSequence, WorksheetContext, WorksheetError};
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct SearchLimits {
+    pub max_depth: usize, // maximum number of rule applications
+    pub max_nodes: usize, // maximum number of expanded expressions
+}
+
+impl Default for SearchLimits {
+    fn default() -> Self {
+        return SearchLimits { max_depth: 8, max_nodes: 10000 };
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SearchError {
+    EmptySequence,
+    /// every reachable expression within `max_depth` is visited
+    GoalNotFound,
+    NodeLimitReached,
+    AutoRules(WorksheetError),
+}
+impl From<WorksheetError> for SearchError {
+    fn from(err: WorksheetError) -> Self {
+        SearchError::AutoRules(err)
+    }
+}
+
+/// search for a sequence of rule applications from `start` to `goal` using the rules of `ruleset`
+pub fn search(ruleset: &RuleSet, start: &Expression, goal: &Expression, limits: &SearchLimits)
+-> Result<WorkableExpressionSequence, SearchError> {
+    let mut ctx = WorksheetContext::default();
+    ctx.set_ruleset(ruleset.clone());
+    let mut seq = WorkableExpressionSequence::new(ctx);
+    seq.push(Action::Introduce("Introduce".to_string()), start.clone())?;
+    return seq.search_for(goal, limits);
+}
+
+impl WorkableExpressionSequence {
+    /// breadth-first search for a sequence of rule applications from the last expression to `goal`,
+    /// the returned sequence is this sequence with the found steps appended
+    /// * the goal is reached by an expression that is equivalent to it (`Expression::is_equivalent_to`)
+    /// * `NOTE`: every expression is only visited once, `0 + x` and `x + 0` are different expressions
+    ///   since a rule can apply to only one of them
+    pub fn search_for(&self, goal: &Expression, limits: &SearchLimits) -> Result<WorkableExpressionSequence, SearchError> {
+        if self.history.is_empty() { return Err(SearchError::EmptySequence); }
+        if self.last_expression().is_equivalent_to(goal) { return Ok(self.clone()); }
+        let start_len = self.history.len();
+        let mut visited: HashSet<Expression> = HashSet::from([self.last_expression().clone()]);
+        let mut queue: VecDeque<WorkableExpressionSequence> = VecDeque::from([self.clone()]);
+        let mut expanded_nodes = 0;
+
+        while let Some(seq) = queue.pop_front() {
+            let depth = seq.history[start_len..].iter().filter(|line| !line.is_auto_generated).count();
+            if depth >= limits.max_depth { continue; }
+            if expanded_nodes >= limits.max_nodes { return Err(SearchError::NodeLimitReached); }
+            expanded_nodes += 1;
+
+            for next_seq in seq.get_next_sequences_by_rules() {
+                if next_seq.last_expression().is_equivalent_to(goal) { return Ok(next_seq); }
+                if !visited.insert(next_seq.last_expression().clone()) { continue; }
+                queue.push_back(next_seq);
+            }
+        }
+        return Err(SearchError::GoalNotFound);
+    }
+
+    /// every sequence that can be reached by applying one rule (at any address) to the last expression
+    fn get_next_sequences_by_rules(&self) -> Vec<WorkableExpressionSequence> {
+        let ctx = self.get_context();
+        let expr = self.last_expression();
+        let mut result = vec![];
+        for rule_id in &ctx.rule_ids {
+            let rule = match ctx.rule_map.get(rule_id) {
+                Some(rule) => rule,
+                None => continue,
+            };
+            let addresses: Vec<Address> = if rule.expression.is_implication() {
+                vec![address![]]
+            } else {
+                expr.get_possible_rule_application_addresses(rule, &ctx.expression_context)
+            };
+            for addr in addresses {
+                if let Ok((new_expr, assumptions)) = expr.apply_rule_in_context_at(rule, &addr, &ctx.expression_context) {
+                    let mut new_seq = self.clone();
+                    // a step after which the auto rules don't terminate is not taken
+                    let provenance = expr.get_rule_provenance_at(rule, &addr, &ctx.expression_context);
+                    let action = Action::ApplyRule(rule.label.clone());
+                    if new_seq.push_with_provenance(action, new_expr, assumptions, provenance).is_err() { continue; }
+                    result.push(new_seq);
+                }
+            }
+        }
+        return result;
+    }
+}
@@ -0,0 +1,196 @@
+use crate::address;
+use crate::algebra::{AlgebraCtxFlags, AlgebraError};
+use crate::arithmetic::{get_arithmetic_ctx, ArithmeticOperator};
+use crate::expression::{Address, Expression, ExpressionError, expression_builder as eb};
+use crate::number::Number;
+use crate::worksheet::{Action, WorkableExpressionSequence, WorksheetContext};
+
+const LIMIT_OF_SOLVER_STEPS: usize = 50;
+
+impl Expression {
+    /// `(a, b)` such that the expression is `a * var + b`,
+    /// or `None` if it's not linear in `var` (or the coefficients are not numeric)
+    pub fn linear_coefficients(&self, var: &str) -> Option<(Number, Number)> {
+        use ArithmeticOperator::*;
+        let (zero, one) = (Number::integer(0), Number::integer(1));
+        if self.is_value() {
+            if self.symbol == var { return Some((one, zero)); }
+            return Number::parse(&self.symbol).map(|b| (zero, b));
+        }
+        let children = self.children.as_ref()?.iter()
+            .map(|c| c.linear_coefficients(var))
+            .collect::<Option<Vec<(Number, Number)>>>()?;
+        let result = match self.identify_arithmetic_operator()? {
+            Add | AddTrain => children.iter().try_fold((zero.clone(), zero), |(a, b), (ca, cb)|
+                Some((a.checked_add(ca)?, b.checked_add(cb)?)))?,
+            Sub => (children[0].0.checked_sub(&children[1].0)?, children[0].1.checked_sub(&children[1].1)?),
+            Negative => (children[0].0.checked_neg()?, children[0].1.checked_neg()?),
+            Mul | MulTrain => {
+                let mut result = (zero, one);
+                for (ca, cb) in children {
+                    // var * var is not linear
+                    if !result.0.is_zero() && !ca.is_zero() { return None; }
+                    let a = result.0.checked_mul(&cb)?.checked_add(&ca.checked_mul(&result.1)?)?;
+                    result = (a, result.1.checked_mul(&cb)?);
+                }
+                result
+            },
+            Div => {
+                let (ca, cb) = &children[1];
+                if !ca.is_zero() || cb.is_zero() { return None; }
+                (children[0].0.checked_div(cb)?, children[0].1.checked_div(cb)?)
+            },
+            // ex: `sqrt(4)`
+            _ if !self.contains_symbol(var) => (zero, self.calculate_numeric()?),
+            _ => return None,
+        };
+        return Some(result);
+    }
+
+    pub fn contains_symbol(&self, symbol: &str) -> bool {
+        if self.symbol == symbol && self.is_value() { return true; }
+        return self.children.iter().flatten().any(|c| c.contains_symbol(symbol));
+    }
+
+    fn find_value(&self, symbol: &str) -> Option<&Expression> {
+        if self.symbol == symbol && self.is_value() { return Some(self); }
+        return self.children.iter().flatten().find_map(|c| c.find_value(symbol));
+    }
+
+    /// solve a linear equation in `var` (with numeric coefficients),
+    /// the result is a worked solution that ends with `var = value`
+    pub fn solve_linear_for(&self, var: &str) -> Result<WorkableExpressionSequence, AlgebraError> {
+        let mut expression_ctx = get_arithmetic_ctx();
+        expression_ctx.add_flag(AlgebraCtxFlags::SimplifyOneAndZero);
+        let mut ctx = WorksheetContext::default();
+        ctx.expression_context = expression_ctx;
+        ctx.set_normalization_function_by_name("algebra");
+        ctx.set_get_possible_actions_function_by_name("polynomial");
+        let mut seq = WorkableExpressionSequence::new(ctx);
+        seq.push(Action::Introduce("Introduce".to_string()), self.clone())
+            .map_err(|_| AlgebraError::FunctionApplicationError)?;
+        seq.solve_linear_for(var)?;
+        return Ok(seq);
+    }
+}
+
+impl WorkableExpressionSequence {
+    /// continue the sequence until the last expression is `var = value`, one step at a time:
+    /// * calculate the numeric parts
+    /// * collect the like terms of each side
+    /// * move the `var` terms to the lhs, and the constants to the rhs
+    /// * divide both side by the coefficient of `var`
+    /// * expand the sides when no term can be moved (ex: `7 - x` into `-x + 7`)
+    /// * `NOTE`: every step is a possible action of the context (see `polynomial::get_possible_actions`),
+    ///   so the sequence can be replayed (see `ExpressionSequence::verify_in_context`)
+    pub fn solve_linear_for(&mut self, var: &str) -> Result<(), AlgebraError> {
+        let var_expr = self.last_expression().find_value(var).cloned().ok_or(AlgebraError::NotLinear)?;
+        for _ in 0..LIMIT_OF_SOLVER_STEPS {
+            let expr = self.last_expression().clone();
+            let (lhs, rhs) = match (expr.lhs(), expr.rhs()) {
+                (Some(lhs), Some(rhs)) => (lhs, rhs),
+                _ => return Err(ExpressionError::NotAnEquation.into()),
+            };
+            if lhs == &var_expr && rhs.is_numeric_constant() { return Ok(()); }
+            if self.try_calculate_numeric_part() { continue; }
+
+            let (a_lhs, b_lhs) = lhs.linear_coefficients(var).ok_or(AlgebraError::NotLinear)?;
+            let (a_rhs, _) = rhs.linear_coefficients(var).ok_or(AlgebraError::NotLinear)?;
+            if a_lhs == a_rhs { return Err(AlgebraError::NoUniqueSolution); }
+            if self.try_apply_named_action(&vec![address![0]], &["Collect like terms"]) { continue; }
+            if self.try_apply_named_action(&vec![address![1]], &["Collect like terms"]) { continue; }
+
+            if a_lhs.is_zero() {
+                if !self.try_apply_named_action(&vec![address![]], &["Flip the equation"]) {
+                    return Err(AlgebraError::FunctionApplicationError);
+                }
+                continue;
+            }
+            let step = if !a_rhs.is_zero() {
+                Some((ArithmeticOperator::Sub, linear_expression(&var_expr, &a_rhs, &Number::integer(0))))
+            } else if b_lhs.is_negative() {
+                Some((ArithmeticOperator::Add, b_lhs.checked_neg().ok_or(AlgebraError::NotLinear)?.to_expression()))
+            } else if !b_lhs.is_zero() {
+                Some((ArithmeticOperator::Sub, b_lhs.to_expression()))
+            } else if !a_lhs.is_one() {
+                Some((ArithmeticOperator::Div, a_lhs.to_expression()))
+            } else {
+                // ex: `(2 * x) / 2 = 2`
+                None
+            };
+            if let Some((op, operand)) = &step {
+                if self.try_apply_to_both_side_by_selection(var, op, operand) { continue; }
+            }
+            if !self.try_apply_named_action(&vec![address![0]], &["Expand", "Fully simplify"])
+                && !self.try_apply_named_action(&vec![address![1]], &["Expand", "Fully simplify"]) {
+                return Err(AlgebraError::FunctionApplicationError);
+            }
+        }
+        return Err(AlgebraError::StepLimitReached);
+    }
+
+    /// apply the first possible action (of the selection `addr_vec`) that `is_wanted` and changes the expression
+    fn try_apply_possible_action(&mut self, addr_vec: &Vec<Address>, is_wanted: impl Fn(&Action, &Expression) -> bool) -> bool {
+        let expr = self.last_expression();
+        let index = self.get_possible_actions(addr_vec).iter()
+            .position(|(action, new_expr)| new_expr != expr && is_wanted(action, new_expr));
+        return match index {
+            Some(index) => self.try_apply_action_by_index(addr_vec, index),
+            None => false,
+        };
+    }
+
+    /// apply the first possible action (of the selection `addr_vec`) named one of `names`
+    fn try_apply_named_action(&mut self, addr_vec: &Vec<Address>, names: &[&str]) -> bool {
+        return self.try_apply_possible_action(addr_vec, |action, _| names.contains(&action.as_str()));
+    }
+
+    /// calculate the first numeric part (ex: `3 + 1`) that changes the expression
+    fn try_calculate_numeric_part(&mut self) -> bool {
+        let is_calculation = |action: &Action, _: &Expression| action.as_str().starts_with("Calculate");
+        for addr in self.last_expression().get_all_addresses() {
+            if self.try_apply_possible_action(&vec![addr], is_calculation) { return true; }
+        }
+        return false;
+    }
+
+    /// apply `op operand` to both side as the possible action of a selected term (ex: the `1` of `2 * x - 1 = 3`,
+    /// or the `x` of `-x = 5`),
+    /// the action must give the same coefficients of `var` on each side
+    fn try_apply_to_both_side_by_selection(&mut self, var: &str, op: &ArithmeticOperator, operand: &Expression) -> bool {
+        let expr = self.last_expression().clone();
+        let expected = match expr.apply_simple_arithmetic_to_both_side(op, operand) {
+            Ok(expected) => linear_coefficients_of_sides(&expected, var),
+            Err(_) => return false,
+        };
+        let is_expected = |action: &Action, new_expr: &Expression| {
+            return action.as_str().ends_with("to both side") && linear_coefficients_of_sides(new_expr, var) == expected;
+        };
+        for addr in expr.get_all_addresses().into_iter().filter(|addr| addr.path.len() >= 2 && addr.sub.is_none()) {
+            if self.try_apply_possible_action(&vec![address![], addr], is_expected) { return true; }
+        }
+        return false;
+    }
+}
+
+/// `linear_coefficients` of the lhs and rhs of an equation
+fn linear_coefficients_of_sides(expr: &Expression, var: &str) -> Option<((Number, Number), (Number, Number))> {
+    return Some((expr.lhs()?.linear_coefficients(var)?, expr.rhs()?.linear_coefficients(var)?));
+}
+
+/// `a * var + b`, without the parts that are not needed
+fn linear_expression(var_expr: &Expression, a: &Number, b: &Number) -> Expression {
+    if a.is_zero() { return b.to_expression(); }
+    let var_term = if a.is_one() {
+        var_expr.clone()
+    } else if a.checked_neg().is_some_and(|a| a.is_one()) {
+        eb::unary("-", var_expr.clone())
+    } else {
+        eb::binary("*", a.to_expression(), var_expr.clone())
+    };
+    if b.is_zero() { return var_term; }
+    return match b.checked_neg() {
+        Some(neg_b) if b.is_negative() => eb::binary("-", var_term, neg_b.to_expression()),
+        _ => eb::binary("+", var_term, b.to_expression()),
+    };
+}
@@ -0,0 +1,57 @@
+use std::collections::HashMap;
+use std::fmt;
+use serde::{Serialize, Deserialize};
+use crate::expression::{Context, Expression, ExpressionType};
+use crate::number::Number;
+
+/// the kind of value an expression stands for,
+/// set for parameters and (the result of) operators in `Context::sorts`, and for the variables of a rule in `Rule::sorts`
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub enum Sort {
+    Boolean,
+    Integer,
+    Real,
+    Set,
+    Matrix,
+}
+
+impl Sort {
+    /// every value of `self` is also a value of `other`, ex: `Integer` is a subsort of `Real`
+    pub fn is_subsort_of(&self, other: &Sort) -> bool {
+        return self == other || (*self == Sort::Integer && *other == Sort::Real);
+    }
+
+    /// the sort of `expr`, `None` if it can't be inferred
+    /// * values and operators are looked up in `sorts` first
+    /// * numeric constants are `Integer` or `Real`
+    /// * other operators have the sort of their children, if they all have the same sort
+    pub fn infer(expr: &Expression, sorts: &HashMap<String, Sort>) -> Option<Sort> {
+        if let Some(sort) = sorts.get(&expr.symbol) { return Some(*sort); }
+        if expr.is_operator() {
+            let mut children_sorts = expr.children.as_ref()?.iter().map(|child| Sort::infer(child, sorts));
+            let first = children_sorts.next()??;
+            return children_sorts.all(|sort| sort == Some(first)).then_some(first);
+        }
+        if expr.exp_type != ExpressionType::ValueConst { return None; }
+        return match Number::parse(&expr.symbol)? {
+            n if n.is_integer() => Some(Sort::Integer),
+            _ => Some(Sort::Real),
+        };
+    }
+}
+
+impl fmt::Display for Sort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl Context {
+    pub fn set_sort(&mut self, symbol: impl ToString, sort: Sort) -> Context {
+        self.sorts.insert(symbol.to_string(), sort);
+        return self.clone();
+    }
+    pub fn sort_of(&self, expr: &Expression) -> Option<Sort> {
+        return Sort::infer(expr, &self.sorts);
+    }
+}
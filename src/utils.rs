@@ -1,5 +1,7 @@
 use std::collections::HashMap;
 use std::hash::Hash;
+use num_bigint::BigInt;
+use num_traits::Zero;
 
 #[macro_export]
 macro_rules! vec_strings {
@@ -40,10 +42,11 @@ pub fn is_number(s: &str) -> bool {
 
 // Math Functions ===================================================
 
-pub fn gcd(a: i64, b: i64) -> i64 {
-    if b == 0 { a } else { gcd(b, a % b) }
+pub fn gcd(a: &BigInt, b: &BigInt) -> BigInt {
+    if b.is_zero() { a.clone() } else { gcd(b, &(a % b)) }
 }
-pub fn lcm(a: i64, b: i64) -> i64 {
+pub fn lcm(a: &BigInt, b: &BigInt) -> BigInt {
+    if a.is_zero() && b.is_zero() { return BigInt::zero(); }
     a / gcd(a,b) * b
 }
 
@@ -0,0 +1,149 @@
+use std::collections::HashSet;
+use std::fmt;
+use crate::expression::{Context, Expression, ExpressionType};
+use crate::rule::{Rule, RuleSet};
+use crate::sort::Sort;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValidationError {
+    DuplicateId(String),
+    /// the expression of the rule is neither an equation nor an implication
+    NotAStatement(String),
+    /// variables of the rhs that don't appear in the lhs
+    UnboundVariables { id: String, variables: Vec<String> },
+    /// the repeated expression of a variadic (`...`) has no param ending with `_i` (or `_j`, ...)
+    MissingVariadicParam { id: String, expr: String },
+    /// auto rules that can be applied again to their own result, one after the other
+    NonTerminating(Vec<String>),
+    /// every application of the rule `id` is also an application of the rule `covered_by`
+    Redundant { id: String, covered_by: String },
+}
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationError::DuplicateId(id) => write!(f, "duplicate id: {}", id),
+            ValidationError::NotAStatement(id) => write!(f, "{}: not an equation or implication", id),
+            ValidationError::UnboundVariables { id, variables } => {
+                write!(f, "{}: variables not in the lhs: {}", id, variables.join(", "))
+            },
+            ValidationError::MissingVariadicParam { id, expr } => {
+                write!(f, "{}: variadic without param (ex: `A_i`) in `{}`", id, expr)
+            },
+            ValidationError::NonTerminating(ids) => write!(f, "auto rules can loop: {}", ids.join(" -> ")),
+            ValidationError::Redundant { id, covered_by } => write!(f, "{}: redundant with {}", id, covered_by),
+        }
+    }
+}
+
+impl RuleSet {
+    /// check the rules for mistakes that `parse_ruleset_from_json` doesn't catch,
+    /// every error found is reported, in the order of the rules
+    /// * `NOTE`: a loop of auto rules is only detected if it takes at most 2 rules
+    pub fn validate(&self) -> Result<(), Vec<ValidationError>> {
+        let mut errors = Vec::new();
+        let mut ids = HashSet::new();
+        for rule in self.rule_vec.iter() {
+            if !ids.insert(rule.id.clone()) { errors.push(ValidationError::DuplicateId(rule.id.clone())); }
+            errors.extend(validate_rule(rule));
+        }
+        errors.extend(validate_auto_rules(self));
+        errors.extend(find_redundant_rules(self));
+        if !errors.is_empty() { return Err(errors); }
+        return Ok(());
+    }
+}
+
+fn validate_rule(rule: &Rule) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+    let expr = &rule.expression;
+    let (lhs, rhs) = match (expr.lhs(), expr.rhs()) {
+        (Some(lhs), Some(rhs)) if expr.is_equation() || expr.is_implication() => (lhs, rhs),
+        _ => return vec![ValidationError::NotAStatement(rule.id.clone())],
+    };
+
+    let lhs_vars = lhs.get_variable_symbols();
+    let mut variables = rhs.get_variable_symbols().into_iter()
+        .filter(|v| !lhs_vars.contains(v)).collect::<Vec<String>>();
+    variables.sort();
+    variables.dedup();
+    if !variables.is_empty() {
+        errors.push(ValidationError::UnboundVariables { id: rule.id.clone(), variables });
+    }
+
+    if let Some(inner) = find_variadic_without_param(expr) {
+        errors.push(ValidationError::MissingVariadicParam { id: rule.id.clone(), expr: inner.to_string(true) });
+    }
+    return errors;
+}
+
+/// the repeated expression of the first variadic that has no variadic param
+fn find_variadic_without_param(expr: &Expression) -> Option<&Expression> {
+    let children = expr.children.as_ref()?;
+    if expr.exp_type == ExpressionType::Variadic {
+        let inner = children.first()?;
+        if inner.collect_variadic_param(None).is_empty() { return Some(inner); }
+    }
+    return children.iter().find_map(find_variadic_without_param);
+}
+
+/// `true` if the lhs of `next` matches the rhs of `rule` (or a part of it),
+/// so `next` can always be applied after `rule`
+fn can_follow(rule: &Rule, next: &Rule, ctx: &Context) -> bool {
+    let (rhs, lhs) = match (rule.expression.rhs(), next.expression.lhs()) {
+        (Some(rhs), Some(lhs)) => (rhs, lhs),
+        _ => return false,
+    };
+    return !rhs.get_pattern_matches_with(lhs, &next.match_options(ctx)).is_empty();
+}
+
+fn validate_auto_rules(ruleset: &RuleSet) -> Vec<ValidationError> {
+    let ctx = &ruleset.context;
+    let auto_rules = ruleset.rule_vec.iter()
+        .filter(|rule| ruleset.auto_rule_ids.contains(&rule.id)).collect::<Vec<&Rule>>();
+    let mut errors = Vec::new();
+    for (i, rule) in auto_rules.iter().enumerate() {
+        if can_follow(rule, rule, ctx) {
+            errors.push(ValidationError::NonTerminating(vec![rule.id.clone()]));
+            continue;
+        }
+        for other in auto_rules.iter().skip(i + 1) {
+            if can_follow(rule, other, ctx) && can_follow(other, rule, ctx) {
+                errors.push(ValidationError::NonTerminating(vec![rule.id.clone(), other.id.clone()]));
+            }
+        }
+    }
+    return errors;
+}
+
+/// `true` if `rule` is an instance of `general`, ex: `2 * 0 = 0` is an instance of `X * 0 = 0`
+/// * `NOTE`: `general` can't have conditions, and its sorts must hold for `rule`
+fn is_covered_by(rule: &Rule, general: &Rule, ctx: &Context) -> bool {
+    if !general.conditions.is_empty() { return false; }
+    let map = match rule.expression.pattern_match_this_node(&general.expression) {
+        Some(map) => map,
+        None => return false,
+    };
+    if rule.expression != general.expression.apply_match_map(&map) { return false; }
+    let mut sorts = ctx.sorts.clone();
+    sorts.extend(rule.sorts.clone());
+    return general.sorts.iter().all(|(variable, required)| match map.get(variable) {
+        Some(expr) => Sort::infer(expr, &sorts).is_some_and(|sort| sort.is_subsort_of(required)),
+        None => true,
+    });
+}
+
+/// the variations of a single rule are already unique, so the rules compared here come from different rules
+fn find_redundant_rules(ruleset: &RuleSet) -> Vec<ValidationError> {
+    let ctx = &ruleset.context;
+    let rules = &ruleset.rule_vec;
+    let mut errors = Vec::new();
+    for (j, rule) in rules.iter().enumerate() {
+        let earlier = rules.iter().take(j).find(|other| other.id != rule.id && is_covered_by(rule, other, ctx));
+        let later = rules.iter().skip(j + 1).find(|other| other.id != rule.id
+            && is_covered_by(rule, other, ctx) && !is_covered_by(other, rule, ctx));
+        if let Some(general) = earlier.or(later) {
+            errors.push(ValidationError::Redundant { id: rule.id.clone(), covered_by: general.id.clone() });
+        }
+    }
+    return errors;
+}
@@ -0,0 +1,141 @@
+use std::fmt;
+use crate::rule::RuleSet;
+use crate::worksheet::{Action, ExpressionLine, ExpressionSequence, Provenance, WorkableExpressionSequence, WorksheetContext, WorksheetError};
+
+/// the first line of a sequence that can't be reproduced
+#[derive(Debug, Clone, PartialEq)]
+pub struct VerificationError {
+    /// index of the line in `ExpressionSequence::history`
+    pub index: usize,
+    pub reason: InvalidStep,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum InvalidStep {
+    EmptySequence,
+    /// only the first line can (and must) be introduced
+    MisplacedIntroduce,
+    /// the line has no provenance (`Provenance::Unknown`), or is an action pushed without addresses,
+    /// so it can't be replayed
+    Unverifiable(String),
+    UnknownRule(String),
+    /// no possible action at the recorded addresses has the same name and expression
+    ActionNotAvailable(String),
+    RuleNotApplicable { rule_id: String, error: String },
+    /// the auto rules generate a line that is not in the sequence
+    MissingAutoStep,
+    /// the line is marked as auto generated, but the auto rules don't generate it
+    UnexpectedAutoStep,
+    ExpressionMismatch { expected: String, found: String },
+    ActionMismatch { expected: String, found: String },
+    AssumptionsMismatch,
+    /// the rule id, address or bindings differ from the replayed ones
+    ProvenanceMismatch,
+    AutoRules(WorksheetError),
+}
+impl fmt::Display for VerificationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: ", self.index)?;
+        match &self.reason {
+            InvalidStep::EmptySequence => write!(f, "empty sequence"),
+            InvalidStep::MisplacedIntroduce => write!(f, "only the first line can be introduced"),
+            InvalidStep::Unverifiable(action) => write!(f, "`{}` can't be replayed", action),
+            InvalidStep::UnknownRule(id) => write!(f, "unknown rule: {}", id),
+            InvalidStep::ActionNotAvailable(action) => write!(f, "`{}` is not a possible action", action),
+            InvalidStep::RuleNotApplicable { rule_id, error } => write!(f, "{} can't be applied: {}", rule_id, error),
+            InvalidStep::MissingAutoStep => write!(f, "missing auto generated line"),
+            InvalidStep::UnexpectedAutoStep => write!(f, "unexpected auto generated line"),
+            InvalidStep::ExpressionMismatch { expected, found } => write!(f, "expected `{}`, found `{}`", expected, found),
+            InvalidStep::ActionMismatch { expected, found } => write!(f, "expected action `{}`, found `{}`", expected, found),
+            InvalidStep::AssumptionsMismatch => write!(f, "assumptions don't match"),
+            InvalidStep::ProvenanceMismatch => write!(f, "provenance doesn't match"),
+            InvalidStep::AutoRules(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl ExpressionSequence {
+    /// replay every line of `history` with the rules of `ruleset`, see `verify_in_context`
+    pub fn verify(&self, ruleset: &RuleSet) -> Result<(), VerificationError> {
+        let mut ctx = WorksheetContext::default();
+        ctx.set_ruleset(ruleset.clone());
+        return self.verify_in_context(ctx);
+    }
+
+    /// replay every line of `history` from its provenance, and check that it gives exactly the same lines,
+    /// including the normalization and the lines generated by the auto rules
+    /// * the first line must be introduced, its expression is taken as is
+    /// * the other lines must be rule applications (`Provenance::Rule`),
+    ///   or possible actions at the recorded addresses (`Provenance::Action`, see `get_possible_actions`)
+    /// * `NOTE`: only the current branch is verified, and the labels and `unnormalized_expr` of the lines are not checked
+    pub fn verify_in_context(&self, ctx: WorksheetContext) -> Result<(), VerificationError> {
+        let lines = &self.history;
+        if lines.is_empty() { return Err(VerificationError { index: 0, reason: InvalidStep::EmptySequence }); }
+        let mut replay = WorkableExpressionSequence::new(ctx);
+        let mut index = 0;
+        while index < lines.len() {
+            let error = |reason: InvalidStep| VerificationError { index, reason };
+            let line = &lines[index];
+            if line.is_auto_generated { return Err(error(InvalidStep::UnexpectedAutoStep)); }
+            let replay_len = replay.history.len();
+            let pushed = match &line.provenance {
+                Provenance::Introduce if index == 0 => {
+                    let expr = line.unnormalized_expr.clone().unwrap_or(line.expr.clone());
+                    replay.push(line.action.clone(), expr)
+                },
+                Provenance::Introduce => return Err(error(InvalidStep::MisplacedIntroduce)),
+                _ if index == 0 => return Err(error(InvalidStep::MisplacedIntroduce)),
+                Provenance::Rule { rule_id, address, .. } => {
+                    let ctx = replay.get_context();
+                    let rule = ctx.rule_map.get(rule_id)
+                        .ok_or(error(InvalidStep::UnknownRule(rule_id.clone())))?;
+                    let expr = replay.last_expression();
+                    let (new_expr, assumptions) = expr.apply_rule_in_context_at(rule, address, &ctx.expression_context)
+                        .map_err(|err| error(InvalidStep::RuleNotApplicable { rule_id: rule_id.clone(), error: format!("{:?}", err) }))?;
+                    let provenance = expr.get_rule_provenance_at(rule, address, &ctx.expression_context);
+                    replay.push_with_provenance(Action::ApplyRule(rule.label.clone()), new_expr, assumptions, provenance)
+                },
+                Provenance::Action { addr_vec, action } if !addr_vec.is_empty() => {
+                    let (action, expr) = replay.get_possible_actions(addr_vec).into_iter()
+                        .find(|(a, e)| a.as_str() == action && e == &line.expr)
+                        .ok_or(error(InvalidStep::ActionNotAvailable(action.clone())))?;
+                    let (provenance, assumptions) = replay.find_rule_provenance(&action, addr_vec, &expr);
+                    replay.push_with_provenance(action, expr, assumptions, provenance)
+                },
+                Provenance::Action { .. } | Provenance::Unknown => {
+                    return Err(error(InvalidStep::Unverifiable(line.action.to_string())));
+                },
+            };
+            if let Err(err) = pushed {
+                return Err(error(InvalidStep::AutoRules(err)));
+            }
+
+            for expected in replay.history[replay_len..].iter() {
+                let found = match lines.get(index) {
+                    Some(found) => found,
+                    None => return Err(VerificationError { index, reason: InvalidStep::MissingAutoStep }),
+                };
+                if let Some(reason) = compare_lines(expected, found) {
+                    return Err(VerificationError { index, reason });
+                }
+                index += 1;
+            }
+        }
+        return Ok(());
+    }
+}
+
+/// `None` if `found` is the same as the replayed line `expected`
+fn compare_lines(expected: &ExpressionLine, found: &ExpressionLine) -> Option<InvalidStep> {
+    if expected.is_auto_generated && !found.is_auto_generated { return Some(InvalidStep::MissingAutoStep); }
+    if !expected.is_auto_generated && found.is_auto_generated { return Some(InvalidStep::UnexpectedAutoStep); }
+    if expected.expr != found.expr {
+        return Some(InvalidStep::ExpressionMismatch { expected: expected.expr.to_string(true), found: found.expr.to_string(true) });
+    }
+    if expected.action != found.action {
+        return Some(InvalidStep::ActionMismatch { expected: expected.action.to_string(), found: found.action.to_string() });
+    }
+    if expected.assumptions != found.assumptions { return Some(InvalidStep::AssumptionsMismatch); }
+    if expected.provenance != found.provenance { return Some(InvalidStep::ProvenanceMismatch); }
+    return None;
+}
@@ -1,56 +1,152 @@
 use std::{collections::HashMap, fmt::{self, Debug}};
-use crate::expression::Address;
+use crate::expression::{Address, MatchMap};
+use crate::history::{BranchComparison, HistoryTree};
 use crate::rule::{Rule, RuleSet};
+use crate::registry;
 use super::expression::{Context, Expression};
+use serde::{Serialize, Deserialize};
 
-type NormalizationFunction = fn(&Expression, &Context) -> Expression;
-type GetPossibleActionsFunction = fn(&Expression, &WorksheetContext, &Vec<Address>) -> Vec<(Action,Expression)>;
+pub type NormalizationFunction = fn(&Expression, &Context) -> Expression;
+pub type GetPossibleActionsFunction = fn(&Expression, &WorksheetContext, &Vec<Address>) -> Vec<(Action,Expression)>;
 
 const LIMIT_OF_AUTO_GENERATED_STEPS: usize = 100;
 
-#[derive(Debug, PartialEq, Clone)]
+/// version of the JSON document produced by `Worksheet::to_json`
+pub const WORKSHEET_FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub enum Action {
     Introduce(String),
     ApplyRule(String),
     ApplyAction(String),
 }
 
-#[derive(Default, Clone, PartialEq)]
+/// how a line is derived from the line before it
+#[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
+pub enum Provenance {
+    /// lines stored before the provenance was recorded
+    #[default]
+    Unknown,
+    Introduce,
+    /// a rule of the ruleset, applied by the user or by the auto rules (see `ExpressionLine::is_auto_generated`)
+    /// * `bindings` are the expressions matched by the variables of the lhs of the rule
+    Rule { rule_id: String, address: Address, bindings: MatchMap },
+    /// any other action, ex: `Calculate 3 + 1 = 4` or `Substitute from eq1`
+    /// * `addr_vec` are the addresses the action is chosen at (see `get_possible_actions`),
+    ///   empty if the line is pushed directly
+    Action { addr_vec: Vec<Address>, action: String },
+}
+
+/// the functions are either set directly or by their name in the `registry`
+/// * `NOTE`: only the names are serialized, `Worksheet::to_json` looks up the functions that are set directly in the `registry`
+#[derive(Default, Clone, PartialEq, Serialize, Deserialize)]
+#[allow(unpredictable_function_pointer_comparisons)]
 pub struct WorksheetContext {
     pub expression_context : Context,
+    #[serde(skip)]
     normalization_function: Option<NormalizationFunction>,
+    normalization_function_name: Option<String>,
     pub rule_map: HashMap<String, Rule>,
     pub rule_ids: Vec<String>,
+    #[serde(skip)]
     get_possible_actions_function: Option<GetPossibleActionsFunction>,
+    get_possible_actions_function_name: Option<String>,
     pub labelled_expression: Vec<(String, Expression)>,
     pub auto_rule_ids: Vec<String>, // list of rules that needs to be automatically applied
+    pub normalization: Vec<Expression>, // equations applied after the normalization function
 }
 
-#[derive(Clone, PartialEq)]
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
 pub struct ExpressionLine {
     pub action: Action,
     pub expr: Expression,
     pub label: Option<String>,
     pub is_auto_generated: bool,
+    /// conditions of the applied rule that can't be decided, this line is only valid if they hold
+    #[serde(default)]
+    pub assumptions: Vec<Expression>,
+    #[serde(default)]
+    pub provenance: Provenance,
+    /// the expression before the normalization, `None` if the normalization didn't change it
+    #[serde(default)]
+    pub unnormalized_expr: Option<Expression>,
 }
 
+/// `history` is the current branch of `tree`
 #[derive(Default, Clone, PartialEq)]
 pub struct WorkableExpressionSequence {
     pub history: Vec<ExpressionLine>,
     context: WorksheetContext,
+    tree: HistoryTree,
+    /// the last lines of the current branch before each `undo`
+    redo_stack: Vec<usize>,
 }
 
-#[derive(Default, Clone, PartialEq)]
+#[derive(Default, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ExpressionSequence {
     pub history: Vec<ExpressionLine>,
+    /// every branch, empty for sequences stored before branches were kept
+    #[serde(default)]
+    pub tree: HistoryTree,
 }
 
-#[derive(Default, PartialEq)]
+#[derive(Default, PartialEq, Serialize, Deserialize)]
 pub struct Worksheet {
     expression_sequences: Vec<ExpressionSequence>,
     context: WorksheetContext,
 }
 
+#[derive(Debug, PartialEq, Clone)]
+pub enum WorksheetError {
+    InvalidJSON(String),
+    UnsupportedVersion(u32),
+    /// the ids of the auto rules that were applied in a loop
+    NonTerminatingAutoRules(Vec<String>),
+    /// a function that is set directly is not in the `registry`, so it can't be serialized
+    UnregisteredFunction(String),
+    /// the normalization equations are still applicable after the limit of rewrite passes
+    NonTerminatingNormalization,
+    /// no expression has the label
+    UnknownLabel(String),
+}
+impl fmt::Display for WorksheetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WorksheetError::InvalidJSON(err) => write!(f, "invalid JSON: {}", err),
+            WorksheetError::UnsupportedVersion(version) => {
+                write!(f, "unsupported worksheet version: {} (expected {})", version, WORKSHEET_FORMAT_VERSION)
+            },
+            WorksheetError::NonTerminatingAutoRules(ids) => {
+                write!(f, "auto rules don't terminate: {}", ids.join(" -> "))
+            },
+            WorksheetError::UnregisteredFunction(kind) => write!(f, "{} function is not in the registry", kind),
+            WorksheetError::NonTerminatingNormalization => write!(f, "normalization equations don't terminate"),
+            WorksheetError::UnknownLabel(label) => write!(f, "unknown label: {}", label),
+        }
+    }
+}
+impl From<serde_json::Error> for WorksheetError {
+    fn from(err: serde_json::Error) -> Self {
+        WorksheetError::InvalidJSON(err.to_string())
+    }
+}
+
+#[derive(Serialize)]
+struct WorksheetDocumentRefJSON<'a> {
+    version: u32,
+    worksheet: WorksheetRefJSON<'a>,
+}
+#[derive(Serialize)]
+struct WorksheetRefJSON<'a> {
+    expression_sequences: &'a Vec<ExpressionSequence>,
+    context: WorksheetContext,
+}
+#[derive(Deserialize)]
+struct WorksheetDocumentJSON {
+    version: u32,
+    worksheet: serde_json::Value,
+}
+
 impl fmt::Display for Action {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", self.as_str())
@@ -66,16 +162,83 @@ impl Action {
     }
     
 }
+impl WorksheetContext {
+    pub fn set_ruleset(&mut self, ruleset: RuleSet) {
+        self.rule_map = ruleset.get_rule_map();
+        self.expression_context = ruleset.context;
+        self.rule_ids = ruleset.rule_ids;
+        self.auto_rule_ids = ruleset.auto_rule_ids;
+        self.normalization = ruleset.normalization;
+        if let Some(name) = ruleset.normalization_function {
+            self.normalization_function = None;
+            self.normalization_function_name = Some(name);
+        }
+        if let Some(name) = ruleset.get_possible_actions_function {
+            self.get_possible_actions_function = None;
+            self.get_possible_actions_function_name = Some(name);
+        }
+    }
+    pub fn get_normalization_function(&self) -> Option<NormalizationFunction> {
+        if self.normalization_function.is_some() { return self.normalization_function; }
+        return self.normalization_function_name.as_ref()
+            .and_then(|name| registry::get_normalization_function(name));
+    }
+    pub fn get_possible_actions_function(&self) -> Option<GetPossibleActionsFunction> {
+        if self.get_possible_actions_function.is_some() { return self.get_possible_actions_function; }
+        return self.get_possible_actions_function_name.as_ref()
+            .and_then(|name| registry::get_possible_actions_function(name));
+    }
+    /// return `false` if `name` is not in the `registry`
+    pub fn set_normalization_function_by_name(&mut self, name: &str) -> bool {
+        if !registry::contains_normalization_function(name) { return false; }
+        self.normalization_function = None;
+        self.normalization_function_name = Some(name.to_string());
+        return true;
+    }
+    /// return `false` if `name` is not in the `registry`
+    pub fn set_get_possible_actions_function_by_name(&mut self, name: &str) -> bool {
+        if !registry::contains_get_possible_actions_function(name) { return false; }
+        self.get_possible_actions_function = None;
+        self.get_possible_actions_function_name = Some(name.to_string());
+        return true;
+    }
+    /// the same context, with the functions that are set directly replaced by their names in the `registry`
+    pub fn with_function_names(&self) -> Result<WorksheetContext, WorksheetError> {
+        let mut ctx = self.clone();
+        if let Some(f) = self.normalization_function {
+            let name = registry::find_normalization_function_name(f)
+                .ok_or(WorksheetError::UnregisteredFunction("normalization".to_string()))?;
+            ctx.normalization_function = None;
+            ctx.normalization_function_name = Some(name);
+        }
+        if let Some(f) = self.get_possible_actions_function {
+            let name = registry::find_get_possible_actions_function_name(f)
+                .ok_or(WorksheetError::UnregisteredFunction("possible actions".to_string()))?;
+            ctx.get_possible_actions_function = None;
+            ctx.get_possible_actions_function_name = Some(name);
+        }
+        return Ok(ctx);
+    }
+    pub fn get_normalization_function_name(&self) -> Option<&String> {
+        return self.normalization_function_name.as_ref();
+    }
+    pub fn get_possible_actions_function_name(&self) -> Option<&String> {
+        return self.get_possible_actions_function_name.as_ref();
+    }
+}
 impl From<WorkableExpressionSequence> for ExpressionSequence {
     fn from(seq: WorkableExpressionSequence) -> Self {
-        return ExpressionSequence { history: seq.history, };
+        return ExpressionSequence { history: seq.history, tree: seq.tree };
     }
 }
 impl ExpressionSequence {
     pub fn with_context(&self, ctx: WorksheetContext) -> WorkableExpressionSequence {
+        let tree = if self.tree.is_empty() { HistoryTree::from_lines(&self.history) } else { self.tree.clone() };
         return WorkableExpressionSequence {
             history: self.history.clone(),
-            context: ctx
+            context: ctx,
+            tree,
+            redo_stack: vec![],
         };
     }
     pub fn last_expression(&self) -> &Expression {
@@ -87,6 +250,8 @@ impl WorkableExpressionSequence {
         return WorkableExpressionSequence {
             context: ctx,
             history: vec![],
+            tree: HistoryTree::default(),
+            redo_stack: vec![],
         };
     }
     
@@ -94,17 +259,29 @@ impl WorkableExpressionSequence {
         self.context = ctx;
     }
     
+    pub fn get_context(&self) -> &WorksheetContext {
+        return &self.context;
+    }
+    
     pub fn expression(&self, index: usize) -> Option<&Expression> {
         return self.history.get(index).map(|line| &line.expr);
     }
     
+    /// `false` if the rule can't be applied, or if the auto rules don't terminate after it (see `push`)
     pub fn apply_rule_at(&mut self, rule_id: &str, addr: &Address) -> bool {
         let rule = self.context.rule_map.get(rule_id).cloned();
         if let Some(rule) = rule {
             let expr = self.last_expression();
             let rule_label = rule.label.to_string();
-            let result_expr = expr.apply_rule_at(&rule, addr);
-            return self.try_push(Action::ApplyRule(rule_label), result_expr);
+            let ctx = &self.context.expression_context;
+            match expr.apply_rule_in_context_at(&rule, addr, ctx) {
+                Ok((result_expr, assumptions)) => {
+                    let provenance = expr.get_rule_provenance_at(&rule, addr, ctx);
+                    let result = self.push_with_provenance(Action::ApplyRule(rule_label), result_expr, assumptions, provenance);
+                    return result.is_ok();
+                },
+                Err(_) => return false,
+            }
         } else {
             return false;
         }
@@ -114,36 +291,76 @@ impl WorkableExpressionSequence {
         return &self.history.last().unwrap().expr;
     }
     
-    pub fn push(&mut self, action: Action, expr: Expression) {
-        let expr = self.normalize(&expr);
-        self.history.push(ExpressionLine{action, expr, label: None, is_auto_generated: false});
-        self.try_apply_auto_rules();
-    }
-    fn push_auto(&mut self, action: Action, expr: Expression) {
-        let expr = self.normalize(&expr);
-        self.history.push(ExpressionLine{action, expr, label: None, is_auto_generated: true});
+    /// push the (normalized) expression, then apply the auto rules to it
+    /// * `NOTE`: nothing is pushed if the auto rules don't terminate, the line is dropped with the auto generated lines
+    pub fn push(&mut self, action: Action, expr: Expression) -> Result<(), WorksheetError> {
+        return self.push_with_assumptions(action, expr, vec![]);
+    }
+    /// the provenance is `Provenance::Introduce` or `Provenance::Action` (without addresses), depending on `action`
+    pub fn push_with_assumptions(&mut self, action: Action, expr: Expression, assumptions: Vec<Expression>)
+    -> Result<(), WorksheetError> {
+        let provenance = match action {
+            Action::Introduce(_) => Provenance::Introduce,
+            _ => Provenance::Action { addr_vec: vec![], action: action.as_str().to_string() },
+        };
+        return self.push_with_provenance(action, expr, assumptions, provenance);
+    }
+    pub fn push_with_provenance(
+        &mut self, action: Action, expr: Expression, assumptions: Vec<Expression>, provenance: Provenance
+    ) -> Result<(), WorksheetError> {
+        let (history_len, tree_len, current) = (self.history.len(), self.tree.len(), self.tree.current());
+        self.push_line(action, expr, assumptions, provenance, false)?;
+        if let Err(err) = self.try_apply_auto_rules() {
+            self.history.truncate(history_len);
+            self.tree.truncate(tree_len, current);
+            return Err(err);
+        }
+        self.redo_stack.clear();
+        return Ok(());
+    }
+    fn push_auto(&mut self, action: Action, expr: Expression, provenance: Provenance) -> Result<(), WorksheetError> {
+        return self.push_line(action, expr, vec![], provenance, true);
+    }
+    /// nothing is pushed if the expression can't be normalized
+    fn push_line(
+        &mut self, action: Action, expr: Expression, assumptions: Vec<Expression>, provenance: Provenance,
+        is_auto_generated: bool
+    ) -> Result<(), WorksheetError> {
+        let normalized_expr = self.normalize(&expr)?;
+        let unnormalized_expr = if normalized_expr != expr { Some(expr) } else { None };
+        let line = ExpressionLine{
+            action, expr: normalized_expr, label: None, is_auto_generated, assumptions, provenance, unnormalized_expr
+        };
+        self.tree.push(line.clone());
+        self.history.push(line);
+        return Ok(());
     }
     
-    pub fn try_push<T: Debug>(&mut self, action: Action, expr: Result<Expression,T>) -> bool {
+    pub fn try_push<T>(&mut self, action: Action, expr: Result<Expression,T>) -> bool {
         match expr {
-            Ok(expr) => {
-                self.push(action, expr);
-                return true;
-            },
-            Err(err) => {
-                dbg!(err);
-                return false;
-            }
+            Ok(expr) => return self.push(action, expr).is_ok(),
+            Err(_) => return false,
         }
     }
     
-    fn normalize(&self, expr: &Expression) -> Expression {
+    /// apply the normalization function, then the normalization equations
+    /// * `NOTE`: `WorksheetError::NonTerminatingNormalization` if the equations don't reach a fixpoint
+    pub fn normalize(&self, expr: &Expression) -> Result<Expression, WorksheetError> {
         let ctx = &self.context;
-        if let Some(f) = ctx.normalization_function {
-            return f(expr, &ctx.expression_context);
-        } else {
-            return expr.clone();
-        }
+        let f = ctx.get_normalization_function();
+        let expr = match f {
+            Some(f) => f(expr, &ctx.expression_context),
+            None => expr.clone(),
+        };
+        if ctx.normalization.is_empty() { return Ok(expr); }
+        let new_expr = expr.rewrite_to_fixpoint(&ctx.normalization)
+            .map_err(|_| WorksheetError::NonTerminatingNormalization)?;
+        if new_expr == expr { return Ok(expr); }
+        // the rewrite may leave the expression in a non-normalized shape (ex: single children AssocTrain)
+        return Ok(match f {
+            Some(f) => f(&new_expr, &ctx.expression_context),
+            None => new_expr,
+        });
     }
     
     pub fn get_possible_actions_from_labelled_equations(&self, addr_vec: &[Address]) -> Vec<(Action,Expression)> {
@@ -154,8 +371,9 @@ impl WorkableExpressionSequence {
             if let Ok(new_expr) = self.last_expression().apply_rule_expr_at(expr, addr) {
                 let rulestr = format!("Substitute from {}", label);
                 let action = Action::ApplyRule(rulestr);
-                let normalized_expr = self.normalize(&new_expr);
-                possible_actions.push((action, normalized_expr));
+                if let Ok(normalized_expr) = self.normalize(&new_expr) {
+                    possible_actions.push((action, normalized_expr));
+                }
             }
         }
         return possible_actions;
@@ -165,67 +383,197 @@ impl WorkableExpressionSequence {
         let ctx = &self.context;
         let mut possible_actions = Vec::new();
         possible_actions.extend(self.get_possible_actions_from_labelled_equations(addr_vec));
-        if let Some(f) = ctx.get_possible_actions_function {
+        if let Some(f) = ctx.get_possible_actions_function() {
             possible_actions.extend(
                 f(self.last_expression(), ctx, addr_vec).into_iter()
-                    .map(|(action, expr)| {(action, self.normalize(&expr))})
+                    .filter_map(|(action, expr)| self.normalize(&expr).ok().map(|expr| (action, expr)))
             );
         }
         return possible_actions;
     }
     
+    /// possible actions that only need a single address, for every address of the last expression,
+    /// ranked by how promising they are:
+    /// * the ones that reach the `goal` first
+    /// * then the ones whose size is closest to the `goal` (or the smallest if there is no goal)
+    pub fn suggest_next_steps(&self, goal: Option<&Expression>) -> Vec<(Action, Address, Expression)> {
+        let last_expr = self.last_expression();
+        let mut suggestions: Vec<(Action, Address, Expression)> = vec![];
+        for addr in last_expr.get_all_addresses() {
+            for (action, expr) in self.get_possible_actions(&vec![addr.clone()]) {
+                if &expr == last_expr { continue; }
+                if suggestions.iter().any(|(a, _, e)| a == &action && e == &expr) { continue; }
+                suggestions.push((action, addr.clone(), expr));
+            }
+        }
+        let score = |expr: &Expression| -> (bool, usize) {
+            match goal {
+                Some(goal) => (!expr.is_equivalent_to(goal), expr.size().abs_diff(goal.size())),
+                None => (false, expr.size()),
+            }
+        };
+        suggestions.sort_by_key(|(_, _, expr)| score(expr));
+        return suggestions;
+    }
+    
     pub fn try_apply_action_by_index(&mut self, addr_vec: &Vec<Address>, index: usize) -> bool {
         if let Some((action, expr)) = self.get_possible_actions(addr_vec).get(index) {
-            self.push(action.clone(), expr.clone());
-            return true;
+            let (provenance, assumptions) = self.find_rule_provenance(action, addr_vec, expr);
+            return self.push_with_provenance(action.clone(), expr.clone(), assumptions, provenance).is_ok();
         } else {
             return false;
         }
     }
     
+    /// the rule behind a possible action (see `get_possible_actions::from_rule_map`):
+    /// a rule with the label of the action, that gives the same expression, with the assumptions of the rule
+    /// * `NOTE`: `Provenance::Action` with `addr_vec` and the name of the action, and no assumptions, if there is no such rule
+    pub(crate) fn find_rule_provenance(&self, action: &Action, addr_vec: &[Address], expr: &Expression)
+    -> (Provenance, Vec<Expression>) {
+        let (ctx, last_expr) = (&self.context, self.last_expression());
+        let action_provenance = Provenance::Action { addr_vec: addr_vec.to_vec(), action: action.as_str().to_string() };
+        if !matches!(action, Action::ApplyRule(_)) || addr_vec.is_empty() { return (action_provenance, vec![]); }
+        let addr = Address::common_virtual_ancestor_from_vec(addr_vec, last_expr);
+        for rule in ctx.rule_ids.iter().filter_map(|id| ctx.rule_map.get(id)) {
+            if rule.label != action.as_str() { continue; }
+            if let Ok((new_expr, assumptions)) = last_expr.apply_rule_in_context_at(rule, &addr, &ctx.expression_context) {
+                if self.normalize(&new_expr).as_ref() == Ok(expr) {
+                    return (last_expr.get_rule_provenance_at(rule, &addr, &ctx.expression_context), assumptions);
+                }
+            }
+        }
+        return (action_provenance, vec![]);
+    }
+    
     pub fn label_expression(&mut self, label: String, index: usize) {
+        let label = if label.is_empty() { None } else { Some(label) };
         if let Some(line) = self.history.get_mut(index) {
-            if label.is_empty() {
-                line.label = None;
-            } else {
-                line.label = Some(label);
-            }
+            line.label.clone_from(&label);
+        }
+        if let Some(id) = self.current_path().get(index) {
+            if let Some(line) = self.tree.get_line_mut(*id) { line.label = label; }
         }
     }
     
+    /// go back to the line at `index`, the next line pushed starts a new branch from it
+    /// * `NOTE`: the lines after `index` are kept in another branch, see `branches`
     pub fn reset_to(&mut self, index: usize) {
-        if index < self.history.len() {
-            self.history = self.history.iter().take(index+1).cloned().collect();
+        if let Some(id) = self.current_path().get(index) {
+            self.tree.set_current(*id);
+            self.history.truncate(index+1);
+            self.redo_stack.clear();
         }
     }
     
-    pub fn try_apply_auto_rules(&mut self) {
-        if self.context.auto_rule_ids.is_empty() { return; }
+    /// the ids (in the history tree) of the lines of the current branch
+    fn current_path(&self) -> Vec<usize> {
+        return self.tree.current().map(|id| self.tree.path_to(id)).unwrap_or_default();
+    }
+    
+    /// go back to before the last line that is not auto generated,
+    /// `false` if there is nothing to undo (the first line can't be undone)
+    pub fn undo(&mut self) -> bool {
+        let index = match self.history.iter().rposition(|line| !line.is_auto_generated) {
+            Some(index) if index > 0 => index,
+            _ => return false,
+        };
+        self.redo_stack.extend(self.tree.current());
+        self.tree.set_current(self.current_path()[index-1]);
+        self.history.truncate(index);
+        return true;
+    }
+    
+    /// go forward to where the current branch was before the last `undo`, `false` if there is nothing to redo
+    pub fn redo(&mut self) -> bool {
+        let id = match self.redo_stack.pop() {
+            Some(id) => id,
+            None => return false,
+        };
+        self.tree.set_current(id);
+        self.history = self.tree.lines_to(id);
+        return true;
+    }
+    
+    pub fn history_tree(&self) -> &HistoryTree {
+        return &self.tree;
+    }
+    
+    /// the ids of the last lines of every branch, see `HistoryTree::branches`
+    pub fn branches(&self) -> Vec<usize> {
+        return self.tree.branches();
+    }
+    
+    /// the id of the last line of the current branch
+    pub fn current_branch(&self) -> Option<usize> {
+        return self.tree.current();
+    }
+    
+    /// make the branch that ends at the line `id` the current branch (`id` doesn't need to be the last line),
+    /// `false` if there is no line `id`
+    pub fn switch_branch(&mut self, id: usize) -> bool {
+        if !self.tree.set_current(id) { return false; }
+        self.history = self.tree.lines_to(id);
+        self.redo_stack.clear();
+        return true;
+    }
+    
+    pub fn compare_branches(&self, first: usize, second: usize) -> Option<BranchComparison> {
+        return self.tree.compare_branches(first, second);
+    }
+    
+    /// apply the auto rules to the last expression until none of them can be applied
+    /// * `NOTE`: the auto rules don't terminate if an expression repeats, or after `LIMIT_OF_AUTO_GENERATED_STEPS` steps,
+    ///   then the lines generated by this call are removed
+    pub fn try_apply_auto_rules(&mut self) -> Result<(), WorksheetError> {
+        if self.context.auto_rule_ids.is_empty() { return Ok(()); }
         let rule_map = &self.context.rule_map;
         let auto_rules = self.context.auto_rule_ids.iter()
             .filter_map(|id| rule_map.get(id)).cloned().collect::<Vec<Rule>>();
+        let (history_len, tree_len, current) = (self.history.len(), self.tree.len(), self.tree.current());
+        let mut seen_exprs = vec![self.last_expression().clone()];
+        let mut applied_rule_ids = vec![];
         for _ in 0..LIMIT_OF_AUTO_GENERATED_STEPS {
-            let changed = self.f_try_apply_auto_rules(&auto_rules);
-            if !changed { break; }
+            match self.f_try_apply_auto_rules(&auto_rules) {
+                Ok(Some(rule_id)) => applied_rule_ids.push(rule_id),
+                Ok(None) => return Ok(()),
+                Err(err) => {
+                    self.history.truncate(history_len);
+                    self.tree.truncate(tree_len, current);
+                    return Err(err);
+                },
+            }
+            let expr = self.last_expression();
+            // `applied_rule_ids[i]` produced `seen_exprs[i+1]`, so the loop starts at `i`
+            if let Some(i) = seen_exprs.iter().position(|e| e == expr) {
+                self.history.truncate(history_len);
+                self.tree.truncate(tree_len, current);
+                return Err(WorksheetError::NonTerminatingAutoRules(applied_rule_ids.split_off(i)));
+            }
+            seen_exprs.push(expr.clone());
         }
+        self.history.truncate(history_len);
+        self.tree.truncate(tree_len, current);
+        return Err(WorksheetError::NonTerminatingAutoRules(applied_rule_ids));
     }
     
-    /// return `true` if the expression is changed
-    fn f_try_apply_auto_rules(&mut self, rules: &[Rule]) -> bool {
+    /// return the id of the applied rule, if the expression is changed
+    /// * `NOTE`: rules are only applied automatically if all of their conditions are satisfied
+    fn f_try_apply_auto_rules(&mut self, rules: &[Rule]) -> Result<Option<String>, WorksheetError> {
         let expr = self.last_expression();
         for rule in rules {
-            let rule_expr = &rule.expression;
-            let possible_eq_addr = expr.get_possible_equation_application_addresses(rule_expr);
-            if possible_eq_addr.is_empty() { continue; }
-            let addr = possible_eq_addr.first()
-                .expect("Expression::get_possible_equation_application_address is valid");
-            if let Ok(new_expr) = expr.apply_equation_at(rule_expr, addr) {
-                let action = Action::ApplyRule(rule.label.clone());
-                self.push_auto(action, new_expr);
-                return true;
+            let ctx = &self.context.expression_context;
+            let possible_eq_addr = expr.get_possible_rule_application_addresses(rule, ctx);
+            for addr in &possible_eq_addr {
+                if let Ok((new_expr, assumptions)) = expr.apply_rule_in_context_at(rule, addr, ctx) {
+                    if !assumptions.is_empty() { continue; }
+                    let action = Action::ApplyRule(rule.label.clone());
+                    let provenance = expr.get_rule_provenance_at(rule, addr, ctx);
+                    self.push_auto(action, new_expr, provenance)?;
+                    return Ok(Some(rule.id.clone()));
+                }
             }
         }
-        return false;
+        return Ok(None);
     }
 }
 
@@ -253,18 +601,26 @@ impl Worksheet {
     
     pub fn set_normalization_function(&mut self, f: NormalizationFunction) {
         self.context.normalization_function = Some(f);
+        self.context.normalization_function_name = None;
     }
     
     pub fn set_get_possible_actions_function(&mut self, f: GetPossibleActionsFunction) {
         self.context.get_possible_actions_function = Some(f);
+        self.context.get_possible_actions_function_name = None;
+    }
+    
+    /// return `false` if `name` is not in the `registry`
+    pub fn set_normalization_function_by_name(&mut self, name: &str) -> bool {
+        return self.context.set_normalization_function_by_name(name);
+    }
+    
+    /// return `false` if `name` is not in the `registry`
+    pub fn set_get_possible_actions_function_by_name(&mut self, name: &str) -> bool {
+        return self.context.set_get_possible_actions_function_by_name(name);
     }
     
     pub fn set_ruleset(&mut self, ruleset: RuleSet) {
-        let rule_map = ruleset.get_rule_map();
-        self.set_expression_context(ruleset.context);
-        self.set_rule_ids(ruleset.rule_ids);
-        self.set_auto_rule_ids(ruleset.auto_rule_ids);
-        self.set_rule_map(rule_map);
+        self.context.set_ruleset(ruleset);
     }
     pub fn reset_rule_map(&mut self) { 
         self.context.rule_map.clear();
@@ -278,6 +634,9 @@ impl Worksheet {
     pub fn set_auto_rule_ids(&mut self, rule_ids: Vec<String>) { 
         self.context.auto_rule_ids = rule_ids;
     }
+    pub fn set_normalization(&mut self, normalization: Vec<Expression>) {
+        self.context.normalization = normalization;
+    }
     pub fn extend_rule_map(&mut self, rule_map: HashMap<String, Rule>) { 
         self.context.rule_map.extend(rule_map);
     }
@@ -285,26 +644,27 @@ impl Worksheet {
         self.context.rule_ids.extend(rule_ids);
     }
     
-    pub fn introduce_expression(&mut self, expr: Expression) {
+    /// * `NOTE`: no sequence is added if the expression can't be pushed
+    pub fn introduce_expression(&mut self, expr: Expression) -> Result<(), WorksheetError> {
         let mut sequence = WorkableExpressionSequence::new(self.context.clone());
-        sequence.push(Action::Introduce("Introduce".to_string()), expr);
+        sequence.push(Action::Introduce("Introduce".to_string()), expr)?;
         self.expression_sequences.push(sequence.into());
+        return Ok(());
     }
     
-    pub fn introduce_from_label(&mut self, label: &str) -> bool {
-        if let Some((_, expr)) = self.context.labelled_expression.iter().find(|(l, _)| l == label) {
-            let mut sequence = WorkableExpressionSequence::new(self.context.clone());
-            let action_str = format!("Introduce from {}", label);
-            sequence.push(Action::Introduce(action_str), expr.clone());
-            self.expression_sequences.push(sequence.into());
-            return true
-        } else {
-            return false;
-        }
+    pub fn introduce_from_label(&mut self, label: &str) -> Result<(), WorksheetError> {
+        let (_, expr) = self.context.labelled_expression.iter().find(|(l, _)| l == label)
+            .ok_or(WorksheetError::UnknownLabel(label.to_string()))?;
+        let mut sequence = WorkableExpressionSequence::new(self.context.clone());
+        let action_str = format!("Introduce from {}", label);
+        sequence.push(Action::Introduce(action_str), expr.clone())?;
+        self.expression_sequences.push(sequence.into());
+        return Ok(());
     }
     
+    /// the labels of every branch are kept
     fn check_and_update_labelled_expr(&mut self, seq: &WorkableExpressionSequence) {
-        for line in &seq.history {
+        for line in seq.tree.lines() {
             if let Some(label) = &line.label {
                 if self.context.labelled_expression.iter().any(|(l, e)| l == label && e == &line.expr) {
                     continue;
@@ -340,4 +700,24 @@ impl Worksheet {
         return self.expression_sequences.is_empty();
     }
     
+    /// serialize the worksheet into a versioned JSON document
+    /// * `NOTE`: the functions are stored by name, `WorksheetError::UnregisteredFunction` if one that is set directly is not in the `registry`
+    pub fn to_json(&self) -> Result<String, WorksheetError> {
+        let worksheet = WorksheetRefJSON {
+            expression_sequences: &self.expression_sequences,
+            context: self.context.with_function_names()?,
+        };
+        let document = WorksheetDocumentRefJSON { version: WORKSHEET_FORMAT_VERSION, worksheet };
+        return Ok(serde_json::to_string(&document)?);
+    }
+    
+    /// load a worksheet from a JSON document produced by `Worksheet::to_json`
+    /// * `NOTE`: the functions are restored from the `registry`
+    pub fn from_json(json: &str) -> Result<Worksheet, WorksheetError> {
+        let document: WorksheetDocumentJSON = serde_json::from_str(json)?;
+        if document.version != WORKSHEET_FORMAT_VERSION {
+            return Err(WorksheetError::UnsupportedVersion(document.version));
+        }
+        return Ok(serde_json::from_value(document.worksheet)?);
+    }
 }
@@ -1,3 +1,5 @@
+#![allow(clippy::needless_return)]
+
 use equaio::{address, rule};
 use equaio::expression::{Address, expression_builder as eb};
 use equaio::arithmetic;
@@ -116,6 +118,44 @@ mod fraction {
         // assert_eq!(new_expr, target_expr);
     }
     
+    #[test]
+    fn large_integer_division() {
+        let ctx = arithmetic::get_arithmetic_ctx().add_params(vec_strings!["x"]);
+        let expr = parser_prefix::to_expression("/(*(20000000000000000000000,x),*(30000000000000000000000,x))", &ctx).unwrap();
+        let new_expr = expr.apply_fraction_arithmetic(0, 0).unwrap();
+        let target_expr = parser_prefix::to_expression("/(*(2,x),*(3,x))", &ctx).unwrap();
+        assert_eq!(new_expr.to_string(true), target_expr.to_string(true));
+    }
+    
+}
+
+#[cfg(test)]
+mod exponent {
+    use super::*;
+    
+    #[test]
+    fn take_log_of_both_side() {
+        let ctx = arithmetic::get_arithmetic_ctx().add_params(vec_strings!["x"]);
+        let expr = parser::to_expression("2 ^ x = 8", &ctx).unwrap();
+        let actions = algebra::get_possible_actions::apply_operation_both_side(&expr, &[address![], address![0,0]]);
+        assert_eq!(actions.len(), 1);
+        assert_eq!(actions[0].0.to_string(), "Take log base 2 of both side");
+        assert_eq!(actions[0].1, parser::to_expression("log(2, 2 ^ x) = log(2, 8)", &ctx).unwrap());
+        let expr = actions[0].1.apply_simple_arithmetic_equation_at(&address![1]).unwrap();
+        assert_eq!(expr.to_string(true), "(log(2, (2 ^ x)) = 3)");
+    }
+    
+    #[test]
+    fn raise_both_side_to_reciprocal() {
+        let ctx = arithmetic::get_arithmetic_ctx().add_params(vec_strings!["x"]);
+        let expr = parser::to_expression("x ^ 3 = 27", &ctx).unwrap();
+        let actions = algebra::get_possible_actions::apply_operation_both_side(&expr, &[address![], address![0,1]]);
+        assert_eq!(actions.len(), 1);
+        assert_eq!(actions[0].0.to_string(), "Raise both side to 1/3");
+        assert_eq!(actions[0].1, parser::to_expression("(x ^ 3) ^ (1 / 3) = 27 ^ (1 / 3)", &ctx).unwrap());
+        let expr = actions[0].1.apply_simple_arithmetic_equation_at(&address![1]).unwrap();
+        assert_eq!(expr.to_string(true), "(((x ^ 3) ^ (1 / 3)) = 3)");
+    }
 }
 
 #[cfg(test)]
@@ -1,5 +1,8 @@
-use equaio::parser::parser_prefix;
+#![allow(clippy::needless_return, clippy::match_like_matches_macro)]
+
+use equaio::parser::{parser, parser_prefix};
 use equaio::arithmetic;
+use equaio::number::Number;
 use equaio::expression::Address;
 use equaio::address;
 
@@ -12,7 +15,7 @@ mod calculation {
         let ctx = arithmetic::get_arithmetic_ctx();
         let expr = parser_prefix::to_expression("+(+(1,0),+(2,3))", &ctx).unwrap();
         let value = expr.calculate_numeric();
-        assert_eq!(value.unwrap(), 6.0);
+        assert_eq!(value.unwrap(), Number::integer(6));
     }
     
     #[test]
@@ -20,7 +23,7 @@ mod calculation {
         let ctx = arithmetic::get_arithmetic_ctx();
         let expr = parser_prefix::to_expression("+(+(1,0),+(-2,3))", &ctx).unwrap();
         let value = expr.calculate_numeric();
-        assert_eq!(value.unwrap(), 2.0);
+        assert_eq!(value.unwrap(), Number::integer(2));
     }
     
     #[test]
@@ -28,7 +31,7 @@ mod calculation {
         let ctx = arithmetic::get_arithmetic_ctx();
         let expr = parser_prefix::to_expression("+(+(1,0),+(-(2),3))", &ctx).unwrap();
         let value = expr.calculate_numeric();
-        assert_eq!(value.unwrap(), 2.0);
+        assert_eq!(value.unwrap(), Number::integer(2));
     }
 
     #[test]
@@ -40,7 +43,101 @@ mod calculation {
           _ => false,
         });
         let value = expr.calculate_numeric();
-        assert_eq!(value.unwrap(), 10.0);
+        assert_eq!(value.unwrap(), Number::integer(10));
+    }
+
+    #[test]
+    fn exact_fraction() {
+        let ctx = arithmetic::get_arithmetic_ctx();
+        let expr = parser_prefix::to_expression("+(/(1,3),/(1,6))", &ctx).unwrap();
+        let value = expr.calculate_numeric().unwrap();
+        assert_eq!(value.to_string(), "1/2");
+        assert_eq!(value.to_expression(), parser_prefix::to_expression("/(1,2)", &ctx).unwrap());
+        let expr = parser_prefix::to_expression("/(4,-6)", &ctx).unwrap();
+        assert_eq!(expr.calculate_numeric().unwrap().to_string(), "-2/3");
+        let expr = parser_prefix::to_expression("/(1,0)", &ctx).unwrap();
+        assert_eq!(expr.calculate_numeric(), None);
+    }
+
+    #[test]
+    fn large_integer() {
+        let ctx = arithmetic::get_arithmetic_ctx();
+        let expr = parser_prefix::to_expression("*(99999999999999999999,99999999999999999999)", &ctx).unwrap();
+        let value = expr.calculate_numeric().unwrap();
+        assert!(value.is_integer());
+        assert_eq!(value.to_string(), "9999999999999999999800000000000000000001");
+        assert!(value.to_expression().is_integer());
+    }
+
+    #[test]
+    fn large_fraction_sign() {
+        let ctx = arithmetic::get_arithmetic_ctx();
+        // 1 / 10^400
+        let expr = parser_prefix::to_expression("/(1,^(10,400))", &ctx).unwrap();
+        let value = expr.calculate_numeric().unwrap();
+        assert!(!value.is_zero());
+        assert!(!value.is_negative());
+        // -10^400 / (10^400 + 1)
+        let expr = parser_prefix::to_expression("/(-(0,^(10,400)),+(^(10,400),1))", &ctx).unwrap();
+        let value = expr.calculate_numeric().unwrap();
+        assert!(value.is_negative());
+        assert!(!value.is_one());
+        assert!(!value.is_zero());
+    }
+
+    #[test]
+    fn power_and_roots() {
+        let ctx = arithmetic::get_arithmetic_ctx();
+        let calculate = |s: &str| parser_prefix::to_expression(s, &ctx).unwrap().calculate_numeric().map(|n| n.to_string());
+        assert_eq!(calculate("^(2,10)"), Some("1024".to_string()));
+        assert_eq!(calculate("^(2,-2)"), Some("1/4".to_string()));
+        assert_eq!(calculate("^(4,/(3,2))"), Some("8".to_string()));
+        assert_eq!(calculate("sqrt(/(9,4))"), Some("3/2".to_string()));
+        assert_eq!(calculate("root(3,-8)"), Some("-2".to_string()));
+        assert_eq!(calculate("sqrt(-4)"), None);
+        assert_eq!(calculate("^(0,-1)"), None);
+        assert!(matches!(parser_prefix::to_expression("sqrt(2)", &ctx).unwrap().calculate_numeric(), Some(Number::Decimal(_))));
+    }
+
+    #[test]
+    fn logarithm_and_reciprocal() {
+        let ctx = arithmetic::get_arithmetic_ctx();
+        let calculate = |s: &str| parser_prefix::to_expression(s, &ctx).unwrap().calculate_numeric().map(|n| n.to_string());
+        assert_eq!(calculate("log(2,1024)"), Some("10".to_string()));
+        assert_eq!(calculate("log(10,/(1,100))"), Some("-2".to_string()));
+        assert_eq!(calculate("log(4,2)"), Some("1/2".to_string()));
+        assert_eq!(calculate("log(/(1,9),27)"), Some("-3/2".to_string()));
+        assert!(matches!(parser_prefix::to_expression("log(2,3)", &ctx).unwrap().calculate_numeric(), Some(Number::Decimal(_))));
+        assert_eq!(calculate("ln(1)"), Some("0".to_string()));
+        assert_eq!(calculate("log(1,5)"), None);
+        assert_eq!(calculate("ln(0)"), None);
+        assert_eq!(calculate("/(4)"), Some("1/4".to_string()));
+        let expr = parser_prefix::to_expression("/(4)", &ctx).unwrap();
+        assert!(matches!(expr.identify_arithmetic_operator(), Some(arithmetic::ArithmeticOperator::Reciprocal)));
+    }
+
+    #[test]
+    fn inverse() {
+        use arithmetic::ArithmeticOperator::*;
+        assert!(Pow.inverse() == Some(Root));
+        assert!(Reciprocal.inverse() == Some(Reciprocal));
+        assert!(Sqrt.inverse().is_none());
+        assert!(Log.inverse().is_none());
+        assert!(Ln.inverse().is_none());
+    }
+
+    #[test]
+    fn decimal() {
+        let ctx = arithmetic::get_arithmetic_ctx();
+        let calculate = |s: &str| parser_prefix::to_expression(s, &ctx).unwrap().calculate_numeric().map(|n| n.to_string());
+        // decimal literals are exact
+        assert_eq!(calculate("+(0.1,0.2)"), Some("3/10".to_string()));
+        assert_eq!(calculate("+(0.5,/(1,4))"), Some("3/4".to_string()));
+        assert_eq!(calculate("*(2.5e-3,4)"), Some("1/100".to_string()));
+        assert_eq!(calculate("*(1E3,1)"), Some("1000".to_string()));
+        // an inexact operand gives a decimal
+        let expr = parser_prefix::to_expression("+(sqrt(2),0.5)", &ctx).unwrap();
+        assert!(matches!(expr.calculate_numeric(), Some(Number::Decimal(_))));
     }
     
 }
@@ -72,6 +169,29 @@ mod generate_equation {
         let eq = expr.generate_simple_artithmetic_equation_at(&address![0].sub(2)).unwrap();
         assert_eq!(eq.to_string(true), "((3 + 4) = 7)");
     }
+
+    #[test]
+    fn fraction_result() {
+        let ctx = arithmetic::get_arithmetic_ctx();
+        let expr = parser_prefix::to_expression("+(/(1,3),/(1,6))", &ctx).unwrap();
+        let eq = expr.generate_simple_arithmetic_equation().unwrap();
+        assert_eq!(eq.to_string(true), "(((1 / 3) + (1 / 6)) = (1 / 2))");
+        let expr = parser_prefix::to_expression("/(1,3)", &ctx).unwrap();
+        assert!(matches!(expr.generate_simple_arithmetic_equation(), Err(arithmetic::ArithmeticError::NothingToCalculate)));
+    }
+
+    #[test]
+    fn power_result() {
+        let ctx = arithmetic::get_arithmetic_ctx();
+        let expr = parser::to_expression("2 ^ 3 ^ 2", &ctx).unwrap();
+        let eq = expr.generate_simple_artithmetic_equation_at(&address![1]).unwrap();
+        assert_eq!(eq.to_string(true), "((3 ^ 2) = 9)");
+        let expr = parser::to_expression("sqrt(16)", &ctx).unwrap();
+        assert_eq!(expr.generate_simple_arithmetic_equation().unwrap().to_string(true), "(sqrt(16) = 4)");
+        // irrational results are not written down as decimals
+        let expr = parser::to_expression("sqrt(2)", &ctx).unwrap();
+        assert!(matches!(expr.generate_simple_arithmetic_equation(), Err(arithmetic::ArithmeticError::InexactResult)));
+    }
 }
 
 #[cfg(test)]
@@ -1,3 +1,5 @@
+#![allow(clippy::needless_return, clippy::items_after_test_module, clippy::len_zero, clippy::needless_borrow)]
+
 use equaio::expression as exp;
 use equaio::expression::Address;
 use equaio::{vec_strings, vec_index_map};
@@ -224,6 +226,53 @@ mod simple_block {
         assert_eq!(block, expected_block);
     }
     
+    #[test]
+    fn superscript() {
+        let ctx = get_arithmetic_ctx().add_params(vec_strings!["a", "b"]);
+        let block_ctx = BlockContext {
+            superscript_ops: vec_strings!["^"],
+            op_precedence: ctx.op_precedence.clone(),
+            ..Default::default()
+        };
+        let expr = parser::to_expression("(a + b) ^ (a + 1)", &ctx).unwrap();
+        let block = Block::from_root_expression(&expr, &block_ctx);
+        let expected_block = bb::superscript_container(vec![
+            bb::horizontal_container(vec![
+                bb::symbol("a".to_string(), address![0,0]),
+                bb::symbol("+".to_string(), address![0]),
+                bb::symbol("b".to_string(), address![0,1]),
+            ], address![0]).add_tag(BlockTag::Parentheses),
+            bb::horizontal_container(vec![
+                bb::symbol("a".to_string(), address![1,0]),
+                bb::symbol("+".to_string(), address![1]),
+                bb::symbol("1".to_string(), address![1,1]),
+            ], address![1]),
+        ], address![]);
+        assert_eq!(block, expected_block);
+    }
+    
+    #[test]
+    fn radical() {
+        let ctx = get_arithmetic_ctx().add_params(vec_strings!["a"]);
+        let block_ctx = BlockContext {
+            radical_ops: vec_strings!["sqrt", "root"],
+            ..Default::default()
+        };
+        let expr = parser::to_expression("sqrt(a) + root(3, a)", &ctx).unwrap();
+        let block = Block::from_root_expression(&expr, &block_ctx);
+        let expected_block = bb::horizontal_container(vec![
+            bb::radical_container(vec![
+                bb::symbol("a".to_string(), address![0,0]),
+            ], address![0]),
+            bb::symbol("+".to_string(), address![]),
+            bb::radical_container(vec![
+                bb::symbol("3".to_string(), address![1,0]),
+                bb::symbol("a".to_string(), address![1,1]),
+            ], address![1]),
+        ], address![]);
+        assert_eq!(block, expected_block);
+    }
+    
     #[test]
     fn algebra_with_number(){
         let ctx = get_arithmetic_ctx().add_param("x".to_string());
@@ -339,7 +388,7 @@ mod simple_block {
         let mut ws = init_algebra_worksheet(vec_strings!["x"]);
         let expr = parser_prefix::to_expression("=(-(*(2,x),1),3)", &ws.get_expression_context()).unwrap();
         assert_eq!(expr.clone().to_string(true), "(((2 * x) - 1) = 3)");
-        ws.introduce_expression(expr.clone());
+        ws.introduce_expression(expr.clone()).unwrap();
         let seq0 = ws.get_workable_expression_sequence(0).unwrap();
         let expr0 = seq0.last_expression();
         assert_eq!(expr0.to_string(true), "(((2 * x) - 1) = 3)");
@@ -0,0 +1,50 @@
+#![allow(clippy::needless_return)]
+
+use equaio::rule;
+
+#[cfg(test)]
+mod confluence_test {
+    use super::*;
+
+    #[test]
+    fn confluent() {
+        let str = std::fs::read_to_string("rules/algebra_simplify.json").unwrap();
+        let ruleset = rule::parse_ruleset_from_json_with_loader(&str, &rule::FileRulesetLoader::new("rules")).unwrap();
+        assert_eq!(ruleset.find_critical_pairs(), vec![]);
+
+        let str = r#"
+        {
+            "name": "simple",
+            "context": { "base": "arithmetic" },
+            "rules": [
+                { "id": "sub_self", "expr": "X - X = 0", "auto": true },
+                { "id": "sub_zero", "expr": "X - 0 = X", "auto": true }
+            ]
+        }
+        "#;
+        // `0 - 0` is rewritten to `0` by both rules
+        assert_eq!(rule::parse_ruleset_from_json(str).unwrap().find_critical_pairs(), vec![]);
+    }
+
+    #[test]
+    fn critical_pairs() {
+        let str = r#"
+        {
+            "name": "simple",
+            "context": { "base": "arithmetic" },
+            "rules": [
+                { "id": "mul_zero", "expr": "X * 0 = 0", "auto": true },
+                { "id": "mul_self", "expr": "X * X = X ^ 2", "auto": true },
+                { "id": "not_auto", "expr": "X ^ 2 = X * X" }
+            ]
+        }
+        "#;
+        let critical_pairs = rule::parse_ruleset_from_json(str).unwrap().find_critical_pairs();
+        assert_eq!(critical_pairs.len(), 1);
+        let critical_pair = &critical_pairs[0];
+        assert_eq!(critical_pair.rule_ids, ("simple/mul_zero".to_string(), "simple/mul_self".to_string()));
+        assert_eq!(critical_pair.overlap.to_string(true), "(0 * 0)");
+        assert_eq!(critical_pair.results.0.to_string(true), "0");
+        assert_eq!(critical_pair.results.1.to_string(true), "(0 ^ 2)");
+    }
+}
@@ -0,0 +1,75 @@
+#![allow(clippy::needless_return)]
+
+use equaio::arithmetic::get_arithmetic_ctx;
+use equaio::expression::{Context, Expression};
+use equaio::parser::parser;
+use equaio::vec_strings;
+
+fn ctx() -> Context {
+    return get_arithmetic_ctx().add_params(vec_strings!["x", "y", "z"]);
+}
+
+fn parse(s: &str) -> Expression {
+    return parser::to_expression(s, &ctx()).unwrap();
+}
+
+fn is_equal(a: &str, b: &str) -> bool {
+    return parse(a).is_mathematically_equal(&parse(b), &ctx());
+}
+
+#[cfg(test)]
+mod equality {
+    use super::*;
+
+    #[test]
+    fn canonicalize() {
+        let ctx = ctx();
+        let a = parse("y + (x + z)").canonicalize(&ctx);
+        let b = parse("(z + y) + x").canonicalize(&ctx);
+        assert_eq!(a, b);
+        assert_eq!(parse("2 * x * 3").canonicalize(&ctx), parse("6 * x").canonicalize(&ctx));
+        assert_eq!(parse("1 + 2 / 4").canonicalize(&ctx).to_string(true), "(3 / 2)");
+    }
+
+    #[test]
+    fn reordering() {
+        assert!(is_equal("x + 2 * y", "y * 2 + x"));
+        assert!(is_equal("x * (y * z)", "(z * x) * y"));
+        assert!(is_equal("x - y", "-y + x"));
+        assert!(is_equal("2 + 3", "5"));
+    }
+
+    #[test]
+    fn polynomial() {
+        assert!(is_equal("(x + 1) ^ 2", "x ^ 2 + 2 * x + 1"));
+        assert!(is_equal("(x - y) * (x + y)", "x * x - y * y"));
+        assert!(!is_equal("(x + 1) ^ 2", "x ^ 2 + 1"));
+    }
+
+    #[test]
+    fn numeric() {
+        assert!(is_equal("sqrt(4 * x * x)", "2 * sqrt(x * x)"));
+        assert!(is_equal("ln(x * x * y * y)", "ln(x * x) + ln(y * y)"));
+        assert!(is_equal("x / y", "x * (/ y)"));
+        assert!(!is_equal("sqrt(x * x)", "x"));
+        assert!(!is_equal("ln(x * x)", "ln(x)"));
+    }
+
+    #[test]
+    fn non_commutative() {
+        let mut ctx = ctx();
+        ctx.binary_ops.push("@".to_string());
+        ctx.assoc_ops.push("@".to_string());
+        let parse = |s: &str| parser::to_expression(s, &ctx).unwrap();
+        assert!(parse("x @ (y @ z)").is_mathematically_equal(&parse("(x @ y) @ z"), &ctx));
+        assert!(!parse("x @ y").canonicalize(&ctx).is_equivalent_to(&parse("y @ x").canonicalize(&ctx)));
+    }
+    
+    #[test]
+    fn statement() {
+        assert!(is_equal("x + 1 = y", "y = 1 + x"));
+        assert!(is_equal("2 * x = 4", "x * 2 = 2 + 2"));
+        assert!(!is_equal("x = y + 1", "x = y"));
+        assert!(!is_equal("x = y", "x + y"));
+    }
+}
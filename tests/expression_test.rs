@@ -1,3 +1,5 @@
+#![allow(clippy::needless_return, clippy::println_empty_string)]
+
 use equaio::expression as exp;
 use equaio::expression::Address;
 use equaio::vec_strings;
@@ -62,6 +64,26 @@ mod address {
         let addr_common = Address::common_virtual_ancestor(&addr0, &addr1, &expr);
         assert_eq!(addr_common, address![].sub(0));
     }
+    
+    #[test]
+    fn get_all_addresses() {
+        let ctx = exp::Context {
+            parameters: vec_strings!["a", "b", "c", "d"],
+            binary_ops: vec_strings!["+", "*"],
+            assoc_ops: vec_strings!["+", "*"],
+            ..Default::default()
+        };
+        let expr = parser::to_expression("a + (b * c) + d", &ctx).unwrap();
+        let addresses = expr.get_all_addresses();
+        let target = vec![
+            address![], address![].sub(0), address![].sub(1),
+            address![0],
+            address![1], address![1,0], address![1,1],
+            address![2],
+        ];
+        assert_eq!(addresses, target);
+        assert_eq!(expr.size(), 6);
+    }
 }
 
 #[cfg(test)]
@@ -629,6 +651,37 @@ mod variadic {
         assert_eq!(map.get("A_3").unwrap().to_string(true), "z");
     }
     
+    #[test]
+    fn pattern_match_ac() {
+        let ctx = exp::Context {
+            parameters: vec_strings!["a", "b", "c", "0"],
+            binary_ops: vec_strings!["+", "*"],
+            assoc_ops: vec_strings!["+", "*"],
+            ..Default::default()
+        };
+        let options = exp::MatchOptions { ac_ops: vec_strings!["+", "*"], ..Default::default() };
+        let expr = parser_prefix::to_expression("+(a,0,b)", &ctx).unwrap();
+        let pattern = parser_prefix::to_expression("+(X,0)", &ctx).unwrap();
+        assert!(expr.pattern_match_this_node(&pattern).is_none());
+        let map = expr.pattern_match_this_node_with(&pattern, &options).unwrap();
+        assert_eq!(map.get("X").unwrap().to_string(true), "(a + b)");
+        
+        let expr = parser_prefix::to_expression("+(*(c,a),b)", &ctx).unwrap();
+        let pattern = parser_prefix::to_expression("+(Y,*(a,X))", &ctx).unwrap();
+        let map = expr.pattern_match_this_node_with(&pattern, &options).unwrap();
+        assert_eq!(map.get("X").unwrap().to_string(true), "c");
+        assert_eq!(map.get("Y").unwrap().to_string(true), "b");
+        
+        // the variables must still be consistent
+        let expr = parser_prefix::to_expression("+(a,b,c)", &ctx).unwrap();
+        let pattern = parser_prefix::to_expression("+(X,X)", &ctx).unwrap();
+        assert!(expr.pattern_match_this_node_with(&pattern, &options).is_none());
+        // `-` is not commutative
+        let expr = parser_prefix::to_expression("-(0,a)", &ctx).unwrap();
+        let pattern = parser_prefix::to_expression("-(X,0)", &ctx).unwrap();
+        assert!(expr.pattern_match_this_node_with(&pattern, &options).is_none());
+    }
+    
     #[test]
     fn apply_match_map() {
         let ctx = exp::Context {
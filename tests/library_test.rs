@@ -0,0 +1,60 @@
+#![allow(clippy::needless_return)]
+
+use equaio::library::{LibraryError, RuleLibrary};
+use std::collections::HashMap;
+
+#[cfg(test)]
+mod library_test {
+    use super::*;
+
+    #[test]
+    fn load_dir() {
+        let (library, errors) = RuleLibrary::load_dir("rules");
+        assert!(errors.is_empty());
+        assert_eq!(library.names(), vec!["algebra", "algebra_simplify", "logic"]);
+
+        let logic = library.get_ruleset("logic").unwrap();
+        assert_eq!(logic.name, "logic");
+        assert!(library.get_ruleset("geometry").is_none());
+
+        let rule = library.get_rule("logic/identity_and/0").unwrap();
+        assert_eq!(rule.label, "Identity (AND)");
+        assert!(library.get_rule("logic/unknown").is_none());
+
+        // `algebra_simplify` has the rules of `algebra`, in the same namespace
+        assert_eq!(library.get_rulesets_of_rule("algebra/add_zero/0"), vec!["algebra", "algebra_simplify"]);
+        assert_eq!(library.get_rulesets_of_rule("logic/identity_and/0"), vec!["logic"]);
+    }
+
+    #[test]
+    fn errors() {
+        let valid = r#"{ "name": "valid", "context": { "base": "arithmetic" }, "rules": [ { "id": "r", "expr": "X + 0 = X" } ] }"#;
+        let invalid_json = r#"{ "name": "invalid_json", "rules": [ "#;
+        let invalid_import = r#"{ "name": "invalid_import", "imports": [{"name": "missing"}], "rules": [] }"#;
+        let invalid_rules = r#"{ "name": "invalid_rules", "imports": [{"name": "valid"}], "rules": [ { "id": "r", "expr": "X = X + 0", "auto": true } ] }"#;
+        let loader = HashMap::from([
+            ("valid".to_string(), valid.to_string()),
+            ("invalid_json".to_string(), invalid_json.to_string()),
+            ("invalid_import".to_string(), invalid_import.to_string()),
+            ("invalid_rules".to_string(), invalid_rules.to_string()),
+        ]);
+        let names = loader.keys().cloned().collect::<Vec<String>>();
+        let (library, errors) = RuleLibrary::load(&names, &loader);
+        let names = errors.iter().map(|error| match error {
+            LibraryError::InvalidRuleset { name, .. } | LibraryError::InvalidRules { name, .. } => name.clone(),
+            _ => panic!("unexpected error: {}", error),
+        }).collect::<Vec<String>>();
+        // all the errors are reported, in order
+        assert_eq!(names, vec!["invalid_import", "invalid_json", "invalid_rules"]);
+        assert_eq!(errors[0].to_string(), "invalid_import: import not found: missing");
+        // `r` overrides the imported `valid/r`, and keeps its id
+        assert_eq!(errors[2].to_string(), "invalid_rules: auto rules can loop: valid/r");
+        // the valid rulesets are still loaded
+        assert_eq!(library.names(), vec!["valid"]);
+        assert!(library.get_rule("valid/r").is_some());
+
+        let (library, errors) = RuleLibrary::load_dir("no_such_dir");
+        assert!(matches!(errors[0], LibraryError::Io(_)));
+        assert!(library.names().is_empty());
+    }
+}
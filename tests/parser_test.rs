@@ -1,3 +1,5 @@
+#![allow(clippy::needless_return)]
+
 use equaio::parser::{parser, parser_prefix};
 use equaio::expression as exp;
 use equaio::vec_strings;
@@ -216,4 +218,174 @@ mod variadic {
         let expr1 = parser::to_expression("X * (A + ...) = (X * A) + ...", &ctx).unwrap();
         assert_eq!(expr0, expr1);
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod precedence {
+    use super::*;
+    use std::collections::HashMap;
+    use equaio::arithmetic::get_arithmetic_ctx;
+    
+    #[test]
+    fn arithmetic() {
+        let ctx = get_arithmetic_ctx().add_params(vec_strings!["a", "b", "c", "d"]);
+        
+        let expr0 = parser_prefix::to_expression("+(a,*(b,c))", &ctx).unwrap();
+        let expr1 = parser::to_expression("a + b * c", &ctx).unwrap();
+        assert_eq!(expr0, expr1);
+        
+        let expr0 = parser_prefix::to_expression("-(+(a,/(b,c)),d)", &ctx).unwrap();
+        let expr1 = parser::to_expression("a + b / c - d", &ctx).unwrap();
+        assert_eq!(expr0, expr1);
+        
+        let expr0 = parser_prefix::to_expression("=(+(*(a,b),*(c,d)),a)", &ctx).unwrap();
+        let expr1 = parser::to_expression("a * b + c * d = a", &ctx).unwrap();
+        assert_eq!(expr0, expr1);
+        assert_eq!(expr1.to_string(true), "(((a * b) + (c * d)) = a)");
+    }
+    
+    #[test]
+    fn same_precedence_is_left_associative() {
+        let ctx = get_arithmetic_ctx().add_params(vec_strings!["a", "b", "c"]);
+        let expr0 = parser_prefix::to_expression("-(-(a,b),c)", &ctx).unwrap();
+        let expr1 = parser::to_expression("a - b - c", &ctx).unwrap();
+        assert_eq!(expr0, expr1);
+    }
+    
+    #[test]
+    fn right_associative() {
+        let ctx = exp::Context {
+            parameters: vec_strings!["a", "b", "c"],
+            binary_ops: vec_strings!["^", "*"],
+            op_precedence: HashMap::from([("*".to_string(), 1), ("^".to_string(), 2)]),
+            right_assoc_ops: vec_strings!["^"],
+            ..Default::default()
+        };
+        let expr0 = parser_prefix::to_expression("^(a,^(b,c))", &ctx).unwrap();
+        let expr1 = parser::to_expression("a ^ b ^ c", &ctx).unwrap();
+        assert_eq!(expr0, expr1);
+        
+        let expr0 = parser_prefix::to_expression("*(^(a,b),^(c,^(a,b)))", &ctx).unwrap();
+        let expr1 = parser::to_expression("a ^ b * c ^ a ^ b", &ctx).unwrap();
+        assert_eq!(expr0, expr1);
+    }
+    
+    #[test]
+    fn unary() {
+        let ctx = get_arithmetic_ctx().add_params(vec_strings!["a", "b", "lnx"]);
+        let expr0 = parser_prefix::to_expression("-(^(2,2))", &ctx).unwrap();
+        let expr1 = parser::to_expression("-2 ^ 2", &ctx).unwrap();
+        assert_eq!(expr0, expr1);
+        assert_eq!(expr1.calculate_numeric().unwrap().to_string(), "-4");
+
+        let expr0 = parser_prefix::to_expression("*(-(a),b)", &ctx).unwrap();
+        let expr1 = parser::to_expression("-a * b", &ctx).unwrap();
+        assert_eq!(expr0, expr1);
+
+        let expr0 = parser_prefix::to_expression("^(sqrt(a),2)", &ctx).unwrap();
+        let expr1 = parser::to_expression("sqrt(a) ^ 2", &ctx).unwrap();
+        assert_eq!(expr0, expr1);
+
+        // `lnx` is a parameter, not `ln x`
+        let expr0 = parser_prefix::to_expression("+(lnx,ln(a))", &ctx).unwrap();
+        let expr1 = parser::to_expression("lnx + ln(a)", &ctx).unwrap();
+        assert_eq!(expr0, expr1);
+    }
+
+    #[test]
+    fn statement() {
+        let ctx = exp::Context {
+            parameters: vec_strings!["a", "b"],
+            binary_ops: vec_strings!["+"],
+            op_precedence: HashMap::from([("+".to_string(), 1)]),
+            ..Default::default()
+        };
+        let expr0 = parser_prefix::to_expression("=>(=(+(a,b),b),=(a,+(b,b)))", &ctx).unwrap();
+        let expr1 = parser::to_expression("a + b = b => a = b + b", &ctx).unwrap();
+        assert_eq!(expr0, expr1);
+    }
+    
+    #[test]
+    fn variadic() {
+        let ctx = get_arithmetic_ctx();
+        let expr0 = parser_prefix::to_expression("=(*(X,+(...(A_i))),+(...(*(X,A_i))))", &ctx).unwrap();
+        let expr1 = parser::to_expression("X * (A_i + ...) = X * A_i + ...", &ctx).unwrap();
+        assert_eq!(expr0, expr1);
+    }
+    
+    #[test]
+    fn invalid() {
+        let ctx = get_arithmetic_ctx();
+        assert!(parser::to_expression("a + * b", &ctx).is_err());
+        assert!(parser::to_expression("a + b +", &ctx).is_err());
+        assert!(parser::to_expression("... + a", &ctx).is_err());
+    }
+}
+
+#[cfg(test)]
+mod error {
+    use super::*;
+    use equaio::arithmetic::get_arithmetic_ctx;
+    use equaio::parser::parse_error::{ParseError, ParseErrorKind};
+    
+    fn assert_error(err: ParseError, kind: ParseErrorKind, span: std::ops::Range<usize>, token: &str) {
+        assert_eq!(err.kind, kind);
+        assert_eq!(err.span, span);
+        assert_eq!(err.token, token);
+    }
+    
+    #[test]
+    fn unbalanced_parenthesis() {
+        let ctx = get_arithmetic_ctx();
+        let err = parser::to_expression("(a + b", &ctx).unwrap_err();
+        assert_error(err, ParseErrorKind::UnbalancedParenthesis, 0..1, "(");
+        let err = parser::to_expression("a + b) * c", &ctx).unwrap_err();
+        assert_error(err, ParseErrorKind::UnbalancedParenthesis, 5..6, ")");
+        let err = parser_prefix::to_expression("+(a,f(b)", &ctx).unwrap_err();
+        assert_error(err, ParseErrorKind::UnbalancedParenthesis, 1..2, "(");
+    }
+    
+    #[test]
+    fn missing_operand() {
+        let ctx = get_arithmetic_ctx();
+        let text = "a + * b";
+        let err = parser::to_expression(text, &ctx).unwrap_err();
+        assert_eq!(err.source_in(text), "*");
+        assert_error(err, ParseErrorKind::MissingOperand, 4..5, "*");
+        let err = parser::to_expression("x = a +", &ctx).unwrap_err();
+        assert_error(err, ParseErrorKind::MissingOperand, 6..7, "+");
+        let err = parser::to_expression("f(a, ) + 1", &ctx).unwrap_err();
+        assert_error(err, ParseErrorKind::EmptyExpression, 4..4, "");
+        let err = parser_prefix::to_expression("+(a,,b)", &ctx).unwrap_err();
+        assert_error(err, ParseErrorKind::MissingOperand, 4..4, "");
+        let err = parser_prefix::to_expression("f(x , , y)", &ctx).unwrap_err();
+        assert_error(err, ParseErrorKind::MissingOperand, 5..5, "");
+        let err = parser_prefix::to_expression("f(g(x, y),  , y)", &ctx).unwrap_err();
+        assert_error(err, ParseErrorKind::MissingOperand, 10..10, "");
+    }
+    
+    #[test]
+    fn unknown_operator() {
+        let ctx = get_arithmetic_ctx();
+        let err = parser::to_expression("a + b ? c", &ctx).unwrap_err();
+        assert_error(err, ParseErrorKind::UnknownOperator, 6..7, "?");
+        let err = parser::to_expression("(a + b) (c)", &ctx).unwrap_err();
+        assert_error(err, ParseErrorKind::UnknownOperator, 8..11, "(...)");
+    }
+    
+    #[test]
+    fn misplaced_variadic() {
+        let ctx = get_arithmetic_ctx();
+        let err = parser::to_expression("... + a", &ctx).unwrap_err();
+        assert_error(err, ParseErrorKind::MisplacedVariadic, 0..3, "...");
+        let err = parser::to_expression("A_i - ...", &ctx).unwrap_err();
+        assert_error(err, ParseErrorKind::MisplacedVariadic, 4..9, "-");
+    }
+    
+    #[test]
+    fn span_after_unary() {
+        let ctx = get_arithmetic_ctx();
+        let text = "-a + -b ) ";
+        let err = parser::to_expression(text, &ctx).unwrap_err();
+        assert_error(err, ParseErrorKind::UnbalancedParenthesis, 8..9, ")");
+    }
+}
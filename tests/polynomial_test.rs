@@ -0,0 +1,107 @@
+#![allow(clippy::needless_return)]
+
+use equaio::address;
+use equaio::arithmetic::get_arithmetic_ctx;
+use equaio::expression::{Address, Expression};
+use equaio::parser::parser;
+use equaio::polynomial::{self, PolynomialError};
+use equaio::vec_strings;
+use equaio::worksheet::{WorkableExpressionSequence, WorksheetContext, Action};
+
+fn parse(s: &str) -> Expression {
+    let ctx = get_arithmetic_ctx().add_params(vec_strings!["x", "y"]);
+    return parser::to_expression(s, &ctx).unwrap();
+}
+
+#[cfg(test)]
+mod to_polynomial {
+    use super::*;
+    use equaio::number::Rational;
+
+    #[test]
+    fn terms() {
+        let vars = vec_strings!["x", "y"];
+        let poly = parse("2 * x * y - 3 * y * x + x / 2 + 1").to_polynomial(&vars).unwrap();
+        assert_eq!(poly.terms.len(), 3);
+        assert_eq!(poly.terms.get(&vec![1, 1]), Some(&Rational::from_integer(-1)));
+        assert_eq!(poly.terms.get(&vec![1, 0]), Some(&Rational::new(1, 2).unwrap()));
+        assert_eq!(poly.terms.get(&vec![0, 0]), Some(&Rational::from_integer(1)));
+        assert_eq!(poly.degree(), 2);
+        assert!(parse("x - x").to_polynomial(&vars).unwrap().is_zero());
+    }
+
+    #[test]
+    fn round_trip() {
+        let vars = vec_strings!["x", "y"];
+        let poly = parse("(x - y) * (x + y)").to_polynomial(&vars).unwrap();
+        let expr = Expression::from_polynomial(&poly);
+        assert_eq!(expr.to_string(true), "((x ^ 2) + (-(y ^ 2)))");
+        assert_eq!(expr.to_polynomial(&vars).unwrap(), poly);
+    }
+
+    #[test]
+    fn not_a_polynomial() {
+        let vars = vec_strings!["x", "y"];
+        assert!(matches!(parse("x / y").to_polynomial(&vars), Err(PolynomialError::NotAPolynomial(_))));
+        assert!(matches!(parse("x ^ y").to_polynomial(&vars), Err(PolynomialError::NotAPolynomial(_))));
+        assert!(matches!(parse("sqrt(x)").to_polynomial(&vars), Err(PolynomialError::NotAPolynomial(_))));
+        assert!(matches!(parse("sqrt(2) * x").to_polynomial(&vars), Err(PolynomialError::InexactCoefficient(_))));
+        // decimal literals are exact
+        assert!(parse("0.5 * x").to_polynomial(&vars).is_ok());
+        assert!(matches!(parse("x ^ 100").to_polynomial(&vars), Err(PolynomialError::ExponentTooLarge)));
+        // `y` is not one of the variables
+        assert!(matches!(parse("x + y").to_polynomial(&["x".to_string()]), Err(PolynomialError::NotAPolynomial(_))));
+    }
+}
+
+#[cfg(test)]
+mod simplification {
+    use super::*;
+
+    #[test]
+    fn expand() {
+        assert_eq!(parse("(x + 1) ^ 2").expand().unwrap().to_string(true), "((x ^ 2) + (2 * x) + 1)");
+        assert_eq!(parse("x / 2 + x / 3").expand().unwrap().to_string(true), "((5 / 6) * x)");
+        assert_eq!(parse("x - x").expand().unwrap().to_string(true), "0");
+    }
+
+    #[test]
+    fn collect_like_terms() {
+        assert_eq!(parse("2 * x + y + 3 * x").collect_like_terms().unwrap().to_string(true), "((5 * x) + y)");
+        // products of sums are not expanded
+        assert_eq!(parse("2 * (x + 1) + x + x").collect_like_terms().unwrap().to_string(true), "((2 * x) + (2 * (x + 1)))");
+        assert!(parse("2 * x").collect_like_terms().is_err());
+        assert!(matches!(parse("2 * (x + 1) + x").collect_like_terms(), Err(PolynomialError::NoLikeTerms)));
+    }
+
+    #[test]
+    fn fully_simplify() {
+        let expr = parse("(x + 1) ^ 2 = x * x + 1");
+        assert_eq!(expr.fully_simplify().unwrap().to_string(true), "(((x ^ 2) + (2 * x) + 1) = ((x ^ 2) + 1))");
+    }
+}
+
+#[cfg(test)]
+mod worksheet {
+    use super::*;
+
+    #[test]
+    fn actions() {
+        let mut ctx = WorksheetContext::default();
+        ctx.expression_context = get_arithmetic_ctx().add_params(vec_strings!["x", "y"]);
+        ctx.set_get_possible_actions_function_by_name("polynomial");
+        let mut seq = WorkableExpressionSequence::new(ctx);
+        seq.push(Action::Introduce("Introduce".to_string()), parse("2 * (x + 1) + x = 5")).unwrap();
+
+        let actions = seq.get_possible_actions(&vec![address![0]]);
+        let names = actions.iter().map(|(action, _)| action.to_string()).collect::<Vec<String>>();
+        assert_eq!(names[0], "Expand");
+        assert!(!names.contains(&"Collect like terms".to_string()));
+        let actions = polynomial::get_possible_actions::polynomial(seq.last_expression(), &[address![0]]);
+        assert_eq!(actions.len(), 1);
+
+        assert!(seq.expand_at(&address![0]));
+        assert_eq!(seq.last_expression(), &parse("3 * x + 2 = 5"));
+        assert!(!seq.collect_like_terms_at(&address![1]));
+    }
+}
@@ -0,0 +1,68 @@
+#![allow(clippy::needless_return)]
+
+use equaio::registry;
+use equaio::rule::{self, ParserError};
+use equaio::worksheet::Worksheet;
+use equaio::parser::parser;
+use equaio::address;
+use equaio::expression::{Address, Expression};
+
+#[cfg(test)]
+mod registry_test {
+    use super::*;
+    
+    #[test]
+    fn builtin() {
+        assert!(registry::contains_normalization_function("algebra"));
+        for name in ["algebra", "arithmetic", "basic"] {
+            assert!(registry::contains_get_possible_actions_function(name));
+        }
+        assert!(registry::get_normalization_function("unknown").is_none());
+        assert!(registry::get_possible_actions_function("unknown").is_none());
+    }
+    
+    #[test]
+    fn register() {
+        registry::register_normalization_function("test_identity", |expr, _ctx| expr.clone());
+        registry::register_get_possible_actions_function("test_none", |_expr, _ctx, _addr_vec| vec![]);
+        let f = registry::get_normalization_function("test_identity").unwrap();
+        let expr = Expression::default();
+        assert_eq!(f(&expr, &Default::default()), expr);
+        
+        let mut ws = Worksheet::new();
+        assert!(ws.set_normalization_function_by_name("test_identity"));
+        assert!(ws.set_get_possible_actions_function_by_name("test_none"));
+        assert!(!ws.set_normalization_function_by_name("unknown"));
+    }
+}
+
+#[cfg(test)]
+mod ruleset {
+    use super::*;
+    
+    #[test]
+    fn from_json() {
+        let rulestr = std::fs::read_to_string("rules/logic.json").unwrap();
+        let ruleset = rule::parse_ruleset_from_json(&rulestr).unwrap();
+        assert_eq!(ruleset.normalization_function, Some("algebra".to_string()));
+        assert_eq!(ruleset.get_possible_actions_function, Some("basic".to_string()));
+        
+        let mut ws = Worksheet::new();
+        ws.set_ruleset(ruleset);
+        ws.introduce_expression(parser::to_expression("A | 0", &ws.get_expression_context()).unwrap()).unwrap();
+        let seq = ws.get(0).unwrap();
+        let actions = seq.get_possible_actions(&vec![address![]]);
+        assert!(actions.iter().any(|(action, _)| action.to_string() == "Identity (OR)"));
+    }
+    
+    #[test]
+    fn unknown_function() {
+        let rulestr = r#"{
+            "name": "simple",
+            "get_possible_actions_function": "unknown",
+            "rules": []
+        }"#;
+        let result = rule::parse_ruleset_from_json(rulestr);
+        assert!(matches!(result, Err(ParserError::UnknownFunction(name)) if name == "unknown"));
+    }
+}
@@ -1,3 +1,5 @@
+#![allow(clippy::needless_return)]
+
 use::equaio::rule;
 
 fn assert_rule_eq(rule: &rule::Rule, id: &str, label: &str, expr: &str) {
@@ -103,9 +105,11 @@ mod rule_test {
         }
         "#;
         let rules = rule::parse_ruleset_from_json(str).unwrap().rule_vec;
-        assert_eq!(rules.len(), 2);
+        assert_eq!(rules.len(), 4);
         assert_rule_eq(&rules[0], "simple/rule0/0", "", "((A * (B + C)) = ((A * B) + (A * C)))");
         assert_rule_eq(&rules[1], "simple/rule0/1", "", "(((B + C) * A) = ((A * B) + (A * C)))");
+        assert_rule_eq(&rules[2], "simple/rule0/2", "", "((A * (C + B)) = ((A * B) + (A * C)))");
+        assert_rule_eq(&rules[3], "simple/rule0/3", "", "(((C + B) * A) = ((A * B) + (A * C)))");
     }
     
     #[test]
@@ -129,9 +133,58 @@ mod rule_test {
         }
         "#;
         let rules = rule::parse_ruleset_from_json(str).unwrap().rule_vec;
-        assert_eq!(rules.len(), 2);
+        assert_eq!(rules.len(), 4);
         assert_rule_eq(&rules[0], "simple/rule0/0", "", "((A * (B + C)) = ((A * B) + (A * C)))");
         assert_rule_eq(&rules[1], "simple/rule0/1", "", "(((B + C) * A) = ((A * B) + (A * C)))");
+        assert_rule_eq(&rules[2], "simple/rule0/2", "", "((A * (C + B)) = ((A * B) + (A * C)))");
+        assert_rule_eq(&rules[3], "simple/rule0/3", "", "(((C + B) * A) = ((A * B) + (A * C)))");
+    }
+    
+    #[test]
+    fn implication_variation() {
+        let str = r#"
+        {
+            "name": "logic",
+            "context": { "binary_ops": ["&", "|"], "commutative_ops": ["&", "|"] },
+            "variations": [
+                {"expr":  "P & Q = Q & P"}
+            ],
+            "rules": [
+                { "id": "and_elim", "expr": "P & Q => P" },
+                { "id": "and_self", "expr": "P & P => P" }
+            ]
+        }
+        "#;
+        let rules = rule::parse_ruleset_from_json(str).unwrap().rule_vec;
+        assert_eq!(rules.len(), 3);
+        assert_rule_eq(&rules[0], "logic/and_elim/0", "", "((P & Q) => P)");
+        assert_rule_eq(&rules[1], "logic/and_elim/1", "", "((Q & P) => P)");
+        assert_rule_eq(&rules[2], "logic/and_self", "", "((P & P) => P)");
+    }
+    
+    #[test]
+    fn rhs_variation() {
+        let str = r#"
+        {
+            "name": "logic",
+            "context": { "binary_ops": ["&", "|"], "commutative_ops": ["&", "|"] },
+            "variations": [
+                {"expr":  "P | Q = Q | P"}
+            ],
+            "rules": [
+                { "id": "intro", "expr": "P => P | Q" },
+                { "id": "intro_lhs_only", "expr": "P => P | Q", "variate_rhs": false },
+                { "id": "intro_self", "expr": "P => P | P" }
+            ],
+            "variate_rhs": true
+        }
+        "#;
+        let rules = rule::parse_ruleset_from_json(str).unwrap().rule_vec;
+        assert_eq!(rules.len(), 4);
+        assert_rule_eq(&rules[0], "logic/intro/0", "", "(P => (P | Q))");
+        assert_rule_eq(&rules[1], "logic/intro/1", "", "(P => (Q | P))");
+        assert_rule_eq(&rules[2], "logic/intro_lhs_only", "", "(P => (P | Q))");
+        assert_rule_eq(&rules[3], "logic/intro_self", "", "(P => (P | P))");
     }
 }
 
@@ -169,4 +222,415 @@ mod from_file {
             assert_rule_eq(&rules[i], id, description, expression);
         }
     }
+    
+    #[test]
+    fn normalization() {
+        let rulestr = std::fs::read_to_string("rules/algebra.json").unwrap();
+        let normalization = rule::parse_ruleset_from_json(&rulestr).unwrap().normalization;
+        let normalization_str = normalization.iter().map(|e| e.to_string(true)).collect::<Vec<_>>();
+        assert_eq!(normalization_str, vec!["((-0) = 0)"]);
+    }
+    
+    #[test]
+    fn serde_roundtrip() {
+        let rulestr = std::fs::read_to_string("rules/logic.json").unwrap();
+        let ruleset = rule::parse_ruleset_from_json(&rulestr).unwrap();
+        let json = serde_json::to_string(&ruleset).unwrap();
+        let loaded: rule::RuleSet = serde_json::from_str(&json).unwrap();
+        assert!(loaded == ruleset);
+    }
+}
+
+#[cfg(test)]
+mod precedence {
+    use super::*;
+    
+    #[test]
+    fn context_precedence() {
+        let str = r#"
+        {
+            "name": "logic",
+            "context": {
+                "unary_ops": ["~"],
+                "binary_ops": ["&", "|"],
+                "assoc_ops": ["&", "|"],
+                "op_precedence": {"|": 1, "&": 2}
+            },
+            "rules": [
+                {
+                    "id": "distributivity_and",
+                    "expr": "P & (Q | R) = P & Q | P & R"
+                }
+            ]
+        }
+        "#;
+        let rules = rule::parse_ruleset_from_json(str).unwrap().rule_vec;
+        assert_rule_eq(&rules[0], "logic/distributivity_and", "", "((P & (Q | R)) = ((P & Q) | (P & R)))");
+    }
+}
+
+#[cfg(test)]
+mod error {
+    use super::*;
+    use equaio::parser::parse_error::ParseErrorKind;
+    
+    #[test]
+    fn invalid_expression() {
+        let str = r#"
+        {
+            "name": "simple",
+            "context": { "base": "arithmetic" },
+            "rules": [
+                { "id": "rule0", "expr": "X + 0 = X" },
+                { "id": "rule1", "expr": "X * (1 = X" }
+            ]
+        }
+        "#;
+        match rule::parse_ruleset_from_json(str) {
+            Err(rule::ParserError::InvalidExpression { id, expr, error }) => {
+                assert_eq!(id, "simple/rule1");
+                assert_eq!(expr, "X * (1 = X");
+                assert_eq!(error.kind, ParseErrorKind::UnbalancedParenthesis);
+                assert_eq!(error.span, 4..5);
+            },
+            _ => panic!("expected an invalid expression error"),
+        }
+    }
+    
+    #[test]
+    fn invalid_variation() {
+        let str = r#"
+        {
+            "name": "simple",
+            "context": { "base": "arithmetic" },
+            "variations": [ {"expr": "A + B = B +"} ],
+            "rules": [ { "id": "rule0", "expr": "X + 0 = X" } ]
+        }
+        "#;
+        let err = rule::parse_ruleset_from_json(str).err().unwrap();
+        assert_eq!(err.to_string(), "invalid expression in `variations`: missing operand at 10..11 (`+`) in `A + B = B +`");
+    }
+    
+    #[test]
+    fn non_commutative_variation() {
+        let str = r#"
+        {
+            "name": "simple",
+            "context": { "base": "arithmetic", "binary_ops": ["@"], "assoc_ops": ["@"] },
+            "variations": [ {"expr": "A + B = B + A"}, {"expr": "A @ B = B @ A"} ],
+            "rules": [ { "id": "rule0", "expr": "X + 0 = X" } ]
+        }
+        "#;
+        let err = rule::parse_ruleset_from_json(str).err().unwrap();
+        assert_eq!(err.to_string(), "invalid rule: variations: `@` is not commutative");
+    }
+    
+    #[test]
+    fn normalization_not_an_equation() {
+        let rulestr = r#"{
+            "name": "simple",
+            "context": { "base": "arithmetic" },
+            "normalization": [{"expr": "A + 0"}],
+            "rules": []
+        }"#;
+        let result = rule::parse_ruleset_from_json(rulestr);
+        assert!(matches!(result, Err(rule::ParserError::InvalidRule(_))));
+    }
+}
+
+#[cfg(test)]
+mod condition {
+    use super::*;
+    use equaio::address;
+    use equaio::arithmetic::get_arithmetic_ctx;
+    use equaio::expression::{Address, ExpressionError};
+    use equaio::parser::parser;
+    
+    fn get_ruleset() -> rule::RuleSet {
+        let str = r#"
+        {
+            "name": "simple",
+            "context": { "base": "arithmetic" },
+            "rules": [
+                { "id": "div_self", "expr": "X / X = 1", "conditions": ["X != 0"] },
+                { "id": "int_only", "expr": "X * 1 = X", "conditions": ["is_integer(X)", "X >= 0"] }
+            ]
+        }
+        "#;
+        return rule::parse_ruleset_from_json(str).unwrap();
+    }
+    
+    #[test]
+    fn parse() {
+        let rules = get_ruleset().rule_vec;
+        let conditions = rules.iter()
+            .map(|r| r.conditions.iter().map(|c| c.to_string(true)).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        assert_eq!(conditions, vec![vec!["(X != 0)"], vec!["is_integer(X)", "(X >= 0)"]]);
+    }
+    
+    #[test]
+    fn check() {
+        let rule_map = get_ruleset().get_rule_map();
+        let div_self = rule_map.get("simple/div_self").unwrap();
+        let int_only = rule_map.get("simple/int_only").unwrap();
+        let ctx = get_arithmetic_ctx().add_params(vec!["x".to_string()]);
+        let expr = |s: &str| parser::to_expression(s, &ctx).unwrap();
+        
+        let (result, assumptions) = expr("5 / 5").apply_rule_with_assumptions_at(div_self, &address![]).unwrap();
+        assert_eq!(result.to_string(true), "1");
+        assert!(assumptions.is_empty());
+        
+        let result = expr("(1 - 1) / (1 - 1)").apply_rule_at(div_self, &address![]);
+        assert!(matches!(result, Err(ExpressionError::ConditionNotSatisfied(c)) if c == "((1 - 1) != 0)"));
+        
+        let (_, assumptions) = expr("x / x").apply_rule_with_assumptions_at(div_self, &address![]).unwrap();
+        let assumptions = assumptions.iter().map(|c| c.to_string(true)).collect::<Vec<_>>();
+        assert_eq!(assumptions, vec!["(x != 0)"]);
+        
+        assert!(expr("2 * 1").apply_rule_at(int_only, &address![]).is_ok());
+        assert!(expr("2.5 * 1").apply_rule_at(int_only, &address![]).is_err());
+        assert!(expr("(0 - 2) * 1").apply_rule_at(int_only, &address![]).is_err());
+    }
+}
+
+#[cfg(test)]
+mod ac_matching {
+    use super::*;
+    use equaio::address;
+    use equaio::arithmetic::get_arithmetic_ctx;
+    use equaio::expression::Address;
+    use equaio::parser::parser;
+    
+    fn get_ruleset(ac_matching: bool) -> rule::RuleSet {
+        let str = r#"
+        {
+            "name": "simple",
+            "context": { "base": "arithmetic" },
+            "ac_matching": AC_MATCHING,
+            "rules": [
+                { "id": "add_zero", "expr": "X + 0 = X" },
+                { "id": "mul_zero", "expr": "X * 0 = 0" }
+            ]
+        }
+        "#.replace("AC_MATCHING", &ac_matching.to_string());
+        return rule::parse_ruleset_from_json(&str).unwrap();
+    }
+    
+    #[test]
+    fn apply() {
+        let rule_map = get_ruleset(true).get_rule_map();
+        let add_zero = rule_map.get("simple/add_zero").unwrap();
+        let mul_zero = rule_map.get("simple/mul_zero").unwrap();
+        assert_eq!(add_zero.ac_ops, vec!["+", "*"]);
+        let ctx = get_arithmetic_ctx().add_params(vec!["a".to_string(), "b".to_string()]);
+        let expr = |s: &str| parser::to_expression(s, &ctx).unwrap();
+        
+        let result = expr("a + 0 + b").apply_rule_at(add_zero, &address![]).unwrap();
+        assert_eq!(result.to_string(true), "(a + b)");
+        let result = expr("0 + a").apply_rule_at(add_zero, &address![]).unwrap();
+        assert_eq!(result.to_string(true), "a");
+        let result = expr("2 * a * 0 * b = 1").apply_rule_at(mul_zero, &address![0]).unwrap();
+        assert_eq!(result.to_string(true), "(0 = 1)");
+        
+        let addresses = expr("(0 + a) * b").get_possible_rule_application_addresses(add_zero, &ctx);
+        assert_eq!(addresses, vec![address![0]]);
+    }
+    
+    #[test]
+    fn disabled() {
+        let rule_map = get_ruleset(false).get_rule_map();
+        let add_zero = rule_map.get("simple/add_zero").unwrap();
+        assert!(add_zero.ac_ops.is_empty());
+        let ctx = get_arithmetic_ctx().add_params(vec!["a".to_string(), "b".to_string()]);
+        let expr = parser::to_expression("0 + a", &ctx).unwrap();
+        assert!(expr.apply_rule_at(add_zero, &address![]).is_err());
+    }
+}
+
+#[cfg(test)]
+mod sort {
+    use super::*;
+    use equaio::address;
+    use equaio::arithmetic::get_arithmetic_ctx;
+    use equaio::expression::{Address, Context, ExpressionError};
+    use equaio::parser::parser;
+    use equaio::sort::Sort;
+    
+    fn get_ruleset() -> rule::RuleSet {
+        let str = r#"
+        {
+            "name": "simple",
+            "context": { "base": "arithmetic" },
+            "sorts": {"X": "Real"},
+            "rules": [
+                { "id": "mul_one", "expr": "X * 1 = X" },
+                { "id": "pow_pow", "expr": "(X ^ N) ^ M = X ^ (N * M)", "sorts": {"N": "Integer", "M": "Integer"} }
+            ]
+        }
+        "#;
+        return rule::parse_ruleset_from_json(str).unwrap();
+    }
+    
+    fn logic_ctx() -> Context {
+        let mut ctx = get_arithmetic_ctx().add_params(vec!["p".to_string(), "q".to_string(), "a".to_string()]);
+        ctx.binary_ops.push("&".to_string());
+        return ctx.set_sort("&", Sort::Boolean);
+    }
+    
+    #[test]
+    fn parse() {
+        let rule_map = get_ruleset().get_rule_map();
+        let mul_one = rule_map.get("simple/mul_one").unwrap();
+        let pow_pow = rule_map.get("simple/pow_pow").unwrap();
+        assert_eq!(mul_one.sorts.get("X"), Some(&Sort::Real));
+        assert_eq!(pow_pow.sorts.get("X"), Some(&Sort::Real));
+        assert_eq!(pow_pow.sorts.get("N"), Some(&Sort::Integer));
+        assert_eq!(pow_pow.sorts.get("M"), Some(&Sort::Integer));
+    }
+    
+    #[test]
+    fn apply() {
+        let rule_map = get_ruleset().get_rule_map();
+        let mul_one = rule_map.get("simple/mul_one").unwrap();
+        let pow_pow = rule_map.get("simple/pow_pow").unwrap();
+        let ctx = logic_ctx();
+        let expr = |s: &str| parser::to_expression(s, &ctx).unwrap();
+        
+        let (result, _) = expr("a * 1").apply_rule_in_context_at(mul_one, &address![], &ctx).unwrap();
+        assert_eq!(result.to_string(true), "a");
+        let result = expr("(p & q) * 1").apply_rule_in_context_at(mul_one, &address![], &ctx);
+        assert!(matches!(result, Err(ExpressionError::PatternDoesNotMatch)));
+        assert!(expr("(p & q) * 1").get_possible_rule_application_addresses(mul_one, &ctx).is_empty());
+        
+        let (result, _) = expr("(a ^ 2) ^ 3").apply_rule_in_context_at(pow_pow, &address![], &ctx).unwrap();
+        assert_eq!(result.to_string(true), "(a ^ (2 * 3))");
+        assert!(expr("(a ^ 0.5) ^ 2").apply_rule_in_context_at(pow_pow, &address![], &ctx).is_err());
+        // `a * 2` is a `Real`, while the unknown sort of the parameter `a` is allowed
+        assert!(expr("(a ^ (a * 2)) ^ 2").apply_rule_in_context_at(pow_pow, &address![], &ctx).is_err());
+        assert!(expr("(a ^ a) ^ 2").apply_rule_in_context_at(pow_pow, &address![], &ctx).is_ok());
+    }
+
+    #[test]
+    fn operator_sort() {
+        let str = r#"
+        {
+            "name": "logic",
+            "context": { "binary_ops": ["&"], "sorts": {"&": "Boolean"} },
+            "sorts": {"P": "Boolean"},
+            "rules": [ { "id": "idempotent_and", "expr": "P & P = P" } ]
+        }
+        "#;
+        let ruleset = rule::parse_ruleset_from_json(str).unwrap();
+        assert!(!ruleset.context.sorts.contains_key("P"));
+        let rule_map = ruleset.get_rule_map();
+        let idempotent_and = rule_map.get("logic/idempotent_and").unwrap();
+        let ctx = logic_ctx();
+        let expr = |s: &str| parser::to_expression(s, &ctx).unwrap();
+
+        let (result, _) = expr("(p & q) & (p & q)").apply_rule_in_context_at(idempotent_and, &address![], &ctx).unwrap();
+        assert_eq!(result.to_string(true), "(p & q)");
+        assert!(expr("(a + 1) & (a + 1)").apply_rule_in_context_at(idempotent_and, &address![], &ctx).is_err());
+    }
+}
+
+#[cfg(test)]
+mod import {
+    use super::*;
+    use std::collections::HashMap;
+    
+    fn loader() -> HashMap<String, String> {
+        let base = r#"
+        {
+            "name": "base",
+            "context": { "base": "arithmetic" },
+            "sorts": {"X": "Real"},
+            "variations": [ {"expr": "A + B = B + A"} ],
+            "normalization_function": "algebra",
+            "rules": [
+                { "id": "add_zero", "expr": "X + 0 = X", "label": "Addition with 0" },
+                { "id": "mul_one", "expr": "X * 1 = X", "label": "Multiplication with 1" },
+                { "id": "mul_zero", "expr": "X * 0 = 0", "label": "Multiplication with 0" }
+            ]
+        }
+        "#;
+        let logic = r#"
+        {
+            "name": "logic",
+            "context": { "binary_ops": ["&"], "assoc_ops": ["&"], "commutative_ops": ["&"] },
+            "rules": [ { "id": "idempotent_and", "expr": "P & P = P" } ]
+        }
+        "#;
+        let cycle_a = r#"{ "name": "a", "imports": [{"name": "b"}], "rules": [] }"#;
+        let cycle_b = r#"{ "name": "b", "imports": [{"name": "a"}], "rules": [] }"#;
+        return HashMap::from([
+            ("base".to_string(), base.to_string()),
+            ("logic".to_string(), logic.to_string()),
+            ("a".to_string(), cycle_a.to_string()),
+            ("b".to_string(), cycle_b.to_string()),
+        ]);
+    }
+    
+    #[test]
+    fn import() {
+        let str = r#"
+        {
+            "name": "derived",
+            "imports": [ {"name": "base", "exclude": ["mul_zero"]}, {"name": "logic"}, {"name": "base"} ],
+            "context": { "parameters": ["x"] },
+            "rules": [
+                { "id": "mul_one", "auto": true },
+                { "id": "sub_zero", "expr": "X - 0 = X" }
+            ]
+        }
+        "#;
+        let ruleset = rule::parse_ruleset_from_json_with_loader(str, &loader()).unwrap();
+        assert_eq!(ruleset.rule_ids, vec![
+            "base/add_zero/0", "base/add_zero/1", "base/mul_one", "logic/idempotent_and",
+            // excluded only from the first import of `base`
+            "base/mul_zero",
+            "derived/sub_zero",
+        ]);
+        let rules = &ruleset.rule_vec;
+        assert_rule_eq(&rules[1], "base/add_zero/1", "Addition with 0", "((0 + X) = X)");
+        assert_rule_eq(&rules[2], "base/mul_one", "Multiplication with 1", "((X * 1) = X)");
+        assert_eq!(ruleset.auto_rule_ids, vec!["base/mul_one"]);
+        
+        let ctx = &ruleset.context;
+        assert!(ctx.handle_numerics);
+        assert!(ctx.binary_ops.contains(&"&".to_string()) && ctx.binary_ops.contains(&"+".to_string()));
+        assert!(ctx.is_commutative("&"));
+        assert_eq!(ctx.parameters, vec!["x"]);
+        assert_eq!(ruleset.normalization_function, Some("algebra".to_string()));
+    }
+    
+    #[test]
+    fn from_file() {
+        // `algebra_simplify` imports `algebra` and makes some of its rules auto
+        let loader = rule::FileRulesetLoader::new("rules");
+        let simplify = std::fs::read_to_string("rules/algebra_simplify.json").unwrap();
+        let simplify = rule::parse_ruleset_from_json_with_loader(&simplify, &loader).unwrap();
+        let algebra = std::fs::read_to_string("rules/algebra.json").unwrap();
+        let algebra = rule::parse_ruleset_from_json(&algebra).unwrap();
+        assert_eq!(simplify.rule_ids, algebra.rule_ids);
+        assert_eq!(simplify.context, algebra.context);
+        assert_eq!(simplify.normalization, algebra.normalization);
+        assert!(algebra.auto_rule_ids.is_empty());
+        assert!(simplify.auto_rule_ids.contains(&"algebra/add_zero/0".to_string()));
+        
+        // without a loader the import can't be resolved
+        let str = std::fs::read_to_string("rules/algebra_simplify.json").unwrap();
+        assert!(matches!(rule::parse_ruleset_from_json(&str), Err(rule::ParserError::ImportNotFound(name)) if name == "algebra"));
+    }
+    
+    #[test]
+    fn error() {
+        let str = r#"{ "name": "c", "imports": [{"name": "a"}], "rules": [] }"#;
+        let err = rule::parse_ruleset_from_json_with_loader(str, &loader()).err().unwrap();
+        assert_eq!(err.to_string(), "import cycle: a -> b -> a");
+        
+        let str = r#"{ "name": "c", "imports": [{"name": "unknown"}], "rules": [] }"#;
+        let err = rule::parse_ruleset_from_json(str).err().unwrap();
+        assert!(matches!(err, rule::ParserError::ImportNotFound(name) if name == "unknown"));
+    }
 }
@@ -0,0 +1,88 @@
+#![allow(clippy::needless_return)]
+
+use equaio::rule::{self, RuleSet};
+use equaio::search::{self, SearchLimits, SearchError};
+use equaio::expression::Context;
+use equaio::parser::parser;
+use equaio::vec_strings;
+
+fn get_ruleset(name: &str) -> RuleSet {
+    let rulestr = std::fs::read_to_string(format!("rules/{}.json", name)).unwrap();
+    return rule::parse_ruleset_from_json_with_loader(&rulestr, &rule::FileRulesetLoader::new("rules")).unwrap();
+}
+
+fn get_ctx(ruleset: &RuleSet, params: Vec<String>) -> Context {
+    return ruleset.context.clone().add_params(params);
+}
+
+#[cfg(test)]
+mod search_test {
+    use super::*;
+    
+    #[test]
+    fn logic() {
+        let ruleset = get_ruleset("logic");
+        let ctx = get_ctx(&ruleset, vec_strings!["A", "B"]);
+        let start = parser::to_expression("(~A | B) & (A | B)", &ctx).unwrap();
+        let goal = parser::to_expression("B", &ctx).unwrap();
+        let seq = search::search(&ruleset, &start, &goal, &SearchLimits::default()).unwrap();
+        assert_eq!(seq.history.first().unwrap().expr, start);
+        assert_eq!(seq.last_expression(), &goal);
+        assert_eq!(seq.history.len(), 4);
+    }
+    
+    #[test]
+    fn algebra() {
+        let ruleset = get_ruleset("algebra");
+        let ctx = get_ctx(&ruleset, vec_strings!["x", "y"]);
+        let start = parser::to_expression("x * 1 + 0 * y", &ctx).unwrap();
+        let goal = parser::to_expression("x", &ctx).unwrap();
+        let seq = search::search(&ruleset, &start, &goal, &SearchLimits::default()).unwrap();
+        let actions = seq.history.iter().map(|line| line.action.to_string()).collect::<Vec<_>>();
+        assert_eq!(actions, vec!["Introduce", "Multiplication with 1", "Multiplication with 0", "Addition with 0"]);
+    }
+    
+    #[test]
+    fn already_at_goal() {
+        let ruleset = get_ruleset("logic");
+        let ctx = get_ctx(&ruleset, vec_strings!["A"]);
+        let start = parser::to_expression("A", &ctx).unwrap();
+        let seq = search::search(&ruleset, &start, &start, &SearchLimits::default()).unwrap();
+        assert_eq!(seq.history.len(), 1);
+    }
+    
+    #[test]
+    fn limits() {
+        let ruleset = get_ruleset("logic");
+        let ctx = get_ctx(&ruleset, vec_strings!["A", "B"]);
+        let start = parser::to_expression("(~A | B) & (A | B)", &ctx).unwrap();
+        let goal = parser::to_expression("B", &ctx).unwrap();
+        
+        let limits = SearchLimits { max_depth: 2, ..Default::default() };
+        let result = search::search(&ruleset, &start, &goal, &limits);
+        assert_eq!(result.err(), Some(SearchError::GoalNotFound));
+        
+        let limits = SearchLimits { max_nodes: 2, ..Default::default() };
+        let result = search::search(&ruleset, &start, &goal, &limits);
+        assert_eq!(result.err(), Some(SearchError::NodeLimitReached));
+    }
+    
+    #[test]
+    fn commuted_expressions_are_different_nodes() {
+        let rulestr = r#"{
+            "name": "commute",
+            "context": { "base": "arithmetic" },
+            "rules": [
+                { "id": "swap", "expr": "A + B = B + A" },
+                { "id": "add_zero", "expr": "X + 0 = X" }
+            ]
+        }"#;
+        let ruleset = rule::parse_ruleset_from_json(rulestr).unwrap();
+        let ctx = get_ctx(&ruleset, vec_strings!["x"]);
+        let start = parser::to_expression("0 + x", &ctx).unwrap();
+        let goal = parser::to_expression("x", &ctx).unwrap();
+        let seq = search::search(&ruleset, &start, &goal, &SearchLimits::default()).unwrap();
+        let exprs = seq.history.iter().map(|line| line.expr.to_string(true)).collect::<Vec<_>>();
+        assert_eq!(exprs, vec!["(0 + x)", "(x + 0)", "x"]);
+    }
+}
@@ -0,0 +1,78 @@
+#![allow(clippy::needless_return)]
+
+use equaio::algebra::AlgebraError;
+use equaio::arithmetic::get_arithmetic_ctx;
+use equaio::expression::Expression;
+use equaio::number::{Number, Rational};
+use equaio::parser::parser;
+use equaio::vec_strings;
+use equaio::worksheet::ExpressionSequence;
+
+fn parse(s: &str) -> Expression {
+    let ctx = get_arithmetic_ctx().add_params(vec_strings!["x", "y"]);
+    return parser::to_expression(s, &ctx).unwrap();
+}
+
+#[cfg(test)]
+mod solver_test {
+    use super::*;
+
+    #[test]
+    fn linear_coefficients() {
+        assert_eq!(parse("2 * x - 1").linear_coefficients("x"), Some((Number::integer(2), Number::integer(-1))));
+        assert_eq!(parse("3 * (x + 2)").linear_coefficients("x"), Some((Number::integer(3), Number::integer(6))));
+        assert_eq!(parse("x / 2 + x").linear_coefficients("x"), Some((Number::Rational(Rational::new(3, 2).unwrap()), Number::integer(0))));
+        assert_eq!(parse("5").linear_coefficients("x"), Some((Number::integer(0), Number::integer(5))));
+        assert_eq!(parse("x * x").linear_coefficients("x"), None);
+        assert_eq!(parse("1 / x").linear_coefficients("x"), None);
+        assert_eq!(parse("x + y").linear_coefficients("x"), None);
+    }
+
+    #[test]
+    fn solve_linear() {
+        let cases = [
+            ("2 * x - 1 = 3", "x = 2"),
+            ("3 * x + 2 = x + 6", "x = 2"),
+            ("5 = x + 2", "x = 3"),
+            ("x + x = 3", "x = 3 / 2"),
+            ("7 - x = 2", "x = 5"),
+        ];
+        for (input, expected) in cases {
+            let seq = parse(input).solve_linear_for("x").unwrap();
+            assert_eq!(seq.history.first().unwrap().expr, parse(input));
+            assert_eq!(seq.last_expression(), &parse(expected));
+            // every step is replayable
+            let ctx = seq.get_context().clone();
+            assert_eq!(ExpressionSequence::from(seq).verify_in_context(ctx), Ok(()), "{}", input);
+        }
+        
+        // divided by -1, ex: `-x / -1 = 5 / -1`
+        let seq = parse("-x = 5").solve_linear_for("x").unwrap();
+        assert_eq!(seq.last_expression().to_string(true), "(x = -5)");
+        assert!(seq.history.iter().any(|line| line.action.to_string() == "Apply /-1 to both side"));
+        let ctx = seq.get_context().clone();
+        assert_eq!(ExpressionSequence::from(seq).verify_in_context(ctx), Ok(()));
+    }
+
+    #[test]
+    fn solve_linear_steps() {
+        let seq = parse("2 * x - 1 = 3").solve_linear_for("x").unwrap();
+        let actions = seq.history.iter().map(|line| line.action.to_string()).collect::<Vec<String>>();
+        assert_eq!(actions, vec_strings![
+            "Introduce",
+            "Apply +1 to both side",
+            "Calculate -1 + 1 = 0",
+            "Calculate 3 + 1 = 4",
+            "Apply /2 to both side",
+            "Calculate 4 / 2 = 2",
+            "Expand"
+        ]);
+    }
+
+    #[test]
+    fn solve_linear_error() {
+        assert!(matches!(parse("x * x = 4").solve_linear_for("x"), Err(AlgebraError::NotLinear)));
+        assert!(matches!(parse("x + 1 = x + 2").solve_linear_for("x"), Err(AlgebraError::NoUniqueSolution)));
+        assert!(matches!(parse("y + 1 = 2").solve_linear_for("x"), Err(AlgebraError::NotLinear)));
+    }
+}
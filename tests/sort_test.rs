@@ -0,0 +1,53 @@
+#![allow(clippy::needless_return)]
+
+use equaio::arithmetic::get_arithmetic_ctx;
+use equaio::expression::{Context, Expression};
+use equaio::parser::parser;
+use equaio::sort::Sort;
+use equaio::vec_strings;
+
+fn ctx() -> Context {
+    let mut ctx = get_arithmetic_ctx().add_params(vec_strings!["p", "q", "a"]);
+    ctx.binary_ops.push("&".to_string());
+    ctx.binary_ops.push("|".to_string());
+    return ctx.set_sort("&", Sort::Boolean).set_sort("p", Sort::Boolean).set_sort("a", Sort::Matrix);
+}
+
+fn parse(s: &str) -> Expression {
+    return parser::to_expression(s, &ctx()).unwrap();
+}
+
+#[cfg(test)]
+mod sort {
+    use super::*;
+
+    #[test]
+    fn subsort() {
+        assert!(Sort::Integer.is_subsort_of(&Sort::Real));
+        assert!(Sort::Real.is_subsort_of(&Sort::Real));
+        assert!(!Sort::Real.is_subsort_of(&Sort::Integer));
+        assert!(!Sort::Boolean.is_subsort_of(&Sort::Real));
+    }
+
+    #[test]
+    fn infer() {
+        let ctx = ctx();
+        assert_eq!(ctx.sort_of(&parse("2")), Some(Sort::Integer));
+        assert_eq!(ctx.sort_of(&parse("2.5")), Some(Sort::Real));
+        assert_eq!(ctx.sort_of(&parse("p")), Some(Sort::Boolean));
+        assert_eq!(ctx.sort_of(&parse("a")), Some(Sort::Matrix));
+        assert_eq!(ctx.sort_of(&parse("p & q")), Some(Sort::Boolean));
+        assert_eq!(ctx.sort_of(&parse("q")), None);
+        assert_eq!(ctx.sort_of(&parse("q + 1")), Some(Sort::Real));
+        assert_eq!(ctx.sort_of(&parse("sqrt(4)")), Some(Sort::Real));
+    }
+
+    #[test]
+    fn infer_from_children() {
+        let ctx = ctx();
+        // `|` has no sort
+        assert_eq!(ctx.sort_of(&parse("p | (p & q)")), Some(Sort::Boolean));
+        assert_eq!(ctx.sort_of(&parse("p | q")), None);
+        assert_eq!(ctx.sort_of(&parse("p | a")), None);
+    }
+}
@@ -1,4 +1,7 @@
+#![allow(clippy::needless_return)]
+
 use equaio::utils;
+use num_bigint::BigInt;
 
 #[cfg(test)]
 mod function {
@@ -6,17 +9,25 @@ mod function {
     
     #[test]
     fn gcd () {
-        assert_eq!(utils::gcd(12, 15), 3);
-        assert_eq!(utils::gcd(15, 12), 3);
-        assert_eq!(utils::gcd(12, 0), 12);
-        assert_eq!(utils::gcd(0, 12), 12);
+        assert_eq!(utils::gcd(&BigInt::from(12), &BigInt::from(15)), BigInt::from(3));
+        assert_eq!(utils::gcd(&BigInt::from(15), &BigInt::from(12)), BigInt::from(3));
+        assert_eq!(utils::gcd(&BigInt::from(12), &BigInt::from(0)), BigInt::from(12));
+        assert_eq!(utils::gcd(&BigInt::from(0), &BigInt::from(12)), BigInt::from(12));
     }
     
     #[test]
     fn lcm () {
-        assert_eq!(utils::lcm(12, 15), 60);
-        assert_eq!(utils::lcm(15, 12), 60);
-        assert_eq!(utils::lcm(12, 0), 0);
-        assert_eq!(utils::lcm(0, 12), 0);
+        assert_eq!(utils::lcm(&BigInt::from(12), &BigInt::from(15)), BigInt::from(60));
+        assert_eq!(utils::lcm(&BigInt::from(15), &BigInt::from(12)), BigInt::from(60));
+        assert_eq!(utils::lcm(&BigInt::from(12), &BigInt::from(0)), BigInt::from(0));
+        assert_eq!(utils::lcm(&BigInt::from(0), &BigInt::from(12)), BigInt::from(0));
+    }
+
+    #[test]
+    fn gcd_large () {
+        let a = "123456789012345678901234567890".parse::<BigInt>().unwrap();
+        let b = &a * BigInt::from(7);
+        assert_eq!(utils::gcd(&a, &b), a);
+        assert_eq!(utils::lcm(&a, &b), b);
     }
 }
@@ -0,0 +1,99 @@
+#![allow(clippy::needless_return)]
+
+use equaio::rule;
+use equaio::validation::ValidationError;
+
+fn validate(rules: &str) -> Result<(), Vec<ValidationError>> {
+    let str = format!(r#"{{ "name": "test", "context": {{ "base": "arithmetic" }}, "rules": [ {} ] }}"#, rules);
+    return rule::parse_ruleset_from_json(&str).unwrap().validate();
+}
+
+#[cfg(test)]
+mod validation_test {
+    use super::*;
+
+    #[test]
+    fn valid() {
+        let rules = r#"
+            { "id": "add_zero", "expr": "X + 0 = X", "auto": true },
+            { "id": "distribute", "expr": "X * (A_i + ...) = (X * A_i) + ..." },
+            { "id": "div_self", "expr": "X / X = 1", "conditions": ["X != 0"] },
+            { "id": "div_self_2", "expr": "2 / 2 = 1" }
+        "#;
+        assert_eq!(validate(rules), Ok(()));
+        for name in ["algebra", "algebra_simplify", "logic"] {
+            let str = std::fs::read_to_string(format!("rules/{}.json", name)).unwrap();
+            let ruleset = rule::parse_ruleset_from_json_with_loader(&str, &rule::FileRulesetLoader::new("rules")).unwrap();
+            assert_eq!(ruleset.validate(), Ok(()), "{}", name);
+        }
+    }
+
+    #[test]
+    fn rules() {
+        let rules = r#"
+            { "id": "r", "expr": "X + 0 = X" },
+            { "id": "r", "expr": "X - 0 = X" },
+            { "id": "not_statement", "expr": "X + 0" },
+            { "id": "unbound", "expr": "X * 0 = Y + Z * Y" },
+            { "id": "variadic", "expr": "X * (A + ...) = (X * A) + ..." }
+        "#;
+        let errors = validate(rules).err().unwrap();
+        assert_eq!(errors, vec![
+            ValidationError::DuplicateId("test/r".to_string()),
+            ValidationError::NotAStatement("test/not_statement".to_string()),
+            ValidationError::UnboundVariables { id: "test/unbound".to_string(), variables: vec!["Y".to_string(), "Z".to_string()] },
+            ValidationError::MissingVariadicParam { id: "test/variadic".to_string(), expr: "A".to_string() },
+        ]);
+        assert_eq!(errors[2].to_string(), "test/unbound: variables not in the lhs: Y, Z");
+    }
+
+    #[test]
+    fn non_terminating() {
+        let rules = r#"
+            { "id": "swap", "expr": "A + B = B + A", "auto": true },
+            { "id": "add_zero", "expr": "X + 0 = 0 + X + 0", "auto": true },
+            { "id": "expand", "expr": "X * (Y + Z) = X * Y + X * Z", "auto": true },
+            { "id": "factor", "expr": "X * Y + X * Z = X * (Y + Z)", "auto": true },
+            { "id": "factor_manual", "expr": "X * Y - X * Z = X * (Y - Z)" }
+        "#;
+        let errors = validate(rules).err().unwrap();
+        assert_eq!(errors, vec![
+            ValidationError::NonTerminating(vec!["test/swap".to_string()]),
+            ValidationError::NonTerminating(vec!["test/add_zero".to_string()]),
+            ValidationError::NonTerminating(vec!["test/expand".to_string(), "test/factor".to_string()]),
+        ]);
+        assert_eq!(errors[2].to_string(), "auto rules can loop: test/expand -> test/factor");
+    }
+
+    #[test]
+    fn redundant() {
+        let str = r#"
+        {
+            "name": "test",
+            "context": { "base": "arithmetic" },
+            "sorts": {"n": "Integer", "x": "Real"},
+            "variations": [ {"expr": "A * B = B * A"} ],
+            "rules": [
+                { "id": "mul_sum_zero", "expr": "(A + B) * 0 = 0" },
+                { "id": "mul_zero", "expr": "X * 0 = 0" },
+                { "id": "zero_mul", "expr": "0 * Y = 0" },
+                { "id": "pow_zero", "expr": "N ^ 0 = 1", "sorts": {"N": "Integer"} },
+                { "id": "pow_zero_n", "expr": "n ^ 0 = 1" },
+                { "id": "pow_zero_x", "expr": "x ^ 0 = 1" }
+            ]
+        }
+        "#;
+        let errors = rule::parse_ruleset_from_json(str).unwrap().validate().err().unwrap();
+        assert_eq!(errors, vec![
+            // covered by a later, more general rule
+            ValidationError::Redundant { id: "test/mul_sum_zero/0".to_string(), covered_by: "test/mul_zero/0".to_string() },
+            ValidationError::Redundant { id: "test/mul_sum_zero/1".to_string(), covered_by: "test/mul_zero/1".to_string() },
+            // the same as a variation of `mul_zero`
+            ValidationError::Redundant { id: "test/zero_mul/0".to_string(), covered_by: "test/mul_zero/1".to_string() },
+            ValidationError::Redundant { id: "test/zero_mul/1".to_string(), covered_by: "test/mul_zero/0".to_string() },
+            // an `Integer` is also a `Real`, but `x` is not an `Integer`, so `pow_zero_x` is not redundant
+            ValidationError::Redundant { id: "test/pow_zero".to_string(), covered_by: "test/pow_zero_x".to_string() },
+            ValidationError::Redundant { id: "test/pow_zero_n".to_string(), covered_by: "test/pow_zero".to_string() },
+        ]);
+    }
+}
@@ -0,0 +1,160 @@
+#![allow(clippy::needless_return)]
+
+use equaio::rule::{self, RuleSet};
+use equaio::worksheet::{Worksheet, Action, ExpressionSequence, Provenance, WorkableExpressionSequence, WorksheetContext};
+use equaio::verify::{VerificationError, InvalidStep};
+use equaio::parser::{parser, parser_prefix};
+use equaio::address;
+use equaio::expression::Address;
+use equaio::vec_strings;
+
+#[cfg(test)]
+mod verify_test {
+    use super::*;
+
+    fn get_ruleset() -> RuleSet {
+        let rulestr = r#"{
+            "name": "simple",
+            "context": { "base": "arithmetic" },
+            "rules": [
+                { "id": "add_zero", "expr": "X + 0 = X", "auto": true },
+                { "id": "mul_one", "expr": "X * 1 = X", "label": "Multiplication with 1" }
+            ]
+        }"#;
+        return rule::parse_ruleset_from_json(rulestr).unwrap();
+    }
+
+    /// `(x + 0) * 1 = y * 1`, then (auto) `add_zero` at `[0,0]`, `mul_one` at `[0]` and `mul_one` at `[1]`
+    fn get_sequence(ruleset: &RuleSet) -> ExpressionSequence {
+        let mut ws = Worksheet::new();
+        ws.set_ruleset(ruleset.clone());
+        ws.set_expression_context(ws.get_expression_context().add_params(vec_strings!["x", "y"]));
+        ws.introduce_expression(parser::to_expression("(x + 0) * 1 = y * 1", &ws.get_expression_context()).unwrap()).unwrap();
+        let mut seq = ws.get(0).unwrap();
+        assert!(seq.apply_rule_at("simple/mul_one", &address![0]));
+        assert!(seq.apply_rule_at("simple/mul_one", &address![1]));
+        assert_eq!(seq.history.len(), 4);
+        ws.store(0, seq);
+        // received as JSON
+        let ws = Worksheet::from_json(&ws.to_json().unwrap()).unwrap();
+        return ws.get(0).unwrap().into();
+    }
+
+    fn invalid(index: usize, reason: InvalidStep) -> Result<(), VerificationError> {
+        return Err(VerificationError { index, reason });
+    }
+
+    #[test]
+    fn valid() {
+        let ruleset = get_ruleset();
+        let seq = get_sequence(&ruleset);
+        assert_eq!(seq.verify(&ruleset), Ok(()));
+
+        assert_eq!(ExpressionSequence::default().verify(&ruleset), invalid(0, InvalidStep::EmptySequence));
+    }
+
+    #[test]
+    fn tampered() {
+        let ruleset = get_ruleset();
+        let seq = get_sequence(&ruleset);
+        let ctx = ruleset.context.clone().add_params(vec_strings!["x", "y"]);
+
+        let mut tampered = seq.clone();
+        tampered.history[2].expr = parser::to_expression("x = y", &ctx).unwrap();
+        assert_eq!(tampered.verify(&ruleset), invalid(2, InvalidStep::ExpressionMismatch {
+            expected: "(x = (y * 1))".to_string(),
+            found: "(x = y)".to_string(),
+        }));
+
+        let mut tampered = seq.clone();
+        tampered.history[2].provenance = Provenance::Rule {
+            rule_id: "simple/mul_two".to_string(), address: address![0], bindings: Default::default(),
+        };
+        assert_eq!(tampered.verify(&ruleset), invalid(2, InvalidStep::UnknownRule("simple/mul_two".to_string())));
+
+        let mut tampered = seq.clone();
+        if let Provenance::Rule { address, .. } = &mut tampered.history[2].provenance { *address = address![0,0]; }
+        assert!(matches!(tampered.verify(&ruleset), Err(VerificationError { index: 2, reason: InvalidStep::RuleNotApplicable { .. } })));
+
+        let mut tampered = seq.clone();
+        if let Provenance::Rule { bindings, .. } = &mut tampered.history[3].provenance { bindings.clear(); }
+        assert_eq!(tampered.verify(&ruleset), invalid(3, InvalidStep::ProvenanceMismatch));
+
+        let mut tampered = seq.clone();
+        tampered.history[3].action = Action::ApplyRule("Addition with 0".to_string());
+        assert_eq!(tampered.verify(&ruleset), invalid(3, InvalidStep::ActionMismatch {
+            expected: "Multiplication with 1".to_string(),
+            found: "Addition with 0".to_string(),
+        }));
+
+        let mut tampered = seq.clone();
+        tampered.history[2].provenance = Provenance::Introduce;
+        assert_eq!(tampered.verify(&ruleset), invalid(2, InvalidStep::MisplacedIntroduce));
+    }
+
+    #[test]
+    fn auto_steps() {
+        let ruleset = get_ruleset();
+        let seq = get_sequence(&ruleset);
+
+        let mut missing = seq.clone();
+        missing.history.remove(1);
+        assert_eq!(missing.verify(&ruleset), invalid(1, InvalidStep::MissingAutoStep));
+
+        let mut unexpected = seq.clone();
+        unexpected.history[2].is_auto_generated = true;
+        assert_eq!(unexpected.verify(&ruleset), invalid(2, InvalidStep::UnexpectedAutoStep));
+
+        // the line of `add_zero` is not generated when it is not an auto rule
+        let mut ruleset_without_auto = ruleset.clone();
+        ruleset_without_auto.auto_rule_ids.clear();
+        assert_eq!(seq.verify(&ruleset_without_auto), invalid(1, InvalidStep::UnexpectedAutoStep));
+    }
+
+    #[test]
+    fn unverifiable() {
+        let ruleset = get_ruleset();
+        let mut seq = get_sequence(&ruleset);
+        let ctx = ruleset.context.clone().add_params(vec_strings!["x", "y"]);
+        seq.history.push(seq.history[3].clone());
+        seq.history[4].action = Action::ApplyAction("Add 1".to_string());
+        seq.history[4].expr = parser::to_expression("x + 1 = y + 1", &ctx).unwrap();
+        seq.history[4].provenance = Provenance::Action { addr_vec: vec![], action: "Add 1".to_string() };
+        assert_eq!(seq.verify(&ruleset), invalid(4, InvalidStep::Unverifiable("Add 1".to_string())));
+    }
+
+    #[test]
+    fn actions() {
+        let ruleset = rule::parse_ruleset_from_json(&std::fs::read_to_string("rules/algebra.json").unwrap()).unwrap();
+        // the ruleset sets the normalization and the possible actions functions by name
+        let mut ctx = WorksheetContext::default();
+        ctx.set_ruleset(ruleset.clone());
+        ctx.expression_context = ctx.expression_context.add_params(vec_strings!["x"]);
+
+        // solve 2*x - 1 = 3
+        let mut seq = WorkableExpressionSequence::new(ctx.clone());
+        let expr = parser_prefix::to_expression("=(-(*(2,x),1),3)", &ctx.expression_context).unwrap();
+        seq.push(Action::Introduce("Introduce".to_string()), expr).unwrap();
+        assert!(seq.try_apply_action_by_index(&vec![address![], address![0,1]], 0));
+        assert!(seq.try_apply_action_by_index(&vec![address![1]], 0));
+        assert!(seq.try_apply_action_by_index(&vec![address![0].sub(1)], 0));
+        assert!(seq.try_apply_action_by_index(&vec![address![0,1], address![0,0]], 0));
+        let seq: ExpressionSequence = seq.into();
+        assert_eq!(seq.history[4].action, Action::ApplyRule("Addition with 0".to_string()));
+        assert!(matches!(seq.history[4].provenance, Provenance::Rule { .. }));
+        assert_eq!(seq.verify_in_context(ctx.clone()), Ok(()));
+
+        let mut tampered = seq.clone();
+        tampered.history[1].expr = parser::to_expression("2 * x - 1 + 2 = 3 + 2", &ctx.expression_context).unwrap();
+        assert_eq!(tampered.verify_in_context(ctx.clone()), invalid(1, InvalidStep::ActionNotAvailable("Apply +1 to both side".to_string())));
+
+        let mut tampered = seq.clone();
+        if let Provenance::Action { addr_vec, .. } = &mut tampered.history[2].provenance { *addr_vec = vec![address![0]]; }
+        assert_eq!(tampered.verify_in_context(ctx.clone()), invalid(2, InvalidStep::ActionNotAvailable("Calculate 3 + 1 = 4".to_string())));
+
+        assert_eq!(seq.verify(&ruleset), Ok(()));
+        let mut ruleset_without_actions = ruleset.clone();
+        ruleset_without_actions.get_possible_actions_function = None;
+        assert_eq!(seq.verify(&ruleset_without_actions), invalid(1, InvalidStep::ActionNotAvailable("Apply +1 to both side".to_string())));
+    }
+}
@@ -1,5 +1,7 @@
+#![allow(clippy::needless_return)]
+
 use equaio::rule::{self, RuleSet};
-use equaio::worksheet::{Worksheet,Action,WorkableExpressionSequence};
+use equaio::worksheet::{Worksheet,Action,WorkableExpressionSequence,WorksheetContext,WorksheetError};
 use equaio::parser::{parser_prefix,parser};
 use equaio::arithmetic::{self, get_arithmetic_ctx};
 use equaio::address;
@@ -10,7 +12,7 @@ use equaio::vec_strings;
 fn get_algebra_ruleset(auto_simplify: bool) -> RuleSet {
     let filepath = if auto_simplify { "rules/algebra_simplify.json" } else { "rules/algebra.json" };
     let rulestr = std::fs::read_to_string(filepath).unwrap();
-    let ruleset = rule::parse_ruleset_from_json(&rulestr);
+    let ruleset = rule::parse_ruleset_from_json_with_loader(&rulestr, &rule::FileRulesetLoader::new("rules"));
     return ruleset.unwrap();
 }
 fn init_algebra_worksheet(variables: Vec<String>, auto_simplify: bool) -> Worksheet {
@@ -33,7 +35,7 @@ mod algebra_test {
     fn simple() {
         // solve 2*x - 1 = 3
         let mut ws = init_algebra_worksheet(vec_strings!["x"], false);
-        ws.introduce_expression(parser_prefix::to_expression("=(-(*(2,x),1),3)", &ws.get_expression_context()).unwrap());
+        ws.introduce_expression(parser_prefix::to_expression("=(-(*(2,x),1),3)", &ws.get_expression_context()).unwrap()).unwrap();
         
         let mut seq0 = ws.get_workable_expression_sequence(0).unwrap();
         let status = seq0.apply_simple_arithmetic_to_both_side(arithmetic::ArithmeticOperator::Add, &eb::constant("1"));
@@ -79,7 +81,7 @@ mod algebra_test {
     fn simple_auto() {
         // solve 2*x - 1 = 3
         let mut ws = init_algebra_worksheet(vec_strings!["x"], true);
-        ws.introduce_expression(parser_prefix::to_expression("=(-(*(2,x),1),3)", &ws.get_expression_context()).unwrap());
+        ws.introduce_expression(parser_prefix::to_expression("=(-(*(2,x),1),3)", &ws.get_expression_context()).unwrap()).unwrap();
         
         let mut seq0 = ws.get_workable_expression_sequence(0).unwrap();
         assert!(seq0.apply_simple_arithmetic_to_both_side(arithmetic::ArithmeticOperator::Add, &eb::constant("1")));
@@ -113,13 +115,14 @@ mod algebra_test {
 #[cfg(test)]
 mod get_possible_actions {
     use super::*;
+    use equaio::worksheet::Provenance;
     
     #[test]
     fn arithmetic_both_side_given_inner() {
         // x = 3 / (1-x)
         let mut ws = init_algebra_worksheet(vec_strings!["x"], false);
         let expr = parser_prefix::to_expression("=(x,/(3,-(1,x)))", &ws.get_expression_context()).unwrap();
-        ws.introduce_expression(expr.clone());
+        ws.introduce_expression(expr.clone()).unwrap();
         
         let seq0 = ws.get_workable_expression_sequence(0).unwrap();
         let actions = seq0.get_possible_actions(&vec![address![], address![1,1]]);
@@ -142,7 +145,7 @@ mod get_possible_actions {
     fn swap_comutative_binary_ops() {
         let mut ws = init_algebra_worksheet(vec_strings!["x"], false);
         let expr = parser::to_expression("1 + x = 3", &ws.get_expression_context()).unwrap();
-        ws.introduce_expression(expr);
+        ws.introduce_expression(expr).unwrap();
         
         let seq0 = ws.get_workable_expression_sequence(0).unwrap();
         let actions = seq0.get_possible_actions(&vec![address![0,0], address![0,1]]);
@@ -152,7 +155,7 @@ mod get_possible_actions {
         
         // nested
         let expr = parser::to_expression("(1 * x) + (x * 2) = 3", &ws.get_expression_context()).unwrap();
-        ws.introduce_expression(expr);
+        ws.introduce_expression(expr).unwrap();
         
         let seq1 = ws.get_workable_expression_sequence(1).unwrap();
         let actions = seq1.get_possible_actions(&vec![address![0,0,1], address![0,1,0]]);
@@ -165,7 +168,7 @@ mod get_possible_actions {
     fn swap_comutative_assoc_train() {
         let mut ws = init_algebra_worksheet(vec_strings!["x"], false);
         let expr = parser::to_expression("1 + x + 2 + 4 = 3", &ws.get_expression_context()).unwrap();
-        ws.introduce_expression(expr);
+        ws.introduce_expression(expr).unwrap();
         
         let seq0 = ws.get_workable_expression_sequence(0).unwrap();
         let actions = seq0.get_possible_actions(&vec![address![0,0], address![0,2]]);
@@ -175,7 +178,7 @@ mod get_possible_actions {
         
         // nested
         let expr = parser::to_expression("(1 * x) + (x * 2) + (5 * 6) = 3", &ws.get_expression_context()).unwrap();
-        ws.introduce_expression(expr);
+        ws.introduce_expression(expr).unwrap();
         
         let seq1 = ws.get_workable_expression_sequence(1).unwrap();
         let actions = seq1.get_possible_actions(&vec![address![0,1,1], address![0,2,0]]);
@@ -184,12 +187,34 @@ mod get_possible_actions {
         assert_eq!(actions[0].1.to_string(true), "(((1 * x) + (5 * 6) + (x * 2)) = 3)");
     }
     
+    #[test]
+    fn no_swap_non_comutative_ops() {
+        // `@` is associative but not commutative (ex: function composition)
+        let mut ws = Worksheet::new();
+        let mut ctx = get_arithmetic_ctx().add_params(vec_strings!["f", "g", "h"]);
+        ctx.binary_ops.push("@".to_string());
+        ctx.assoc_ops.push("@".to_string());
+        ws.set_expression_context(ctx);
+        ws.set_get_possible_actions_function(|expr,ctx,addr_vec| 
+            expression::get_possible_actions::basic(expr,ctx,addr_vec));
+        
+        ws.introduce_expression(parser::to_expression("f @ g", &ws.get_expression_context()).unwrap()).unwrap();
+        ws.introduce_expression(parser::to_expression("f @ g @ h", &ws.get_expression_context()).unwrap()).unwrap();
+        let seq0 = ws.get_workable_expression_sequence(0).unwrap();
+        assert!(seq0.get_possible_actions(&vec![address![0], address![1]]).is_empty());
+        let seq1 = ws.get_workable_expression_sequence(1).unwrap();
+        assert!(seq1.get_possible_actions(&vec![address![0], address![2]]).is_empty());
+        
+        let actions = seq1.get_possible_actions(&vec![address![0], address![1]]);
+        assert!(actions.iter().all(|(action,_)| action != &Action::ApplyAction("Reorder".to_string())));
+    }
+    
     
     #[test]
     fn get_action_at_virtual_address() {
         let mut ws = init_algebra_worksheet(vec_strings!["x"], false);
         let expr = parser::to_expression("0 + x + 1 + 2 = 3", &ws.get_expression_context()).unwrap();
-        ws.introduce_expression(expr);
+        ws.introduce_expression(expr).unwrap();
         
         let seq0 = ws.get_workable_expression_sequence(0).unwrap();
         let actions = seq0.get_possible_actions(&vec![address![0,0], address![0,1]]);
@@ -212,7 +237,7 @@ mod get_possible_actions {
     fn simple() {
         // solve 2*x - 1 = 3
         let mut ws = init_algebra_worksheet(vec_strings!["x"], false);
-        ws.introduce_expression(parser_prefix::to_expression("=(-(*(2,x),1),3)", &ws.get_expression_context()).unwrap());
+        ws.introduce_expression(parser_prefix::to_expression("=(-(*(2,x),1),3)", &ws.get_expression_context()).unwrap()).unwrap();
         
         let mut seq0 = ws.get_workable_expression_sequence(0).unwrap();
         assert!(seq0.try_apply_action_by_index(&vec![address![], address![0,1]], 0));
@@ -243,6 +268,9 @@ mod get_possible_actions {
             assert_eq!(line.action.to_string(), target_action_str.to_string());
             assert_eq!(line.expr.to_string(true), target_expr_str.to_string());
         }
+        assert_eq!(seq0.history[1].provenance, Provenance::Action {
+            addr_vec: vec![address![], address![0,1]], action: "Apply +1 to both side".to_string(),
+        });
     }
 }
 
@@ -253,7 +281,7 @@ mod misc {
     #[test]
     fn reset_to() {
         let mut ws = init_algebra_worksheet(vec_strings!["x"], false);
-        ws.introduce_expression(parser_prefix::to_expression("=(-(*(2,x),1),3)", &ws.get_expression_context()).unwrap());
+        ws.introduce_expression(parser_prefix::to_expression("=(-(*(2,x),1),3)", &ws.get_expression_context()).unwrap()).unwrap();
         
         let mut seq0 = ws.get_workable_expression_sequence(0).unwrap();
         assert!(seq0.try_apply_action_by_index(&vec![address![], address![0,1]], 0));
@@ -316,8 +344,8 @@ mod multiple_equation {
         // x - y = 1
         
         let mut ws = init_algebra_worksheet(vec_strings!["x","y"], false);
-        ws.introduce_expression(parser::to_expression("x + y = 3", &ws.get_expression_context()).unwrap());
-        ws.introduce_expression(parser::to_expression("x - y = 1", &ws.get_expression_context()).unwrap());
+        ws.introduce_expression(parser::to_expression("x + y = 3", &ws.get_expression_context()).unwrap()).unwrap();
+        ws.introduce_expression(parser::to_expression("x - y = 1", &ws.get_expression_context()).unwrap()).unwrap();
         
         let mut seq1 = ws.get_workable_expression_sequence(0).unwrap();
         assert!(seq1.try_apply_action_by_index(&vec![address![], address![0,1]], 0));
@@ -375,7 +403,7 @@ mod multiple_equation {
         seq_eq(&seq2, &target2);
         ws.store(1, seq2);
         
-        ws.introduce_from_label("Eq. 1");
+        ws.introduce_from_label("Eq. 1").unwrap();
         let mut seq3 = ws.get_workable_expression_sequence(2).unwrap();
         assert!(seq3.try_apply_action_by_index(&vec![address![1,1]], 0));
         assert!(seq3.try_apply_action_by_index(&vec![address![1]], 0));
@@ -431,7 +459,7 @@ mod logic {
     fn simplification() {
         // (~A | B) & (A | B) = B
         let mut ws = setup_ws(vec_strings!["A","B"]);
-        ws.introduce_expression(parser::to_expression("(~A | B) & (A | B)", &ws.get_expression_context()).unwrap());
+        ws.introduce_expression(parser::to_expression("(~A | B) & (A | B)", &ws.get_expression_context()).unwrap()).unwrap();
         
         let mut seq = ws.get_workable_expression_sequence(0).unwrap();
         assert!(seq.apply_rule_at("logic/factor_out_or/3", &address![]));
@@ -445,4 +473,437 @@ mod logic {
         ];
         seq_eq(&seq, &target);
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod suggest_next_steps {
+    use super::*;
+    
+    #[test]
+    fn follow_suggestions() {
+        let mut ws = init_algebra_worksheet(vec_strings!["x"], false);
+        let ctx = ws.get_expression_context();
+        ws.introduce_expression(parser::to_expression("x * 1 + 0 = 3", &ctx).unwrap()).unwrap();
+        let goal = parser::to_expression("x = 3", &ctx).unwrap();
+        
+        let mut seq = ws.get(0).unwrap();
+        let suggestions = seq.suggest_next_steps(Some(&goal));
+        assert!(!suggestions.is_empty());
+        for (action, addr, expr) in &suggestions {
+            let actions = seq.get_possible_actions(&vec![addr.clone()]);
+            assert!(actions.contains(&(action.clone(), expr.clone())));
+        }
+        
+        for _ in 0..2 {
+            let (action, _, expr) = seq.suggest_next_steps(Some(&goal)).first().unwrap().clone();
+            seq.push(action, expr).unwrap();
+        }
+        assert_eq!(seq.last_expression(), &goal);
+    }
+    
+    #[test]
+    fn without_goal() {
+        let mut ws = init_algebra_worksheet(vec_strings!["x"], false);
+        ws.introduce_expression(parser::to_expression("x * 1 = 3 + 0", &ws.get_expression_context()).unwrap()).unwrap();
+        let seq = ws.get(0).unwrap();
+        let suggestions = seq.suggest_next_steps(None);
+        let sizes = suggestions.iter().map(|(_, _, expr)| expr.size()).collect::<Vec<_>>();
+        assert!(sizes.windows(2).all(|w| w[0] <= w[1]));
+        assert_eq!(sizes.first(), Some(&5));
+    }
+}
+
+#[cfg(test)]
+mod normalization {
+    use super::*;
+    
+    #[test]
+    fn from_ruleset() {
+        let rulestr = r#"{
+            "name": "simple",
+            "context": { "base": "arithmetic" },
+            "normalization": [
+                {"expr": "X + 0 = X"},
+                {"expr": "X * 1 = X"}
+            ],
+            "normalization_function": "algebra",
+            "rules": []
+        }"#;
+        let mut ws = Worksheet::new();
+        ws.set_ruleset(rule::parse_ruleset_from_json(rulestr).unwrap());
+        ws.introduce_expression(parser::to_expression("(a + 0) * 1 + b = c", &ws.get_expression_context()).unwrap()).unwrap();
+        let seq = ws.get(0).unwrap();
+        assert_eq!(seq.last_expression().to_string(true), "((a + b) = c)");
+    }
+    
+    #[test]
+    fn non_terminating() {
+        let rulestr = r#"{
+            "name": "swap",
+            "context": { "base": "arithmetic" },
+            "normalization": [{"expr": "A * B = B * A"}],
+            "rules": []
+        }"#;
+        let mut ctx = WorksheetContext::default();
+        ctx.set_ruleset(rule::parse_ruleset_from_json(rulestr).unwrap());
+        let expr = parser::to_expression("a * b = c", &ctx.expression_context).unwrap();
+        let mut seq = WorkableExpressionSequence::new(ctx);
+        assert_eq!(seq.push(Action::Introduce("".to_string()), expr), Err(WorksheetError::NonTerminatingNormalization));
+        assert!(seq.history.is_empty());
+    }
+}
+
+#[cfg(test)]
+mod condition {
+    use super::*;
+    use equaio::worksheet::ExpressionSequence;
+    
+    fn setup_ws() -> Worksheet {
+        let rulestr = r#"{
+            "name": "simple",
+            "context": { "base": "arithmetic" },
+            "rules": [
+                { "id": "div_self", "expr": "X / X = 1", "conditions": ["X != 0"], "auto": true }
+            ]
+        }"#;
+        let mut ws = Worksheet::new();
+        ws.set_ruleset(rule::parse_ruleset_from_json(rulestr).unwrap());
+        ws.set_expression_context(ws.get_expression_context().add_params(vec_strings!["x"]));
+        return ws;
+    }
+    
+    #[test]
+    fn assumptions() {
+        let mut ws = setup_ws();
+        ws.introduce_expression(parser::to_expression("y = x / x", &ws.get_expression_context()).unwrap()).unwrap();
+        let mut seq = ws.get(0).unwrap();
+        // not applied automatically, because `x != 0` can't be decided
+        assert_eq!(seq.history.len(), 1);
+        assert!(seq.apply_rule_at("simple/div_self", &address![1]));
+        let line = seq.history.last().unwrap();
+        assert_eq!(line.expr.to_string(true), "(y = 1)");
+        assert_eq!(line.assumptions.iter().map(|c| c.to_string(true)).collect::<Vec<_>>(), vec!["(x != 0)"]);
+    }
+    
+    #[test]
+    fn assumptions_of_possible_actions() {
+        let mut ws = setup_ws();
+        assert!(ws.set_get_possible_actions_function_by_name("basic"));
+        ws.introduce_expression(parser::to_expression("y = x / x", &ws.get_expression_context()).unwrap()).unwrap();
+        let mut seq = ws.get(0).unwrap();
+        let index = seq.get_possible_actions(&vec![address![1]]).iter()
+            .position(|(_, expr)| expr.to_string(true) == "(y = 1)").unwrap();
+        assert!(seq.try_apply_action_by_index(&vec![address![1]], index));
+        let line = seq.history.last().unwrap();
+        assert_eq!(line.expr.to_string(true), "(y = 1)");
+        assert_eq!(line.assumptions.iter().map(|c| c.to_string(true)).collect::<Vec<_>>(), vec!["(x != 0)"]);
+        
+        let ctx = seq.get_context().clone();
+        let seq: ExpressionSequence = seq.into();
+        assert_eq!(seq.verify_in_context(ctx), Ok(()));
+    }
+    
+    #[test]
+    fn violated() {
+        let mut ws = setup_ws();
+        ws.introduce_expression(parser::to_expression("y = 0 / 0", &ws.get_expression_context()).unwrap()).unwrap();
+        let mut seq = ws.get(0).unwrap();
+        assert!(!seq.apply_rule_at("simple/div_self", &address![1]));
+        ws.introduce_expression(parser::to_expression("y = 2 / 2", &ws.get_expression_context()).unwrap()).unwrap();
+        let seq = ws.get(1).unwrap();
+        // applied automatically
+        assert_eq!(seq.last_expression().to_string(true), "(y = 1)");
+        assert!(seq.history.iter().all(|line| line.assumptions.is_empty()));
+    }
+}
+
+#[cfg(test)]
+mod serialization {
+    use super::*;
+    use equaio::worksheet::{WorksheetError, WORKSHEET_FORMAT_VERSION};
+    use equaio::registry;
+    
+    fn setup_ws() -> Worksheet {
+        let mut ws = init_algebra_worksheet(vec_strings!["x"], false);
+        assert!(ws.set_normalization_function_by_name("algebra"));
+        assert!(ws.set_get_possible_actions_function_by_name("algebra"));
+        ws.introduce_expression(parser::to_expression("2 * x - 1 = 3", &ws.get_expression_context()).unwrap()).unwrap();
+        let mut seq = ws.get_workable_expression_sequence(0).unwrap();
+        assert!(seq.apply_simple_arithmetic_to_both_side(arithmetic::ArithmeticOperator::Add, &eb::constant("1")));
+        seq.label_expression("eq1".to_string(), 1);
+        ws.store_expression_sequence(0, seq);
+        return ws;
+    }
+    
+    #[test]
+    fn roundtrip() {
+        let ws = setup_ws();
+        let json = ws.to_json().unwrap();
+        let loaded = Worksheet::from_json(&json).unwrap();
+        assert!(loaded == ws);
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded.get_labelled_expression(), ws.get_labelled_expression());
+        
+        let mut seq = ws.get(0).unwrap();
+        let mut loaded_seq = loaded.get(0).unwrap();
+        assert_eq!(loaded_seq.history[1].label, Some("eq1".to_string()));
+        assert!(matches!(loaded_seq.history[1].action, Action::ApplyAction(_)));
+        
+        // the functions are restored from the registry
+        let addr_vec = vec![address![1]];
+        assert!(seq.get_possible_actions(&addr_vec) == loaded_seq.get_possible_actions(&addr_vec));
+        assert!(seq.try_apply_action_by_index(&addr_vec, 0));
+        assert!(loaded_seq.try_apply_action_by_index(&addr_vec, 0));
+        assert!(seq.history == loaded_seq.history);
+    }
+    
+    fn normalize_for_serialization_test(expr: &expression::Expression, ctx: &expression::Context) -> expression::Expression {
+        return expr.normalize_algebra(ctx);
+    }
+    
+    #[test]
+    fn functions_set_directly() {
+        // stored by their name in the registry
+        registry::register_normalization_function("serialization_test", normalize_for_serialization_test);
+        let mut ws = setup_ws();
+        ws.set_normalization_function(normalize_for_serialization_test);
+        let loaded = Worksheet::from_json(&ws.to_json().unwrap()).unwrap();
+        let seq = loaded.get(0).unwrap();
+        assert_eq!(seq.get_context().get_normalization_function_name(), Some(&"serialization_test".to_string()));
+        assert!(!seq.get_possible_actions(&vec![address![1]]).is_empty());
+        
+        // not stored at all, so the worksheet can't be serialized
+        ws.set_normalization_function(|expr,ctx| expr.normalize_algebra(ctx));
+        assert_eq!(ws.to_json(), Err(WorksheetError::UnregisteredFunction("normalization".to_string())));
+    }
+    
+    #[test]
+    fn unsupported_version() {
+        let json = setup_ws().to_json().unwrap();
+        let version_str = format!("\"version\":{}", WORKSHEET_FORMAT_VERSION);
+        assert!(json.contains(&version_str));
+        let json = json.replace(&version_str, "\"version\":999");
+        let result = Worksheet::from_json(&json);
+        assert!(matches!(result, Err(WorksheetError::UnsupportedVersion(999))));
+    }
+    
+    #[test]
+    fn invalid_json() {
+        let result = Worksheet::from_json("{\"version\":1}");
+        assert!(matches!(result, Err(WorksheetError::InvalidJSON(_))));
+        let result = Worksheet::from_json("not a worksheet");
+        assert!(matches!(result, Err(WorksheetError::InvalidJSON(_))));
+    }
+}
+
+#[cfg(test)]
+mod auto_rules {
+    use super::*;
+    use equaio::worksheet::WorksheetError;
+    
+    #[test]
+    fn non_terminating() {
+        let rulestr = r#"{
+            "name": "loop",
+            "context": { "base": "arithmetic" },
+            "rules": [
+                { "id": "add_zero", "expr": "X + 0 = X", "auto": true },
+                { "id": "swap", "expr": "A * B = B * A", "auto": true }
+            ]
+        }"#;
+        let mut ctx = equaio::worksheet::WorksheetContext::default();
+        ctx.set_ruleset(rule::parse_ruleset_from_json(rulestr).unwrap());
+        ctx.expression_context = ctx.expression_context.add_params(vec_strings!["x", "y"]);
+        let mut seq = WorkableExpressionSequence::new(ctx);
+        
+        let expr = parser::to_expression("x + 0", &seq.get_context().expression_context).unwrap();
+        assert!(seq.push(Action::Introduce("Introduce".to_string()), expr).is_ok());
+        assert_eq!(seq.last_expression().to_string(true), "x");
+        assert_eq!(seq.history.len(), 2);
+        
+        let expr = parser::to_expression("(x + 0) * y", &seq.get_context().expression_context).unwrap();
+        let result = seq.push(Action::Introduce("Introduce".to_string()), expr);
+        assert_eq!(result, Err(WorksheetError::NonTerminatingAutoRules(vec_strings!["loop/swap", "loop/swap"])));
+        assert_eq!(result.unwrap_err().to_string(), "auto rules don't terminate: loop/swap -> loop/swap");
+        // the pushed line is dropped with the auto generated lines
+        assert_eq!(seq.history.len(), 2);
+        assert_eq!(seq.last_expression().to_string(true), "x");
+        
+        let expr = parser::to_expression("(x + 0) * y", &seq.get_context().expression_context);
+        assert!(!seq.try_push(Action::ApplyAction("Multiply".to_string()), expr));
+        assert_eq!(seq.history.len(), 2);
+        
+        let mut ws = Worksheet::new();
+        ws.set_ruleset(rule::parse_ruleset_from_json(rulestr).unwrap());
+        ws.set_expression_context(ws.get_expression_context().add_params(vec_strings!["x", "y"]));
+        let expr = parser::to_expression("(x + 0) * y", &ws.get_expression_context()).unwrap();
+        assert!(matches!(ws.introduce_expression(expr), Err(WorksheetError::NonTerminatingAutoRules(_))));
+        assert!(ws.get(0).is_none());
+        assert_eq!(ws.introduce_from_label("Eq. 1"), Err(WorksheetError::UnknownLabel("Eq. 1".to_string())));
+    }
+}
+
+#[cfg(test)]
+mod history {
+    use super::*;
+    
+    fn setup_ws() -> Worksheet {
+        let rulestr = r#"{
+            "name": "simple",
+            "context": { "base": "arithmetic" },
+            "rules": [
+                { "id": "add_zero", "expr": "X + 0 = X", "auto": true },
+                { "id": "swap", "expr": "A + B = B + A" }
+            ]
+        }"#;
+        let mut ws = Worksheet::new();
+        ws.set_ruleset(rule::parse_ruleset_from_json(rulestr).unwrap());
+        ws.set_expression_context(ws.get_expression_context().add_params(vec_strings!["x", "y"]));
+        ws.introduce_expression(parser::to_expression("x + y", &ws.get_expression_context()).unwrap()).unwrap();
+        return ws;
+    }
+    
+    fn push_add_zero(seq: &mut WorkableExpressionSequence) {
+        let expr = eb::binary("+", seq.last_expression().clone(), eb::constant("0"));
+        seq.push(Action::ApplyAction("Add 0".to_string()), expr).unwrap();
+    }
+    
+    fn history_strings(seq: &WorkableExpressionSequence) -> Vec<String> {
+        return seq.history.iter().map(|line| line.expr.to_string(true)).collect();
+    }
+    
+    #[test]
+    fn undo_redo() {
+        let ws = setup_ws();
+        let mut seq = ws.get(0).unwrap();
+        assert!(seq.apply_rule_at("simple/swap", &address![]));
+        assert!(seq.apply_rule_at("simple/swap", &address![]));
+        assert_eq!(history_strings(&seq), vec!["(x + y)", "(y + x)", "(x + y)"]);
+        
+        assert!(seq.undo());
+        assert!(seq.undo());
+        assert!(!seq.undo());
+        assert_eq!(history_strings(&seq), vec!["(x + y)"]);
+        assert!(seq.redo());
+        assert!(seq.redo());
+        assert!(!seq.redo());
+        assert_eq!(history_strings(&seq), vec!["(x + y)", "(y + x)", "(x + y)"]);
+        
+        // the auto generated lines are undone with the line before them
+        assert!(seq.undo());
+        push_add_zero(&mut seq);
+        assert_eq!(history_strings(&seq), vec!["(x + y)", "(y + x)", "((y + x) + 0)", "(y + x)"]);
+        assert!(!seq.redo());
+        assert!(seq.undo());
+        assert_eq!(history_strings(&seq), vec!["(x + y)", "(y + x)"]);
+    }
+    
+    #[test]
+    fn branches() {
+        let mut ws = setup_ws();
+        let mut seq = ws.get(0).unwrap();
+        assert!(seq.apply_rule_at("simple/swap", &address![]));
+        assert!(seq.apply_rule_at("simple/swap", &address![]));
+        seq.reset_to(1);
+        push_add_zero(&mut seq);
+        assert_eq!(seq.branches(), vec![2, 4]);
+        assert_eq!(seq.current_branch(), Some(4));
+        
+        assert!(seq.switch_branch(2));
+        assert_eq!(history_strings(&seq), vec!["(x + y)", "(y + x)", "(x + y)"]);
+        assert!(!seq.switch_branch(5));
+        
+        let comparison = seq.compare_branches(2, 4).unwrap();
+        assert_eq!(comparison.common_len, 2);
+        assert_eq!(comparison.only_in_first.len(), 1);
+        assert_eq!(comparison.only_in_second.len(), 2);
+        assert!(!comparison.same_result);
+        
+        // fork from the first line
+        seq.reset_to(0);
+        push_add_zero(&mut seq);
+        assert_eq!(seq.branches(), vec![2, 4, 6]);
+        let comparison = seq.compare_branches(2, 6).unwrap();
+        assert_eq!(comparison.common_len, 1);
+        assert!(comparison.same_result);
+        
+        // every branch is stored
+        seq.label_expression("eq1".to_string(), 1);
+        assert!(seq.switch_branch(4));
+        ws.store(0, seq);
+        let loaded = Worksheet::from_json(&ws.to_json().unwrap()).unwrap();
+        let seq = loaded.get(0).unwrap();
+        assert_eq!(seq.branches(), vec![2, 4, 6]);
+        assert_eq!(seq.current_branch(), Some(4));
+        assert_eq!(history_strings(&seq), vec!["(x + y)", "(y + x)", "((y + x) + 0)", "(y + x)"]);
+        assert_eq!(loaded.get_labelled_expression().len(), 1);
+    }
+}
+
+#[cfg(test)]
+mod provenance {
+    use super::*;
+    use equaio::worksheet::Provenance;
+    use std::collections::HashMap;
+    
+    fn rule_provenance(rule_id: &str, address: Address, bindings: Vec<(&str, &str)>, ws: &Worksheet) -> Provenance {
+        let ctx = ws.get_expression_context();
+        let bindings = bindings.into_iter()
+            .map(|(var, expr)| (var.to_string(), parser::to_expression(expr, &ctx).unwrap()))
+            .collect::<HashMap<_, _>>();
+        return Provenance::Rule { rule_id: rule_id.to_string(), address, bindings };
+    }
+    
+    #[test]
+    fn rules() {
+        let rulestr = r#"{
+            "name": "simple",
+            "context": { "base": "arithmetic" },
+            "get_possible_actions_function": "basic",
+            "rules": [
+                { "id": "add_zero", "expr": "X + 0 = X", "auto": true },
+                { "id": "mul_one", "expr": "X * 1 = X", "label": "Multiplication with 1" },
+                { "id": "mul_one_again", "expr": "X * 1 = X", "label": "Multiplication with 1 again" }
+            ]
+        }"#;
+        let mut ws = Worksheet::new();
+        ws.set_ruleset(rule::parse_ruleset_from_json(rulestr).unwrap());
+        ws.set_expression_context(ws.get_expression_context().add_params(vec_strings!["x", "y"]));
+        ws.introduce_expression(parser::to_expression("(x + 0) * 1 = y * 1", &ws.get_expression_context()).unwrap()).unwrap();
+        let mut seq = ws.get(0).unwrap();
+        assert!(seq.apply_rule_at("simple/mul_one", &address![0]));
+        // the possible actions of the rules, at `[1]`: `mul_one` then `mul_one_again`
+        assert!(seq.try_apply_action_by_index(&vec![address![1]], 1));
+        
+        let provenances = seq.history.iter().map(|line| line.provenance.clone()).collect::<Vec<_>>();
+        assert_eq!(provenances, vec![
+            Provenance::Introduce,
+            rule_provenance("simple/add_zero", address![0,0], vec![("X", "x")], &ws),
+            rule_provenance("simple/mul_one", address![0], vec![("X", "x")], &ws),
+            rule_provenance("simple/mul_one_again", address![1], vec![("X", "y")], &ws),
+        ]);
+        assert!(seq.history[1].is_auto_generated);
+        assert_eq!(seq.last_expression().to_string(true), "(x = y)");
+        
+        // stored with the worksheet
+        ws.store(0, seq.clone());
+        let loaded = Worksheet::from_json(&ws.to_json().unwrap()).unwrap();
+        assert!(loaded.get(0).unwrap().history == seq.history);
+    }
+    
+    #[test]
+    fn normalization() {
+        let mut ws = init_algebra_worksheet(vec_strings!["x", "y"], false);
+        assert!(ws.set_normalization_function_by_name("algebra"));
+        ws.introduce_expression(parser::to_expression("x + y = 1", &ws.get_expression_context()).unwrap()).unwrap();
+        let mut seq = ws.get(0).unwrap();
+        assert_eq!(seq.history[0].unnormalized_expr, None);
+        
+        // not flattened into a train
+        let expr = eb::equation(eb::binary("+", eb::constant("x"), eb::binary("+", eb::constant("y"), eb::constant("1"))), eb::constant("1"));
+        seq.push(Action::ApplyAction("Add 1".to_string()), expr.clone()).unwrap();
+        let line = seq.history.last().unwrap();
+        assert_eq!(line.provenance, Provenance::Action { addr_vec: vec![], action: "Add 1".to_string() });
+        assert_eq!(line.unnormalized_expr, Some(expr));
+        assert_eq!(line.expr.to_string(true), "((x + y + 1) = 1)");
+    }
+}